@@ -1,20 +1,34 @@
 DROP TABLE IF EXISTS users,
+email_changes,
 follows,
 articles,
+article_tombstones,
+article_translations,
 tags,
 article_tags,
 article_favs,
-comments;
+claps,
+comments,
+comment_reactions,
+events;
 DROP INDEX IF EXISTS users_username_idx,
 users_email_idx,
+users_email_lower_idx,
 follows_follower_id_idx,
 follows_followee_id_idx,
 articles_slug_idx,
 articles_author_id_idx,
+articles_search_idx,
+article_tombstones_deleted_at_idx,
 tags_name_idx,
 article_tags_article_id_idx,
 article_tags_tag_id_idx,
 article_favs_article_id_idx,
 article_favs_user_id_idx,
+claps_user_id_idx,
 comments_author_id_idx,
-comments_article_id_idx;
\ No newline at end of file
+comments_article_id_idx,
+comments_pinned_article_id_idx,
+comments_parent_id_idx,
+comment_reactions_comment_id_idx,
+events_kind_created_at_idx;
\ No newline at end of file
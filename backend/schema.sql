@@ -1,22 +1,39 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
 CREATE TABLE IF NOT EXISTS users (
     id SERIAL NOT NULL PRIMARY KEY,
     username VARCHAR(255) NOT NULL UNIQUE,
     email VARCHAR(255) NOT NULL UNIQUE,
     hash VARCHAR(255) NOT NULL,
     bio TEXT,
-    image VARCHAR(255)
+    image VARCHAR(255),
+    is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    preferences JSONB NOT NULL DEFAULT '{}'
 );
+-- Databases created before these columns existed gain them here.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS preferences JSONB NOT NULL DEFAULT '{}';
 CREATE INDEX IF NOT EXISTS users_username_idx ON users (username);
 CREATE INDEX IF NOT EXISTS users_email_idx ON users (email);
+CREATE INDEX IF NOT EXISTS users_email_lower_idx ON users (LOWER(email));
+CREATE TABLE IF NOT EXISTS email_changes (
+    user_id INTEGER NOT NULL PRIMARY KEY,
+    email VARCHAR(255) NOT NULL,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
 CREATE TABLE IF NOT EXISTS follows (
     follower_id INTEGER NOT NULL,
     followee_id INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     PRIMARY KEY (follower_id, followee_id),
-    FOREIGN KEY (follower_id) REFERENCES users(id),
-    FOREIGN KEY (followee_id) REFERENCES users(id)
+    FOREIGN KEY (follower_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (followee_id) REFERENCES users(id) ON DELETE CASCADE
 );
 CREATE INDEX IF NOT EXISTS follows_follower_id_idx ON follows (follower_id);
 CREATE INDEX IF NOT EXISTS follows_followee_id_idx ON follows (followee_id);
+-- Follows and favorites made before this column existed all get the time it was added.
+ALTER TABLE follows ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
 CREATE TABLE IF NOT EXISTS articles (
     id SERIAL NOT NULL PRIMARY KEY,
     slug VARCHAR(255) NOT NULL UNIQUE,
@@ -26,10 +43,37 @@ CREATE TABLE IF NOT EXISTS articles (
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     author_id INTEGER NOT NULL,
-    FOREIGN KEY (author_id) REFERENCES users(id)
+    published BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Bumped by every update, so concurrent editors can't overwrite each other unknowingly.
+    version INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY (author_id) REFERENCES users(id) ON DELETE CASCADE
 );
+-- Existing articles were all public, so they count as published.
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS published BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
 CREATE INDEX IF NOT EXISTS articles_slug_idx ON articles (slug);
 CREATE INDEX IF NOT EXISTS articles_author_id_idx ON articles (author_id);
+CREATE INDEX IF NOT EXISTS articles_search_idx ON articles USING GIN (
+    to_tsvector('english', title || ' ' || description || ' ' || body)
+);
+-- Deleted articles, so clients syncing with `updated_since` learn about deletions too.
+CREATE TABLE IF NOT EXISTS article_tombstones (
+    id INTEGER NOT NULL PRIMARY KEY,
+    slug VARCHAR(255) NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS article_tombstones_deleted_at_idx ON article_tombstones (deleted_at);
+-- Cached output of the configured translator; stale once the article is edited after `translated_at`.
+CREATE TABLE IF NOT EXISTS article_translations (
+    article_id INTEGER NOT NULL,
+    lang VARCHAR(16) NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    body TEXT NOT NULL,
+    translated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (article_id, lang),
+    FOREIGN KEY (article_id) REFERENCES articles(id) ON DELETE CASCADE
+);
 CREATE TABLE IF NOT EXISTS tags (
     id SERIAL NOT NULL PRIMARY KEY,
     name VARCHAR(255) NOT NULL UNIQUE
@@ -47,12 +91,24 @@ CREATE INDEX IF NOT EXISTS article_tags_tag_id_idx ON article_tags (tag_id);
 CREATE TABLE IF NOT EXISTS article_favs (
     article_id INTEGER NOT NULL,
     user_id INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     PRIMARY KEY (article_id, user_id),
     FOREIGN KEY (article_id) REFERENCES articles(id) ON DELETE CASCADE,
-    FOREIGN KEY (user_id) REFERENCES users(id)
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
 );
+ALTER TABLE article_favs ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
 CREATE INDEX IF NOT EXISTS article_favs_article_id_idx ON article_favs (article_id);
 CREATE INDEX IF NOT EXISTS article_favs_user_id_idx ON article_favs (user_id);
+-- Claps add up per reader, up to a cap enforced by the API.
+CREATE TABLE IF NOT EXISTS claps (
+    article_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    count INTEGER NOT NULL CHECK (count > 0),
+    PRIMARY KEY (article_id, user_id),
+    FOREIGN KEY (article_id) REFERENCES articles(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS claps_user_id_idx ON claps (user_id);
 CREATE TABLE IF NOT EXISTS comments (
     id SERIAL NOT NULL PRIMARY KEY,
     body TEXT NOT NULL,
@@ -60,8 +116,86 @@ CREATE TABLE IF NOT EXISTS comments (
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     author_id INTEGER NOT NULL,
     article_id INTEGER NOT NULL,
-    FOREIGN KEY (author_id) REFERENCES users(id),
-    FOREIGN KEY (article_id) REFERENCES articles(id) ON DELETE CASCADE
+    pinned_at TIMESTAMPTZ,
+    -- The comment this one replies to, on the same article.
+    parent_id INTEGER,
+    FOREIGN KEY (author_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (article_id) REFERENCES articles(id) ON DELETE CASCADE,
+    FOREIGN KEY (parent_id) REFERENCES comments(id) ON DELETE SET NULL
 );
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS pinned_at TIMESTAMPTZ;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS parent_id INTEGER REFERENCES comments(id) ON DELETE SET NULL;
+-- Replies outlive their parent and move up to the top level. Databases from when deleting a
+-- comment took its replies along get the same constraint here.
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = 'comments'::regclass
+            AND conname = 'comments_parent_id_fkey'
+            AND confdeltype <> 'n'
+    ) THEN
+        ALTER TABLE comments DROP CONSTRAINT comments_parent_id_fkey,
+            ADD CONSTRAINT comments_parent_id_fkey
+                FOREIGN KEY (parent_id) REFERENCES comments(id) ON DELETE SET NULL;
+    END IF;
+END
+$$;
+CREATE INDEX IF NOT EXISTS comments_parent_id_idx ON comments (parent_id);
+-- At most one pinned comment per article.
+CREATE UNIQUE INDEX IF NOT EXISTS comments_pinned_article_id_idx ON comments (article_id) WHERE pinned_at IS NOT NULL;
 CREATE INDEX IF NOT EXISTS comments_author_id_idx ON comments (author_id);
-CREATE INDEX IF NOT EXISTS comments_article_id_idx ON comments (article_id);
\ No newline at end of file
+CREATE INDEX IF NOT EXISTS comments_article_id_idx ON comments (article_id);
+CREATE TABLE IF NOT EXISTS comment_reactions (
+    comment_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    emoji VARCHAR(32) NOT NULL,
+    PRIMARY KEY (comment_id, user_id, emoji),
+    FOREIGN KEY (comment_id) REFERENCES comments(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS comment_reactions_comment_id_idx ON comment_reactions (comment_id);
+CREATE TABLE IF NOT EXISTS events (
+    id SERIAL NOT NULL PRIMARY KEY,
+    user_id INTEGER,
+    kind VARCHAR(32) NOT NULL,
+    route VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS events_kind_created_at_idx ON events (kind, created_at);
+-- Databases created before deleting a user cascaded to their data get the same constraints here.
+-- Only constraints that don't cascade yet are replaced, so this is a no-op on later boots.
+-- Adding them NOT VALID and validating separately avoids blocking writes while existing rows are
+-- checked; they already satisfied the old constraints.
+DO $$
+DECLARE
+    fk RECORD;
+BEGIN
+    FOR fk IN
+        SELECT user_fks.table_name, user_fks.constraint_name, user_fks.column_name
+        FROM (VALUES
+            ('follows', 'follows_follower_id_fkey', 'follower_id'),
+            ('follows', 'follows_followee_id_fkey', 'followee_id'),
+            ('articles', 'articles_author_id_fkey', 'author_id'),
+            ('article_favs', 'article_favs_user_id_fkey', 'user_id'),
+            ('comments', 'comments_author_id_fkey', 'author_id'),
+            ('comment_reactions', 'comment_reactions_user_id_fkey', 'user_id')
+        ) AS user_fks (table_name, constraint_name, column_name)
+        LEFT JOIN pg_constraint
+            ON pg_constraint.conrelid = user_fks.table_name::regclass
+            AND pg_constraint.conname = user_fks.constraint_name
+        WHERE pg_constraint.confdeltype IS DISTINCT FROM 'c'
+    LOOP
+        EXECUTE format(
+            'ALTER TABLE %I DROP CONSTRAINT IF EXISTS %I, '
+            'ADD CONSTRAINT %I FOREIGN KEY (%I) REFERENCES users(id) ON DELETE CASCADE NOT VALID',
+            fk.table_name, fk.constraint_name, fk.constraint_name, fk.column_name
+        );
+        EXECUTE format(
+            'ALTER TABLE %I VALIDATE CONSTRAINT %I',
+            fk.table_name, fk.constraint_name
+        );
+    END LOOP;
+END
+$$;
\ No newline at end of file
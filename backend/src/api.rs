@@ -1,19 +1,29 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use axum::{
     extract::{Path, Query, State},
     headers::Authorization,
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
     Json, TypedHeader,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use jsonwebtoken::{DecodingKey, EncodingKey};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{Executor, PgPool};
+use sqlx::{types::Json as SqlJson, Executor, PgPool, Postgres, Transaction};
 use validator::Validate;
 
 use crate::{
     auth::{self, JWTToken},
-    error::{AppError, AppResult},
+    error::{validation_error, AppError, AppResult},
+    mail::Mailer,
+    markdown, toc, Config,
 };
 
 pub async fn prepare_db(pool: &PgPool) -> Result<(), sqlx::Error> {
@@ -32,6 +42,65 @@ pub async fn initialize(State(pool): State<PgPool>) -> AppResult<impl IntoRespon
     Ok(Json(json!({ "message": "ok" })))
 }
 
+/// How long `health` waits for the database before calling it degraded.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// For uptime monitors: 200 while the database answers, 503 when it errors or is too slow.
+pub async fn health(State(pool): State<PgPool>) -> impl IntoResponse {
+    match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, pool.execute("SELECT 1")).await {
+        Ok(Ok(_)) => (StatusCode::OK, Json(json!({ "status": "ok" }))),
+        Ok(Err(err)) => {
+            log::warn!("health check failed: {err}");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "degraded" })),
+            )
+        }
+        Err(_) => {
+            log::warn!("health check timed out after {HEALTH_CHECK_TIMEOUT:?}");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "degraded" })),
+            )
+        }
+    }
+}
+
+/// Times a trivial query, split into waiting for a pooled connection and the round trip itself,
+/// alongside the pool's occupancy. Tells a slow database apart from a starved pool. Admins only.
+pub async fn db_latency(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    require_admin(&pool, user_id, "diagnostics", "only available to admins").await?;
+
+    // Sampled before we take a connection ourselves.
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+
+    let started = Instant::now();
+    let mut conn = pool.acquire().await?;
+    let acquired = Instant::now();
+    conn.execute("SELECT 1").await?;
+    let finished = Instant::now();
+
+    Ok(Json(json!({
+        "latency": {
+            "acquireMs": (acquired - started).as_secs_f64() * 1000.0,
+            "queryMs": (finished - acquired).as_secs_f64() * 1000.0,
+            "totalMs": (finished - started).as_secs_f64() * 1000.0,
+        },
+        "pool": {
+            "size": size,
+            "idle": idle,
+            "inUse": size.saturating_sub(idle),
+        }
+    })))
+}
+
 pub type UserId = i32;
 
 #[derive(Debug, Default, Serialize)]
@@ -73,21 +142,30 @@ struct LoginUser {
     password: String,
 }
 
+/// Emails are stored trimmed and lowercased, so they compare case-insensitively.
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
 pub async fn login(
     State(pool): State<PgPool>,
     State(key): State<EncodingKey>,
-    Json(Login { user }): Json<Login>,
+    State(config): State<Config>,
+    Json(Login { mut user }): Json<Login>,
 ) -> AppResult<impl IntoResponse> {
+    user.email = normalize_email(&user.email);
     user.validate()?;
 
-    let mut conn = pool.acquire().await.unwrap();
-
+    // Compared with LOWER() so accounts stored before emails were normalized still match.
     let user_auth = sqlx::query_as!(
         UserAuth,
-        "SELECT *, NULL AS token FROM users WHERE email = $1",
+        r#"
+        SELECT id, username, email, hash, bio, image, NULL AS token
+        FROM users WHERE LOWER(email) = $1
+        "#,
         user.email
     )
-    .fetch_optional(&mut conn)
+    .fetch_optional(&pool)
     .await?;
 
     let Some(mut user_auth) = user_auth else {
@@ -107,7 +185,7 @@ pub async fn login(
             }))
         })?;
 
-    user_auth.token = Some(auth::generate_jwt(user_auth.id, &key)?);
+    user_auth.token = Some(auth::generate_jwt(user_auth.id, &key, config.token_ttl())?);
 
     Ok(Json(json!({ "user": user_auth })))
 }
@@ -157,47 +235,93 @@ struct RegistrationUser {
 pub async fn registration(
     State(pool): State<PgPool>,
     State(key): State<EncodingKey>,
-    Json(Registration { user }): Json<Registration>,
+    State(config): State<Config>,
+    Json(Registration { mut user }): Json<Registration>,
 ) -> AppResult<impl IntoResponse> {
+    user.email = normalize_email(&user.email);
     user.validate()?;
 
-    let hash = hash_password(user.password)?;
+    // The unique constraint is case-sensitive and older rows may not be normalized yet.
+    let email_taken = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(email) = $1) AS "exists!""#,
+        user.email
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    if email_taken {
+        Err(validation_error("email", "has already been taken"))?
+    }
 
-    let mut conn = pool.acquire().await.unwrap();
+    let hash = hash_password(user.password)?;
 
     let mut user_auth = sqlx::query_as!(
         UserAuth,
         r#"
         INSERT INTO users (username, email, hash)
         VALUES ($1, $2, $3)
-        RETURNING *, NULL AS token
+        RETURNING id, username, email, hash, bio, image, NULL AS token
         "#,
         user.username,
         user.email,
         hash
     )
-    .fetch_one(&mut conn)
-    .await?;
+    .fetch_one(&pool)
+    .await
+    .map_err(|err| match &err {
+        sqlx::Error::Database(db) if db.constraint() == Some("users_email_key") => {
+            validation_error("email", "has already been taken")
+        }
+        sqlx::Error::Database(db) if db.constraint() == Some("users_username_key") => {
+            validation_error("username", "has already been taken")
+        }
+        _ => err.into(),
+    })?;
+
+    user_auth.token = Some(auth::generate_jwt(user_auth.id, &key, config.token_ttl())?);
+
+    Ok(Json(json!({ "user": user_auth })))
+}
+
+/// How long after expiring a token can still be exchanged for a fresh one.
+const TOKEN_REFRESH_GRACE_DAYS: i64 = 7;
 
-    user_auth.token = Some(auth::generate_jwt(user_auth.id, &key)?);
+/// Exchanges a valid or recently expired token for a new one with a full lifetime.
+pub async fn refresh_token(
+    State(pool): State<PgPool>,
+    State(encoding_key): State<EncodingKey>,
+    State(decoding_key): State<DecodingKey>,
+    State(config): State<Config>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let claims = auth::verify_jwt_allow_expired(
+        &token.0,
+        &decoding_key,
+        chrono::Duration::days(TOKEN_REFRESH_GRACE_DAYS),
+    )?;
+
+    let mut user_auth = get_user(claims.user_id, &pool).await?;
+    user_auth.token = Some(auth::generate_jwt(
+        user_auth.id,
+        &encoding_key,
+        config.token_ttl(),
+    )?);
 
     Ok(Json(json!({ "user": user_auth })))
 }
 
 fn verify_token(token: &str, key: &DecodingKey) -> AppResult<UserId> {
-    let claim = auth::verify_jwt(token, &key)?;
+    let claim = auth::verify_jwt(token, key)?;
     Ok(claim.user_id)
 }
 
 async fn get_user(user_id: UserId, pool: &PgPool) -> AppResult<UserAuth> {
-    let mut conn = pool.acquire().await.unwrap();
-
     let user_auth = sqlx::query_as!(
         UserAuth,
-        "SELECT *, NULL AS token FROM users WHERE id = $1",
+        "SELECT id, username, email, hash, bio, image, NULL AS token FROM users WHERE id = $1",
         user_id
     )
-    .fetch_one(&mut conn)
+    .fetch_one(pool)
     .await?;
 
     Ok(user_auth)
@@ -222,12 +346,24 @@ async fn get_user_profile(
         username,
         req_user_id
     )
-    .fetch_one(&mut pool.acquire().await.unwrap())
-    .await?;
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "profile": ["not found"] })))?;
 
     Ok(user)
 }
 
+/// What the profile page shows; articles and follow responses only embed the plain [`UserProfile`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FullProfile {
+    #[serde(flatten)]
+    profile: UserProfile,
+    /// Published articles only.
+    articles_count: i64,
+    followers_count: i64,
+}
+
 async fn auth_user(pool: &PgPool, token: &str, key: &DecodingKey) -> AppResult<UserAuth> {
     let user_id = verify_token(token, key)?;
     let mut user = get_user(user_id, pool).await?;
@@ -235,6 +371,24 @@ async fn auth_user(pool: &PgPool, token: &str, key: &DecodingKey) -> AppResult<U
     Ok(user)
 }
 
+/// Rejects anyone but an admin with a 403 carrying `message` under `field`.
+async fn require_admin(
+    pool: &PgPool,
+    user_id: UserId,
+    field: &str,
+    message: &str,
+) -> AppResult<()> {
+    let is_admin = sqlx::query_scalar!("SELECT is_admin FROM users WHERE id = $1", user_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(false);
+
+    if !is_admin {
+        Err(AppError::ForbiddenError(json!({ field: message })))?
+    }
+    Ok(())
+}
+
 pub async fn get_current_user(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
@@ -264,45 +418,458 @@ struct UpdateUserData {
 pub async fn update_user(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
+    State(mailer): State<Option<Arc<dyn Mailer>>>,
     TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
     Json(UpdateUser { user: data }): Json<UpdateUser>,
 ) -> AppResult<impl IntoResponse> {
+    data.validate()?;
+
     let user = auth_user(&pool, &token.0, &key).await?;
 
-    let hash = data
-        .password
-        .map(|password| hash_password(password))
-        .transpose()?;
+    let hash = data.password.map(hash_password).transpose()?;
+
+    // Nothing is saved, and no confirmation mail goes out, unless every part of the update works.
+    let mut tx = pool.begin().await?;
 
     let mut updated_user = sqlx::query_as!(
         UserAuth,
         "UPDATE users
-            SET (email, username, hash, bio, image) = 
+            SET (username, hash, bio, image) = 
                 (
-                    COALESCE($1, email),
-                    COALESCE($2, username),
-                    COALESCE($3, hash),
-                    COALESCE($4, bio),
-                    COALESCE($5, image)
+                    COALESCE($1, username),
+                    COALESCE($2, hash),
+                    COALESCE($3, bio),
+                    COALESCE($4, image)
                 )
-            WHERE id = $6
-        RETURNING *, NULL AS token
+            WHERE id = $5
+        RETURNING id, username, email, hash, bio, image, NULL AS token
         ",
-        data.email,
         data.username,
         hash,
         data.bio,
         data.image,
         user.id
     )
-    .fetch_one(&mut pool.acquire().await.unwrap())
-    .await?;
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|err| match &err {
+        sqlx::Error::Database(db) if db.constraint() == Some("users_username_key") => {
+            validation_error("username", "has already been taken")
+        }
+        _ => err.into(),
+    })?;
+
+    // A new email only replaces the current one once it's confirmed via `confirm_email`.
+    let email_change = match data.email.as_deref().map(normalize_email) {
+        Some(email) if email != user.email => {
+            Some(request_email_change(&mut tx, mailer.as_deref(), user.id, email).await?)
+        }
+        _ => None,
+    };
+
+    tx.commit().await?;
 
     updated_user.token = user.token;
 
+    Ok(Json(match email_change {
+        Some(email_change) => json!({ "user": updated_user, "emailChange": email_change }),
+        None => json!({ "user": updated_user }),
+    }))
+}
+
+/// Deletes the account and everything it owns: follows both ways, favorites, reactions,
+/// comments, and the user's articles along with other people's comments and favorites on them.
+/// Articles are removed rather than reassigned, since there is no one to hand them to.
+/// The foreign keys cascade the same way; the explicit deletes keep this independent of that.
+pub async fn delete_account(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "DELETE FROM follows WHERE follower_id = $1 OR followee_id = $1",
+        user_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!("DELETE FROM article_favs WHERE user_id = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM comment_reactions WHERE user_id = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM claps WHERE user_id = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM comments WHERE author_id = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query!(
+        "
+        WITH deleted AS (
+            DELETE FROM articles WHERE author_id = $1 RETURNING id, slug
+        )
+        INSERT INTO article_tombstones (id, slug)
+        SELECT id, slug FROM deleted
+        ",
+        user_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let deleted = sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+    // A token for an account that's already gone is as good as an invalid one.
+    if deleted == 0 {
+        Err(AppError::UnauthorizedError(json!({
+            "user": "no longer exists"
+        })))?
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(json!({})))
+}
+
+#[derive(Serialize)]
+struct EmailChange {
+    email: String,
+}
+
+/// Stores `email` as the user's pending address in `tx`, replacing any earlier unconfirmed
+/// request, and mails the confirmation token there. Without a mailer the token could never
+/// arrive, so the change is refused up front.
+async fn request_email_change(
+    tx: &mut Transaction<'_, Postgres>,
+    mailer: Option<&dyn Mailer>,
+    user_id: UserId,
+    email: String,
+) -> AppResult<EmailChange> {
+    let Some(mailer) = mailer else {
+        Err(validation_error(
+            "email",
+            "can't be changed because this server can't send email",
+        ))?
+    };
+
+    let email_taken = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM users WHERE email = $1 AND id <> $2) AS "taken!""#,
+        email,
+        user_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if email_taken {
+        Err(validation_error("email", "has already been taken"))?
+    }
+
+    let token = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect::<String>();
+
+    sqlx::query!(
+        "
+        INSERT INTO email_changes (user_id, email, token)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id) DO UPDATE
+            SET (email, token, created_at) = (EXCLUDED.email, EXCLUDED.token, NOW())
+        ",
+        user_id,
+        email,
+        token,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Sent last, so the caller only commits the request if the token actually went out.
+    mailer
+        .send(
+            &email,
+            "Confirm your new email address",
+            &format!(
+                "Your confirmation token is {token}\n\nIt is valid for a day. Until it's used, \
+                you keep signing in with your current address."
+            ),
+        )
+        .await?;
+
+    Ok(EmailChange { email })
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmail {
+    confirmation: ConfirmEmailData,
+}
+
+#[derive(Deserialize)]
+struct ConfirmEmailData {
+    token: String,
+}
+
+/// Applies the pending email change matching `token`. Tokens are valid for a day.
+pub async fn confirm_email(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    Json(ConfirmEmail { confirmation }): Json<ConfirmEmail>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let updated_user = sqlx::query_as!(
+        UserAuth,
+        "
+        WITH confirmed AS (
+            DELETE FROM email_changes
+            WHERE user_id = $1
+                AND token = $2
+                AND created_at > NOW() - INTERVAL '1 day'
+            RETURNING user_id, email
+        )
+        UPDATE users
+            SET email = confirmed.email
+            FROM confirmed
+            WHERE users.id = confirmed.user_id
+        RETURNING users.id, users.username, users.email, users.hash, users.bio, users.image, NULL AS token
+        ",
+        user_id,
+        confirmation.token,
+    )
+    .fetch_optional(&pool)
+    .await
+    // Someone may have registered with the address while the change was pending.
+    .map_err(|err| match &err {
+        sqlx::Error::Database(db) if db.constraint() == Some("users_email_key") => {
+            validation_error("email", "has already been taken")
+        }
+        _ => err.into(),
+    })?;
+
+    let Some(mut updated_user) = updated_user else {
+        Err(AppError::ForbiddenError(json!({
+            "token": "is invalid or expired"
+        })))?
+    };
+
+    updated_user.token = Some(token.0);
+
     Ok(Json(json!({ "user": updated_user })))
 }
 
+/// Largest serialized preferences object a user may store.
+const PREFERENCES_MAX_BYTES: usize = 16 * 1024;
+
+/// UI settings the frontend keeps for the user across devices. The backend doesn't
+/// interpret them beyond requiring a JSON object of bounded size.
+pub async fn get_preferences(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let preferences = sqlx::query_scalar!("SELECT preferences FROM users WHERE id = $1", user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    Ok(Json(json!({ "preferences": preferences })))
+}
+
+#[derive(Deserialize)]
+pub struct UpdatePreferences {
+    preferences: serde_json::Value,
+}
+
+/// Replaces the stored preferences as a whole.
+pub async fn update_preferences(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    Json(UpdatePreferences { preferences }): Json<UpdatePreferences>,
+) -> AppResult<impl IntoResponse> {
+    if !preferences.is_object() {
+        Err(validation_error(
+            "preferences",
+            "preferences must be an object",
+        ))?
+    }
+    if preferences.to_string().len() > PREFERENCES_MAX_BYTES {
+        Err(validation_error("preferences", "preferences are too large"))?
+    }
+
+    let user_id = verify_token(&token.0, &key)?;
+
+    let preferences = sqlx::query_scalar!(
+        "
+        UPDATE users
+        SET preferences = $1
+        WHERE id = $2
+        RETURNING preferences
+        ",
+        preferences,
+        user_id,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(json!({ "preferences": preferences })))
+}
+
+/// Small counts for the header, all in one query so the UI polls a single endpoint.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badges {
+    /// Email changes still waiting for confirmation.
+    pending_email_changes: i64,
+    /// The user's unpublished articles.
+    drafts: i64,
+}
+
+pub async fn get_badges(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+) -> AppResult<impl IntoResponse> {
+    let Some(TypedHeader(Authorization(token))) = token else {
+        Err(AppError::UnauthorizedError(json!({
+            "badges": "requires authentication"
+        })))?
+    };
+    let user_id = verify_token(&token.0, &key)?;
+
+    let badges = sqlx::query_as!(
+        Badges,
+        r#"
+        SELECT
+            (SELECT COUNT(*)
+                FROM email_changes
+                WHERE user_id = $1 AND created_at > NOW() - INTERVAL '1 day'
+            ) AS "pending_email_changes!",
+            (SELECT COUNT(*)
+                FROM articles
+                WHERE author_id = $1 AND NOT published
+            ) AS "drafts!"
+        "#,
+        user_id,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(json!({ "badges": badges })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    offset: Option<usize>,
+}
+
+/// Everything the caller did recently, newest first: articles published, comments posted,
+/// articles favorited and users followed. Each item has a `type` saying which, and carries the
+/// article, comment or profile it's about.
+pub async fn get_activity(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    State(config): State<Config>,
+    Query(query): Query<ActivityQuery>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let limit = clamp_limit(query.limit, config.default_page_size);
+    let offset = query.offset.unwrap_or(0);
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            activity.kind AS "kind!",
+            activity.created_at AS "created_at!",
+            activity.slug,
+            activity.title,
+            activity.comment_id,
+            activity.comment_body,
+            activity.username,
+            COUNT(*) OVER() AS "count!"
+        FROM (
+            SELECT
+                'article'::TEXT AS kind,
+                articles.created_at,
+                articles.slug,
+                articles.title,
+                NULL::INT4 AS comment_id,
+                NULL::TEXT AS comment_body,
+                NULL::VARCHAR AS username
+            FROM articles
+            WHERE articles.author_id = $1 AND articles.published
+            UNION ALL
+            SELECT 'comment', comments.created_at, articles.slug, articles.title,
+                comments.id, comments.body, NULL
+            FROM comments
+            INNER JOIN articles ON articles.id = comments.article_id
+            WHERE comments.author_id = $1
+            UNION ALL
+            SELECT 'favorite', article_favs.created_at, articles.slug, articles.title,
+                NULL, NULL, NULL
+            FROM article_favs
+            INNER JOIN articles ON articles.id = article_favs.article_id
+            WHERE article_favs.user_id = $1
+            UNION ALL
+            SELECT 'follow', follows.created_at, NULL, NULL, NULL, NULL, users.username
+            FROM follows
+            INNER JOIN users ON users.id = follows.followee_id
+            WHERE follows.follower_id = $1
+        ) activity
+        ORDER BY activity.created_at DESC, activity.kind
+        LIMIT $2 OFFSET $3
+        "#,
+        user_id,
+        limit as i64,
+        offset as i64,
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let count = rows.first().map_or(0, |row| row.count);
+
+    let activity = rows
+        .into_iter()
+        .map(|row| {
+            let mut item = json!({ "type": row.kind, "createdAt": row.created_at });
+            if let (Some(slug), Some(title)) = (row.slug, row.title) {
+                item["article"] = json!({ "slug": slug, "title": title });
+            }
+            if let (Some(id), Some(body)) = (row.comment_id, row.comment_body) {
+                item["comment"] = json!({ "id": id, "body": body });
+            }
+            if let Some(username) = row.username {
+                item["profile"] = json!({ "username": username });
+            }
+            item
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "activity": activity,
+        "activityCount": count,
+    })))
+}
+
 pub async fn get_profile(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
@@ -315,6 +882,29 @@ pub async fn get_profile(
 
     let profile = get_user_profile(&pool, &username, user_id).await?;
 
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*)
+                FROM articles
+                WHERE author_id = $1 AND published
+            ) AS "articles_count!",
+            (SELECT COUNT(*)
+                FROM follows
+                WHERE followee_id = $1
+            ) AS "followers_count!"
+        "#,
+        profile.id,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    let profile = FullProfile {
+        profile,
+        articles_count: counts.articles_count,
+        followers_count: counts.followers_count,
+    };
+
     Ok(Json(json!({ "profile": profile })))
 }
 
@@ -335,7 +925,7 @@ pub async fn follow_user(
         follower_id,
         followee.id
     )
-    .execute(&mut pool.acquire().await.unwrap())
+    .execute(&pool)
     .await?;
 
     followee.following = true;
@@ -361,7 +951,7 @@ pub async fn unfollow_user(
         follower_id,
         followee.id
     )
-    .execute(&mut pool.acquire().await.unwrap())
+    .execute(&pool)
     .await?;
 
     followee.following = false;
@@ -374,12 +964,16 @@ struct ArticleWithCount {
     slug: String,
     title: String,
     description: String,
-    body: String,
+    body: Option<String>,
     tag_list: Vec<String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     favorited: bool,
     favorites_count: i64,
+    comments_count: i64,
+    claps_total: i64,
+    claps_from_me: i32,
+    published: bool,
     author: UserProfile,
     count: i64,
 }
@@ -387,44 +981,300 @@ struct ArticleWithCount {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct Article {
-    #[serde(skip)]
     id: i32,
     slug: String,
     title: String,
     description: String,
-    body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
     tag_list: Vec<String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     favorited: bool,
     favorites_count: i64,
+    comments_count: i64,
+    /// Claps from everyone, each reader giving up to [`MAX_CLAPS_PER_USER`].
+    claps_total: i64,
+    claps_from_me: i32,
+    /// `false` for drafts, which only their author can see.
+    published: bool,
+    /// Only filled in for single-article responses; updates must send it back unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<i32>,
     author: UserProfile,
 }
 
-#[derive(Debug, Deserialize)]
+/// `?format=summary` leaves `body` out of listings, which only need the description.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ArticleFormat {
+    Full,
+    Summary,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ArticleSort {
+    #[default]
+    Newest,
+    Oldest,
+    /// Most favorited first, newest first among equals.
+    MostFavorited,
+    /// Best `q` matches first; only valid together with `q`.
+    Relevance,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct ListArticlesQuery {
     #[serde(default)]
     tag: Option<String>,
     #[serde(default)]
     author: Option<String>,
+    /// Same as `author` but by user id, for internal callers that already know it.
+    #[serde(default)]
+    author_id: Option<UserId>,
     #[serde(default)]
     favorited: Option<String>,
+    /// Only articles favorited at least this many times.
+    #[serde(default)]
+    min_favorites: Option<u32>,
     #[serde(default)]
-    limit: Option<usize>,
+    limit: Option<i64>,
     #[serde(default)]
     offset: Option<usize>,
+    #[serde(default)]
+    format: Option<ArticleFormat>,
+    /// Comma-separated article ids, e.g. `ids=3,1,2`. Results keep this order.
+    #[serde(default)]
+    ids: Option<String>,
+    /// Only articles by authors the requester follows. Combines with the other filters;
+    /// requires authentication and answers 401 without it.
+    #[serde(default)]
+    following: Option<bool>,
+    /// Full-text search over title, description and body (web search syntax).
+    #[serde(default)]
+    q: Option<String>,
+    #[serde(default)]
+    sort: ArticleSort,
+    /// Opts into cursor pagination; see [`parse_cursor`].
+    #[serde(default)]
+    cursor: Option<String>,
+    /// RFC 3339 timestamp; see [`parse_updated_since`].
+    #[serde(default)]
+    updated_since: Option<String>,
 }
 
-pub async fn list_articles(
-    State(pool): State<PgPool>,
-    State(key): State<DecodingKey>,
-    Query(query): Query<ListArticlesQuery>,
-    token: Option<TypedHeader<Authorization<JWTToken>>>,
-) -> AppResult<impl IntoResponse> {
-    let user_id = token
+fn parse_ids(ids: &str) -> AppResult<Vec<i32>> {
+    ids.split(',')
+        .map(|id| id.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| validation_error("ids", "ids must be a comma-separated list of integers"))
+}
+
+/// A position in the newest-first order, tie-broken by id so no two articles share one.
+struct Cursor {
+    created_at: DateTime<Utc>,
+    id: i32,
+}
+
+/// `cursor=<created_at>,<id>` continues after that article and ignores `offset`, so pages
+/// don't shift when articles are added or removed in between. An empty `cursor=` starts
+/// from the top. Responses then carry `nextCursor`, null on the last page.
+fn parse_cursor(cursor: &str) -> AppResult<Option<Cursor>> {
+    if cursor.is_empty() {
+        return Ok(None);
+    }
+
+    let (created_at, id) = cursor
+        .rsplit_once(',')
+        .ok_or_else(|| validation_error("cursor", "cursor must be <created_at>,<id>"))?;
+
+    Ok(Some(Cursor {
+        created_at: DateTime::parse_from_rfc3339(created_at)
+            .map_err(|_| validation_error("cursor", "cursor has an invalid timestamp"))?
+            .with_timezone(&Utc),
+        id: id
+            .parse()
+            .map_err(|_| validation_error("cursor", "cursor has an invalid id"))?,
+    }))
+}
+
+/// `updated_since=<timestamp>` lists only articles created or edited after it, least recently
+/// updated first, so a syncing client can page through the changes and remember the last
+/// `updatedAt` it saw. The response then also carries `deleted`: every article deleted since,
+/// as `{id, slug, deletedAt}`, regardless of the other filters.
+fn parse_updated_since(updated_since: &str) -> AppResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(updated_since)
+        .map(|since| since.with_timezone(&Utc))
+        .map_err(|_| validation_error("updated_since", "must be an RFC 3339 timestamp"))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArticleTombstone {
+    id: i32,
+    slug: String,
+    deleted_at: DateTime<Utc>,
+}
+
+/// Largest page any listing returns, whatever `limit` or the configured default asks for.
+const MAX_PAGE_SIZE: usize = 100;
+
+/// The page size for a requested `limit`. Missing, zero or negative limits get `default`, and
+/// everything is capped at [`MAX_PAGE_SIZE`] so a single request can't scan the whole table.
+fn clamp_limit(limit: Option<i64>, default: usize) -> usize {
+    let limit = match limit {
+        Some(limit) if limit > 0 => usize::try_from(limit).unwrap_or(MAX_PAGE_SIZE),
+        _ => default,
+    };
+
+    limit.clamp(1, MAX_PAGE_SIZE)
+}
+
+/// The cursor after the last article, or `None` if the page wasn't full and nothing follows.
+fn next_cursor(articles: &[ArticleWithCount], limit: usize) -> Option<String> {
+    articles
+        .last()
+        .filter(|_| articles.len() >= limit)
+        .map(|last| {
+            format!(
+                "{},{}",
+                last.created_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                last.id
+            )
+        })
+}
+
+fn article_from_listing(article: ArticleWithCount) -> Article {
+    Article {
+        id: article.id,
+        slug: article.slug,
+        title: article.title,
+        description: article.description,
+        body: article.body,
+        tag_list: article.tag_list,
+        created_at: article.created_at,
+        updated_at: article.updated_at,
+        favorited: article.favorited,
+        favorites_count: article.favorites_count,
+        comments_count: article.comments_count,
+        claps_total: article.claps_total,
+        claps_from_me: article.claps_from_me,
+        published: article.published,
+        version: None,
+        author: article.author,
+    }
+}
+
+/// Builds a listing response; `nextCursor` is only included in cursor mode.
+/// `X-Total-Count`, `X-Limit` and `X-Offset` repeat the paging numbers as headers.
+fn listing_response(
+    articles: Vec<ArticleWithCount>,
+    limit: usize,
+    offset: usize,
+    cursor_mode: bool,
+) -> (HeaderMap, Json<serde_json::Value>) {
+    let total = articles.first().map(|a| a.count).unwrap_or(0);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    headers.insert("x-limit", HeaderValue::from(limit));
+    headers.insert("x-offset", HeaderValue::from(offset));
+
+    let mut body = json!({
+        "articlesCount": total,
+    });
+
+    if cursor_mode {
+        body["nextCursor"] = json!(next_cursor(&articles, limit));
+    }
+
+    body["articles"] = json!(articles
+        .into_iter()
+        .map(article_from_listing)
+        .collect::<Vec<_>>());
+
+    (headers, Json(body))
+}
+
+pub async fn list_articles(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    State(config): State<Config>,
+    Query(query): Query<ListArticlesQuery>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = token
         .map(|token| verify_token(&token.0 .0 .0, &key))
         .transpose()?;
 
+    if query.following == Some(true) && user_id.is_none() {
+        Err(AppError::UnauthorizedError(json!({
+            "following": "requires authentication"
+        })))?
+    }
+
+    let ids = query.ids.as_deref().map(parse_ids).transpose()?;
+
+    let q = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    if query.sort == ArticleSort::Relevance && q.is_none() {
+        Err(validation_error("sort", "relevance sorting requires q"))?
+    }
+
+    let cursor = query.cursor.as_deref().map(parse_cursor).transpose()?;
+    if query.cursor.is_some() && (ids.is_some() || query.sort != ArticleSort::Newest) {
+        Err(validation_error(
+            "cursor",
+            "cursor pagination only supports the newest-first order",
+        ))?
+    }
+    let cursor = cursor.flatten();
+
+    let updated_since = query
+        .updated_since
+        .as_deref()
+        .map(parse_updated_since)
+        .transpose()?;
+    if updated_since.is_some()
+        && (ids.is_some() || query.cursor.is_some() || query.sort != ArticleSort::Newest)
+    {
+        Err(validation_error(
+            "updated_since",
+            "can't be combined with ids, cursor or sort",
+        ))?
+    }
+
+    let limit = clamp_limit(query.limit, config.default_page_size);
+    let offset = if query.cursor.is_some() {
+        0
+    } else {
+        query.offset.unwrap_or(0)
+    };
+
+    // Resolving the username up front lets the query filter on the indexed `author_id`
+    // instead of comparing usernames row by row.
+    let author_id = match &query.author {
+        Some(username) => {
+            let id = sqlx::query_scalar!("SELECT id FROM users WHERE username = $1", username)
+                .fetch_optional(&pool)
+                .await?;
+
+            match id {
+                Some(id) if query.author_id.unwrap_or(id) == id => Some(id),
+                // An unknown author, or one contradicting `author_id`, matches nothing.
+                _ => {
+                    return Ok(listing_response(
+                        vec![],
+                        limit,
+                        offset,
+                        query.cursor.is_some(),
+                    ))
+                }
+            }
+        }
+        None => query.author_id,
+    };
+
     let articles = sqlx::query_as!(
         ArticleWithCount,
         r#"
@@ -433,9 +1283,10 @@ pub async fn list_articles(
             articles.slug,
             articles.title,
             articles.description,
-            articles.body,
+            CASE WHEN $7::BOOL THEN NULL ELSE articles.body END AS body,
             articles.created_at,
             articles.updated_at,
+            articles.published,
             COALESCE(
                 (SELECT
                     array_agg(tags.name ORDER BY tags.name ASC)
@@ -454,6 +1305,28 @@ pub async fn list_articles(
                 FROM article_favs
                 WHERE article_favs.article_id = articles.id
             ) AS "favorites_count!",
+            COALESCE(
+                (SELECT COUNT(*)
+                    FROM comments
+                    WHERE comments.article_id = articles.id
+                ),
+                0
+            ) AS "comments_count!",
+            COALESCE(
+                (SELECT SUM(claps.count)
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                ),
+                0
+            ) AS "claps_total!",
+            COALESCE(
+                (SELECT claps.count
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                    AND claps.user_id = $6
+                ),
+                0
+            ) AS "claps_from_me!",
             (
                 users.id,
                 users.username,
@@ -469,7 +1342,8 @@ pub async fn list_articles(
         FROM articles
         INNER JOIN users ON articles.author_id = users.id
         WHERE
-            ($1::VARCHAR IS NULL OR users.username = $1)
+            articles.published
+            AND ($1::INT4 IS NULL OR articles.author_id = $1)
             AND ($2::VARCHAR IS NULL OR EXISTS (
                 SELECT 1 FROM article_favs
                 INNER JOIN users ON article_favs.user_id = users.id
@@ -480,53 +1354,151 @@ pub async fn list_articles(
                 INNER JOIN tags ON article_tags.tag_id = tags.id
                 WHERE article_tags.article_id = articles.id AND tags.name = $3
             ))
-        ORDER BY created_at DESC
+            AND ($8::INT4[] IS NULL OR articles.id = ANY($8))
+            AND (NOT $9::BOOL OR EXISTS (
+                SELECT 1 FROM follows
+                WHERE follows.follower_id = $6 AND follows.followee_id = users.id
+            ))
+            AND ($10::TEXT IS NULL OR
+                to_tsvector('english', articles.title || ' ' || articles.description || ' ' || articles.body)
+                @@ websearch_to_tsquery('english', $10))
+            AND ($12::TIMESTAMPTZ IS NULL OR (articles.created_at, articles.id) < ($12, $13::INT4))
+            AND ($14::INT8 IS NULL OR (
+                SELECT COUNT(*) FROM article_favs WHERE article_favs.article_id = articles.id
+            ) >= $14)
+            AND ($15::TIMESTAMPTZ IS NULL OR articles.updated_at > $15)
+        ORDER BY
+            array_position($8, articles.id),
+            CASE WHEN $11::BOOL THEN ts_rank(
+                to_tsvector('english', articles.title || ' ' || articles.description || ' ' || articles.body),
+                websearch_to_tsquery('english', $10)
+            ) END DESC,
+            CASE WHEN $17::BOOL THEN (
+                SELECT COUNT(*) FROM article_favs WHERE article_favs.article_id = articles.id
+            ) END DESC,
+            CASE WHEN $15 IS NOT NULL THEN articles.updated_at END ASC,
+            CASE WHEN $15 IS NOT NULL THEN articles.id END ASC,
+            CASE WHEN $16::BOOL THEN articles.created_at END ASC,
+            CASE WHEN $16::BOOL THEN articles.id END ASC,
+            articles.created_at DESC,
+            articles.id DESC
         LIMIT $4 OFFSET $5
         "#,
-        query.author,
+        author_id,
         query.favorited,
         query.tag,
-        query.limit.unwrap_or(20) as i64,
-        query.offset.unwrap_or(0) as i64,
+        limit as i64,
+        offset as i64,
         user_id,
+        query.format == Some(ArticleFormat::Summary),
+        ids.as_deref(),
+        query.following.unwrap_or(false),
+        q,
+        query.sort == ArticleSort::Relevance,
+        cursor.as_ref().map(|c| c.created_at),
+        cursor.as_ref().map(|c| c.id),
+        query.min_favorites.map(i64::from),
+        updated_since,
+        query.sort == ArticleSort::Oldest,
+        query.sort == ArticleSort::MostFavorited,
     )
-    .fetch_all(&mut pool.acquire().await.unwrap())
+    .fetch_all(&pool)
     .await?;
 
-    Ok(Json(json!({
-        "articlesCount": articles.iter().next().map(|a| a.count).unwrap_or(0),
-        "articles": articles.into_iter().map(|article| Article {
-            id: article.id,
-            slug: article.slug,
-            title: article.title,
-            description: article.description,
-            body: article.body,
-            tag_list: article.tag_list,
-            created_at: article.created_at,
-            updated_at: article.updated_at,
-            favorited: article.favorited,
-            favorites_count: article.favorites_count,
-            author: article.author,
-        }).collect::<Vec<_>>(),
-    })))
+    let (headers, mut body) = listing_response(articles, limit, offset, query.cursor.is_some());
+
+    if let Some(since) = updated_since {
+        let deleted = sqlx::query_as!(
+            ArticleTombstone,
+            "
+            SELECT id, slug, deleted_at
+            FROM article_tombstones
+            WHERE deleted_at > $1
+            ORDER BY deleted_at, id
+            ",
+            since,
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        body.0["deleted"] = json!(deleted);
+    }
+
+    Ok((headers, body))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchArticlesQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    format: Option<ArticleFormat>,
+}
+
+/// Full-text search, best matches first. Shorthand for `list_articles` with `q` and
+/// `sort=relevance`, except that a blank `q` is an error instead of listing everything.
+pub async fn search_articles(
+    pool: State<PgPool>,
+    key: State<DecodingKey>,
+    config: State<Config>,
+    Query(query): Query<SearchArticlesQuery>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+) -> AppResult<impl IntoResponse> {
+    if query.q.trim().is_empty() {
+        Err(validation_error("q", "q can't be blank"))?
+    }
+
+    let query = ListArticlesQuery {
+        q: Some(query.q),
+        sort: ArticleSort::Relevance,
+        limit: query.limit,
+        offset: query.offset,
+        format: query.format,
+        ..Default::default()
+    };
+
+    list_articles(pool, key, config, Query(query), token).await
 }
 
 #[derive(Debug, Deserialize)]
 pub struct FeedArticlesQuery {
     #[serde(default)]
-    limit: Option<usize>,
+    limit: Option<i64>,
     #[serde(default)]
     offset: Option<usize>,
+    #[serde(default)]
+    format: Option<ArticleFormat>,
+    /// Opts into cursor pagination; see [`parse_cursor`].
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
 pub async fn feed_articles(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
+    State(config): State<Config>,
     Query(query): Query<FeedArticlesQuery>,
     TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
 ) -> AppResult<impl IntoResponse> {
     let user_id = verify_token(&token.0, &key)?;
 
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(parse_cursor)
+        .transpose()?
+        .flatten();
+    let limit = clamp_limit(query.limit, config.default_page_size);
+    let offset = if query.cursor.is_some() {
+        0
+    } else {
+        query.offset.unwrap_or(0)
+    };
+
     let articles = sqlx::query_as!(
         ArticleWithCount,
         r#"
@@ -535,9 +1507,10 @@ pub async fn feed_articles(
             articles.slug,
             articles.title,
             articles.description,
-            articles.body,
+            CASE WHEN $4::BOOL THEN NULL ELSE articles.body END AS body,
             articles.created_at,
             articles.updated_at,
+            articles.published,
             COALESCE(
                 (SELECT
                     array_agg(tags.name ORDER BY tags.name ASC)
@@ -556,6 +1529,28 @@ pub async fn feed_articles(
                 FROM article_favs
                 WHERE article_favs.article_id = articles.id
             ) AS "favorites_count!",
+            COALESCE(
+                (SELECT COUNT(*)
+                    FROM comments
+                    WHERE comments.article_id = articles.id
+                ),
+                0
+            ) AS "comments_count!",
+            COALESCE(
+                (SELECT SUM(claps.count)
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                ),
+                0
+            ) AS "claps_total!",
+            COALESCE(
+                (SELECT claps.count
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                    AND claps.user_id = $1
+                ),
+                0
+            ) AS "claps_from_me!",
             (
                 users.id,
                 users.username,
@@ -573,36 +1568,132 @@ pub async fn feed_articles(
                 WHERE follows.follower_id = $1
                     AND follows.followee_id = articles.author_id 
             )
-        ORDER BY created_at DESC
+            AND articles.published
+            AND ($5::TIMESTAMPTZ IS NULL OR (articles.created_at, articles.id) < ($5, $6::INT4))
+        ORDER BY articles.created_at DESC, articles.id DESC
         LIMIT $2 OFFSET $3
         "#,
         user_id,
-        query.limit.unwrap_or(20) as i64,
-        query.offset.unwrap_or(0) as i64,
+        limit as i64,
+        offset as i64,
+        query.format == Some(ArticleFormat::Summary),
+        cursor.as_ref().map(|c| c.created_at),
+        cursor.as_ref().map(|c| c.id),
     )
-    .fetch_all(&mut pool.acquire().await.unwrap())
+    .fetch_all(&pool)
     .await?;
 
-    Ok(Json(json!({
-        "articlesCount": articles.iter().next().map(|a| a.count).unwrap_or(0),
-        "articles": articles.into_iter().map(|article| Article {
-            id: article.id,
-            slug: article.slug,
-            title: article.title,
-            description: article.description,
-            body: article.body,
-            tag_list: article.tag_list,
-            created_at: article.created_at,
-            updated_at: article.updated_at,
-            favorited: article.favorited,
-            favorites_count: article.favorites_count,
-            author: article.author,
-        }).collect::<Vec<_>>(),
-    })))
+    Ok(listing_response(
+        articles,
+        limit,
+        offset,
+        query.cursor.is_some(),
+    ))
 }
 
-async fn get_article_by_slug(
-    pool: &PgPool,
+#[derive(Debug, Deserialize)]
+pub struct DraftArticlesQuery {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    format: Option<ArticleFormat>,
+}
+
+/// The caller's unpublished articles, most recently edited first.
+pub async fn draft_articles(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    State(config): State<Config>,
+    Query(query): Query<DraftArticlesQuery>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let limit = clamp_limit(query.limit, config.default_page_size);
+    let offset = query.offset.unwrap_or(0);
+
+    let articles = sqlx::query_as!(
+        ArticleWithCount,
+        r#"
+        SELECT
+            articles.id,
+            articles.slug,
+            articles.title,
+            articles.description,
+            CASE WHEN $4::BOOL THEN NULL ELSE articles.body END AS body,
+            articles.created_at,
+            articles.updated_at,
+            articles.published,
+            COALESCE(
+                (SELECT
+                    array_agg(tags.name ORDER BY tags.name ASC)
+                    FROM article_tags
+                    INNER JOIN tags ON article_tags.tag_id = tags.id
+                    WHERE article_tags.article_id = articles.id
+                ),
+                '{}'::VARCHAR[]
+            ) AS "tag_list!",
+            EXISTS (
+                SELECT 1 FROM article_favs
+                WHERE article_favs.article_id = articles.id
+                AND article_favs.user_id = $1
+            ) AS "favorited!",
+            (SELECT COUNT(*)
+                FROM article_favs
+                WHERE article_favs.article_id = articles.id
+            ) AS "favorites_count!",
+            COALESCE(
+                (SELECT COUNT(*)
+                    FROM comments
+                    WHERE comments.article_id = articles.id
+                ),
+                0
+            ) AS "comments_count!",
+            COALESCE(
+                (SELECT SUM(claps.count)
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                ),
+                0
+            ) AS "claps_total!",
+            COALESCE(
+                (SELECT claps.count
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                    AND claps.user_id = $1
+                ),
+                0
+            ) AS "claps_from_me!",
+            (
+                users.id,
+                users.username,
+                users.bio,
+                users.image,
+                FALSE
+            ) AS "author!: UserProfile",
+            COUNT(*) OVER() AS "count!"
+        FROM articles
+        INNER JOIN users ON articles.author_id = users.id
+        WHERE articles.author_id = $1 AND NOT articles.published
+        ORDER BY articles.updated_at DESC, articles.id DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        user_id,
+        limit as i64,
+        offset as i64,
+        query.format == Some(ArticleFormat::Summary),
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(listing_response(articles, limit, offset, false))
+}
+
+/// Reads through `executor`, so callers inside a transaction see their own uncommitted writes.
+async fn get_article_by_slug<'c>(
+    executor: impl Executor<'c, Database = Postgres>,
     slug: &str,
     user_id: Option<UserId>,
 ) -> AppResult<Article> {
@@ -614,9 +1705,11 @@ async fn get_article_by_slug(
             articles.slug,
             articles.title,
             articles.description,
-            articles.body,
+            articles.body AS "body?",
             articles.created_at,
             articles.updated_at,
+            articles.published,
+            articles.version AS "version?",
             COALESCE(
                 (SELECT
                     array_agg(tags.name ORDER BY tags.name ASC)
@@ -635,6 +1728,28 @@ async fn get_article_by_slug(
                 FROM article_favs
                 WHERE article_favs.article_id = articles.id
             ) AS "favorites_count!",
+            COALESCE(
+                (SELECT COUNT(*)
+                    FROM comments
+                    WHERE comments.article_id = articles.id
+                ),
+                0
+            ) AS "comments_count!",
+            COALESCE(
+                (SELECT SUM(claps.count)
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                ),
+                0
+            ) AS "claps_total!",
+            COALESCE(
+                (SELECT claps.count
+                    FROM claps
+                    WHERE claps.article_id = articles.id
+                    AND claps.user_id = $2
+                ),
+                0
+            ) AS "claps_from_me!",
             (
                 users.id,
                 users.username,
@@ -648,13 +1763,14 @@ async fn get_article_by_slug(
             ) AS "author!: UserProfile"
         FROM articles
         INNER JOIN users ON articles.author_id = users.id
-        WHERE articles.slug = $1
+        WHERE articles.slug = $1 AND (articles.published OR articles.author_id = $2)
         "#,
         slug,
         user_id,
     )
-    .fetch_one(&mut pool.acquire().await.unwrap())
-    .await?;
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "article": ["not found"] })))?;
 
     Ok(article)
 }
@@ -673,131 +1789,509 @@ pub async fn get_article(
     ))
 }
 
-#[derive(Deserialize)]
-pub struct CreateArticle {
-    article: CreateArticleData,
-}
-
-#[derive(Deserialize, Validate)]
-#[serde(rename_all = "camelCase")]
-struct CreateArticleData {
-    #[validate(length(min = 1, message = "title can't be blank"))]
+/// An `articles` row exactly as stored, including the internal ids the API normally hides.
+#[derive(Debug, Serialize)]
+struct RawArticle {
+    id: i32,
+    slug: String,
     title: String,
-    #[validate(length(min = 1, message = "description can't be blank"))]
     description: String,
-    #[validate(length(min = 1, message = "body can't be blank"))]
     body: String,
-    #[serde(default)]
-    tag_list: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    author_id: UserId,
+    published: bool,
+    version: i32,
 }
 
-pub async fn create_article(
+/// The article's headings with unique anchors, for rendering a table of contents.
+pub async fn get_article_toc(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
-    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
-    Json(CreateArticle { article }): Json<CreateArticle>,
+    Path(slug): Path<String>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
 ) -> AppResult<impl IntoResponse> {
-    article.validate()?;
-
-    let user_id = verify_token(&token.0, &key)?;
-
-    let slug = slug::slugify(&article.title);
-    let tags = article.tag_list;
+    let user_id = token
+        .map(|token| verify_token(&token.0 .0 .0, &key))
+        .transpose()?;
 
-    let mut article: Article = sqlx::query_as!(
-        Article,
-        r#"
-            WITH article AS (
-                INSERT INTO articles (slug, title, description, body, author_id)
-                VALUES ($1, $2, $3, $4, $5)
-                RETURNING *
-            )
-            SELECT
-                article.id,
-                article.slug,
-                article.title,
-                article.description,
-                article.body,
-                article.created_at,
-                article.updated_at,
-                FALSE AS "favorited!",
-                '{}'::VARCHAR[] AS "tag_list!",
-                CAST(0 as INT8) AS "favorites_count!",
-                (
-                    users.id,
-                    users.username,
-                    users.bio,
-                    users.image,
-                    EXISTS (
-                        SELECT 1 FROM follows
-                        WHERE follows.follower_id = $5
-                        AND follows.followee_id = users.id
-                    )
-                ) AS "author!: UserProfile"
-            FROM article
-            INNER JOIN users ON users.id = article.author_id
-        "#,
+    let body = sqlx::query_scalar!(
+        "SELECT body FROM articles WHERE slug = $1 AND (published OR author_id = $2)",
         slug,
-        article.title,
-        article.description,
-        article.body,
-        user_id
+        user_id,
     )
-    .fetch_one(&mut pool.acquire().await.unwrap())
-    .await?;
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "article": ["not found"] })))?;
 
-    sqlx::query!(
-        "
-        INSERT INTO tags (name)
-        SELECT * FROM UNNEST($1::TEXT[])
-        ON CONFLICT DO NOTHING
-        ",
-        &tags[..]
+    Ok(Json(json!({ "toc": toc::table_of_contents(&body) })))
+}
+
+/// Returns the stored row behind an article for support and debugging. Admins only.
+pub async fn get_raw_article(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(slug): Path<String>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    require_admin(
+        &pool,
+        user_id,
+        "article",
+        "raw rows are only visible to admins",
     )
-    .execute(&mut pool.acquire().await.unwrap())
     .await?;
 
-    sqlx::query!(
+    let article = sqlx::query_as!(
+        RawArticle,
         "
-        INSERT INTO article_tags (article_id, tag_id)
-        SELECT $1, tags.id FROM tags WHERE tags.name = ANY($2)
+        SELECT
+            id, slug, title, description, body, created_at, updated_at, author_id, published,
+            version
+        FROM articles
+        WHERE slug = $1
         ",
-        article.id,
-        &tags[..],
+        slug
     )
-    .execute(&mut pool.acquire().await.unwrap())
-    .await?;
-
-    article.tag_list = tags;
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "article": ["not found"] })))?;
 
     Ok(Json(json!({ "article": article })))
 }
 
-#[derive(Deserialize)]
-pub struct UpdateArticle {
-    article: UpdateArticleData,
+/// Suggests up to five authors related to the article's author: those favorited by the same
+/// readers weigh more than those who merely share tags.
+pub async fn get_similar_authors(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(slug): Path<String>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = token
+        .map(|token| verify_token(&token.0 .0 .0, &key))
+        .transpose()?;
+
+    visible_article_id(&pool, &slug, user_id).await?;
+
+    let profiles = sqlx::query_as!(
+        UserProfile,
+        r#"
+        WITH source AS (
+            SELECT author_id FROM articles WHERE slug = $1
+        ),
+        candidates AS (
+            SELECT other.author_id, 2 AS score
+            FROM articles AS own
+            INNER JOIN article_favs AS own_favs ON own_favs.article_id = own.id
+            INNER JOIN article_favs AS other_favs ON other_favs.user_id = own_favs.user_id
+            INNER JOIN articles AS other ON other.id = other_favs.article_id
+            WHERE own.author_id = (SELECT author_id FROM source)
+            UNION ALL
+            SELECT other.author_id, 1 AS score
+            FROM articles AS own
+            INNER JOIN article_tags AS own_tags ON own_tags.article_id = own.id
+            INNER JOIN article_tags AS other_tags ON other_tags.tag_id = own_tags.tag_id
+            INNER JOIN articles AS other ON other.id = other_tags.article_id
+            WHERE own.author_id = (SELECT author_id FROM source)
+        )
+        SELECT
+            users.id, users.username AS "username?", users.bio, users.image,
+            ($2::INT4 IS NOT NULL AND EXISTS (
+                SELECT 1 FROM follows
+                WHERE follows.follower_id = $2 AND follows.followee_id = users.id
+            )) AS "following!"
+        FROM candidates
+        INNER JOIN users ON users.id = candidates.author_id
+        WHERE users.id <> (SELECT author_id FROM source)
+            AND ($2::INT4 IS NULL OR users.id <> $2)
+        GROUP BY users.id
+        ORDER BY SUM(candidates.score) DESC, users.id
+        LIMIT 5
+        "#,
+        slug,
+        user_id,
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(json!({ "profiles": profiles })))
+}
+
+/// The people the requester follows who have favorited the article, for "liked by" lines.
+pub async fn get_favorited_by_following(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(slug): Path<String>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+) -> AppResult<impl IntoResponse> {
+    let Some(TypedHeader(Authorization(token))) = token else {
+        Err(AppError::UnauthorizedError(json!({
+            "profiles": "requires authentication"
+        })))?
+    };
+    let user_id = verify_token(&token.0, &key)?;
+
+    visible_article_id(&pool, &slug, Some(user_id)).await?;
+
+    let profiles = sqlx::query_as!(
+        UserProfile,
+        r#"
+        SELECT
+            users.id, users.username AS "username?", users.bio, users.image,
+            TRUE AS "following!"
+        FROM articles
+        INNER JOIN article_favs ON article_favs.article_id = articles.id
+        INNER JOIN follows ON follows.followee_id = article_favs.user_id
+            AND follows.follower_id = $2
+        INNER JOIN users ON users.id = article_favs.user_id
+        WHERE articles.slug = $1
+        ORDER BY users.username
+        "#,
+        slug,
+        user_id,
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(json!({ "profiles": profiles })))
+}
+
+#[derive(Deserialize)]
+pub struct FavoritedByFollowingBatch {
+    slugs: Vec<String>,
+}
+
+/// Most slugs a single `favorited_by_following_batch` request may ask about.
+const FAVORITED_BY_FOLLOWING_MAX_SLUGS: usize = 100;
+
+/// `get_favorited_by_following` for a whole feed page at once, keyed by slug.
+/// Articles none of the requester's followees favorited are left out.
+pub async fn favorited_by_following_batch(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+    Json(FavoritedByFollowingBatch { slugs }): Json<FavoritedByFollowingBatch>,
+) -> AppResult<impl IntoResponse> {
+    let Some(TypedHeader(Authorization(token))) = token else {
+        Err(AppError::UnauthorizedError(json!({
+            "profiles": "requires authentication"
+        })))?
+    };
+    let user_id = verify_token(&token.0, &key)?;
+
+    if slugs.len() > FAVORITED_BY_FOLLOWING_MAX_SLUGS {
+        Err(validation_error("slugs", "too many slugs"))?
+    }
+
+    let mut profiles: BTreeMap<String, Vec<UserProfile>> = BTreeMap::new();
+
+    if !slugs.is_empty() {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                articles.slug,
+                (
+                    users.id,
+                    users.username,
+                    users.bio,
+                    users.image,
+                    TRUE
+                ) AS "profile!: UserProfile"
+            FROM articles
+            INNER JOIN article_favs ON article_favs.article_id = articles.id
+            INNER JOIN follows ON follows.followee_id = article_favs.user_id
+                AND follows.follower_id = $2
+            INNER JOIN users ON users.id = article_favs.user_id
+            WHERE articles.slug = ANY($1)
+                AND (articles.published OR articles.author_id = $2)
+            ORDER BY articles.slug, users.username
+            "#,
+            &slugs[..],
+            user_id,
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        for row in rows {
+            profiles.entry(row.slug).or_default().push(row.profile);
+        }
+    }
+
+    Ok(Json(json!({ "profiles": profiles })))
 }
 
 #[derive(Deserialize)]
+pub struct CreateArticle {
+    article: CreateArticleData,
+}
+
+#[derive(Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+struct CreateArticleData {
+    #[validate(length(min = 1, message = "title can't be blank"))]
+    title: String,
+    #[validate(length(min = 1, message = "description can't be blank"))]
+    description: String,
+    #[validate(length(min = 1, message = "body can't be blank"))]
+    body: String,
+    #[serde(default)]
+    tag_list: Vec<String>,
+    /// `false` saves a draft that stays out of listings until it's published.
+    #[serde(default = "default_published")]
+    published: bool,
+}
+
+fn default_published() -> bool {
+    true
+}
+
+/// Longest tag accepted, in characters.
+const MAX_TAG_LENGTH: usize = 32;
+
+/// Rejects tag lists longer than the configured `max_tags` or containing over-long tags.
+fn validate_tags(tags: &[String], config: &Config) -> AppResult<()> {
+    if tags.len() > config.max_tags {
+        Err(validation_error("tagList", "has too many tags"))?
+    }
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+        Err(validation_error(
+            "tagList",
+            "tags can't be longer than 32 characters",
+        ))?
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateArticleQuery {
+    /// Skips the near-duplicate title check.
+    #[serde(default)]
+    force: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ArticleWarning {
+    message: String,
+    slug: String,
+}
+
+/// Trigram similarity above which a title counts as a near-duplicate of another.
+const DUPLICATE_TITLE_SIMILARITY: f32 = 0.6;
+
+/// The author's existing articles whose titles are close to `title`, most similar first.
+async fn similar_title_warnings(
+    pool: &PgPool,
+    user_id: UserId,
+    title: &str,
+) -> AppResult<Vec<ArticleWarning>> {
+    let similar = sqlx::query!(
+        "
+        SELECT slug, title
+        FROM articles
+        WHERE author_id = $1 AND similarity(title, $2) > $3
+        ORDER BY similarity(title, $2) DESC
+        LIMIT 3
+        ",
+        user_id,
+        title,
+        DUPLICATE_TITLE_SIMILARITY,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(similar
+        .into_iter()
+        .map(|article| ArticleWarning {
+            message: format!(
+                "title is very similar to your article \"{}\"",
+                article.title
+            ),
+            slug: article.slug,
+        })
+        .collect())
+}
+
+/// Near-duplicate titles don't block creation; they come back as `warnings` next to the article.
+pub async fn create_article(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    State(config): State<Config>,
+    Query(query): Query<CreateArticleQuery>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    Json(CreateArticle { article }): Json<CreateArticle>,
+) -> AppResult<impl IntoResponse> {
+    article.validate()?;
+    validate_tags(&article.tag_list, &config)?;
+
+    let user_id = verify_token(&token.0, &key)?;
+
+    let warnings = if query.force == Some(true) {
+        vec![]
+    } else {
+        similar_title_warnings(&pool, user_id, &article.title).await?
+    };
+
+    let slug = slug::slugify(&article.title);
+    let tags = article.tag_list;
+
+    // Dropping the transaction on an early return rolls back, so a failure never leaves an
+    // article without its tags.
+    let mut tx = pool.begin().await?;
+
+    let mut article: Article = sqlx::query_as!(
+        Article,
+        r#"
+            WITH article AS (
+                INSERT INTO articles (slug, title, description, body, author_id, published)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+            )
+            SELECT
+                article.id,
+                article.slug,
+                article.title,
+                article.description,
+                article.body AS "body?",
+                article.created_at,
+                article.updated_at,
+                article.published,
+                article.version AS "version?",
+                FALSE AS "favorited!",
+                '{}'::VARCHAR[] AS "tag_list!",
+                CAST(0 as INT8) AS "favorites_count!",
+                CAST(0 as INT8) AS "comments_count!",
+                CAST(0 as INT8) AS "claps_total!",
+                0 AS "claps_from_me!",
+                (
+                    users.id,
+                    users.username,
+                    users.bio,
+                    users.image,
+                    EXISTS (
+                        SELECT 1 FROM follows
+                        WHERE follows.follower_id = $5
+                        AND follows.followee_id = users.id
+                    )
+                ) AS "author!: UserProfile"
+            FROM article
+            INNER JOIN users ON users.id = article.author_id
+        "#,
+        slug,
+        article.title,
+        article.description,
+        article.body,
+        user_id,
+        article.published,
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO tags (name)
+        SELECT * FROM UNNEST($1::TEXT[])
+        ON CONFLICT DO NOTHING
+        ",
+        &tags[..]
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO article_tags (article_id, tag_id)
+        SELECT $1, tags.id FROM tags WHERE tags.name = ANY($2)
+        ",
+        article.id,
+        &tags[..],
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    article.tag_list = tags;
+
+    if warnings.is_empty() {
+        Ok(Json(json!({ "article": article })))
+    } else {
+        Ok(Json(json!({ "article": article, "warnings": warnings })))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateArticle {
+    article: UpdateArticleData,
+}
+
+/// Fields left out stay as they are; fields that are present may not be blank.
+#[derive(Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
 struct UpdateArticleData {
     #[serde(default)]
+    #[validate(custom(function = "not_blank", message = "title can't be blank"))]
     title: Option<String>,
     #[serde(default)]
+    #[validate(custom(function = "not_blank", message = "description can't be blank"))]
     description: Option<String>,
     #[serde(default)]
+    #[validate(custom(function = "not_blank", message = "body can't be blank"))]
     body: Option<String>,
+    /// `true` publishes a draft; `false` takes an article back to drafts.
+    #[serde(default)]
+    published: Option<bool>,
+    /// Replaces the article's tags; an empty list removes them all.
+    #[serde(default)]
+    tag_list: Option<Vec<String>>,
+    /// The `version` the client read. The update fails with 409 if someone saved in between.
+    version: i32,
+}
+
+fn not_blank(value: &str) -> Result<(), validator::ValidationError> {
+    if value.trim().is_empty() {
+        return Err(validator::ValidationError::new("blank"));
+    }
+    Ok(())
 }
 
 pub async fn update_article(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
+    State(config): State<Config>,
     Path(slug): Path<String>,
     TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
     Json(UpdateArticle { article }): Json<UpdateArticle>,
 ) -> AppResult<impl IntoResponse> {
+    article.validate()?;
+    if let Some(tags) = &article.tag_list {
+        validate_tags(tags, &config)?;
+    }
+
     let user_id = verify_token(&token.0, &key)?;
 
-    let article: Article = sqlx::query_as!(
+    // The article and its tags change together or not at all.
+    let mut tx = pool.begin().await?;
+
+    // Needed to tell whether this update publishes or unpublishes the article; locked so a
+    // concurrent update can't change it in between.
+    let was_published = match article.published {
+        Some(_) => {
+            sqlx::query_scalar!(
+                "SELECT published FROM articles WHERE slug = $1 AND author_id = $2 FOR UPDATE",
+                slug,
+                user_id,
+            )
+            .fetch_optional(&mut tx)
+            .await?
+        }
+        None => None,
+    };
+
+    let updated: Option<Article> = sqlx::query_as!(
         Article,
         r#"
         WITH article AS (
@@ -805,8 +2299,13 @@ pub async fn update_article(
             SET
                 title = COALESCE($1, title),
                 description = COALESCE($2, description),
-                body = COALESCE($3, body)
-            WHERE slug = $4 AND author_id = $5
+                body = COALESCE($3, body),
+                published = COALESCE($6, published),
+                version = version + 1,
+                -- A draft is dated by when it's published, so it shows up as new in listings.
+                created_at = CASE WHEN $6 AND NOT published THEN NOW() ELSE created_at END,
+                updated_at = NOW()
+            WHERE slug = $4 AND author_id = $5 AND version = $7
             RETURNING *
         )
         SELECT
@@ -814,9 +2313,11 @@ pub async fn update_article(
             article.slug,
             article.title,
             article.description,
-            article.body,
+            article.body AS "body?",
             article.created_at,
             article.updated_at,
+            article.published,
+            article.version AS "version?",
             COALESCE(
                 (SELECT
                     array_agg(tags.name ORDER BY tags.name ASC)
@@ -835,6 +2336,28 @@ pub async fn update_article(
                 FROM article_favs
                 WHERE article_favs.article_id = article.id
             ) AS "favorites_count!",    
+            COALESCE(
+                (SELECT COUNT(*)
+                    FROM comments
+                    WHERE comments.article_id = article.id
+                ),
+                0
+            ) AS "comments_count!",
+            COALESCE(
+                (SELECT SUM(claps.count)
+                    FROM claps
+                    WHERE claps.article_id = article.id
+                ),
+                0
+            ) AS "claps_total!",
+            COALESCE(
+                (SELECT claps.count
+                    FROM claps
+                    WHERE claps.article_id = article.id
+                    AND claps.user_id = $5
+                ),
+                0
+            ) AS "claps_from_me!",
             (
                 users.id,
                 users.username,
@@ -854,11 +2377,99 @@ pub async fn update_article(
         article.body,
         slug,
         user_id,
+        article.published,
+        article.version,
     )
-    .fetch_one(&mut pool.acquire().await.unwrap())
+    .fetch_optional(&mut tx)
     .await?;
 
-    Ok(Json(json!({ "article": article })))
+    let Some(mut updated) = updated else {
+        // Nothing matched: either the article isn't the caller's to edit, or it moved on.
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM articles WHERE slug = $1 AND author_id = $2) AS "exists!""#,
+            slug,
+            user_id,
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        if exists {
+            Err(AppError::ConflictError(json!({
+                "article": ["was modified, please reload"]
+            })))?
+        }
+        Err(AppError::NotFoundError(json!({ "article": ["not found"] })))?
+    };
+
+    if let Some(mut tags) = article.tag_list {
+        sqlx::query!(
+            "
+            INSERT INTO tags (name)
+            SELECT * FROM UNNEST($1::TEXT[])
+            ON CONFLICT DO NOTHING
+            ",
+            &tags[..]
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            "
+            DELETE FROM article_tags
+            WHERE article_id = $1 AND tag_id NOT IN (SELECT id FROM tags WHERE name = ANY($2))
+            ",
+            updated.id,
+            &tags[..],
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO article_tags (article_id, tag_id)
+            SELECT $1, tags.id FROM tags WHERE tags.name = ANY($2)
+            ON CONFLICT DO NOTHING
+            ",
+            updated.id,
+            &tags[..],
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // Same order as the tag lists read back from the database.
+        tags.sort();
+        tags.dedup();
+        updated.tag_list = tags;
+    }
+
+    // To clients syncing with `updated_since`, unpublishing looks like a deletion. Republishing
+    // makes the article an ordinary update again.
+    match (was_published, updated.published) {
+        (Some(true), false) => {
+            sqlx::query!(
+                "
+                INSERT INTO article_tombstones (id, slug)
+                VALUES ($1, $2)
+                ON CONFLICT (id) DO UPDATE
+                    SET (slug, deleted_at) = (EXCLUDED.slug, NOW())
+                ",
+                updated.id,
+                updated.slug,
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+        (Some(false), true) => {
+            sqlx::query!("DELETE FROM article_tombstones WHERE id = $1", updated.id)
+                .execute(&mut tx)
+                .await?;
+        }
+        _ => {}
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(json!({ "article": updated })))
 }
 
 pub async fn delete_article(
@@ -871,13 +2482,18 @@ pub async fn delete_article(
 
     sqlx::query!(
         "
-        DELETE FROM articles
-        WHERE slug = $1 AND author_id = $2
+        WITH deleted AS (
+            DELETE FROM articles
+            WHERE slug = $1 AND author_id = $2
+            RETURNING id, slug
+        )
+        INSERT INTO article_tombstones (id, slug)
+        SELECT id, slug FROM deleted
         ",
         slug,
         user_id
     )
-    .execute(&mut pool.acquire().await.unwrap())
+    .execute(&pool)
     .await?;
 
     Ok(Json(json!({})))
@@ -891,6 +2507,61 @@ struct Comment {
     updated_at: DateTime<Utc>,
     body: String,
     author: UserProfile,
+    /// Reaction counts keyed by emoji, omitted when nobody has reacted yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reactions: Option<SqlJson<BTreeMap<String, i64>>>,
+    /// The emojis the requesting user has reacted with.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    my_reactions: Vec<String>,
+    /// Set while the article's author has this comment pinned above the others.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pinned_at: Option<DateTime<Utc>>,
+    /// The comment this one replies to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_id: Option<i32>,
+}
+
+/// A comment with its replies, as returned by `get_comments` with `tree=true`.
+#[derive(Serialize)]
+pub struct CommentNode {
+    comment: Comment,
+    replies: Vec<CommentNode>,
+}
+
+/// Nests `comments` under their parents. Top-level comments keep their order; replies read
+/// oldest first. A reply whose parent isn't among `comments`, e.g. filtered out, becomes top-level.
+fn comment_tree(comments: Vec<Comment>) -> Vec<CommentNode> {
+    fn build(comment: Comment, children: &mut HashMap<i32, Vec<Comment>>) -> CommentNode {
+        let mut replies = children.remove(&comment.id).unwrap_or_default();
+        replies.sort_by_key(|reply| (reply.created_at, reply.id));
+
+        CommentNode {
+            replies: replies
+                .into_iter()
+                .map(|reply| build(reply, children))
+                .collect(),
+            comment,
+        }
+    }
+
+    let ids: HashSet<i32> = comments.iter().map(|comment| comment.id).collect();
+
+    let mut roots = vec![];
+    let mut children: HashMap<i32, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        match comment
+            .parent_id
+            .filter(|parent_id| ids.contains(parent_id))
+        {
+            Some(parent_id) => children.entry(parent_id).or_default().push(comment),
+            None => roots.push(comment),
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|comment| build(comment, &mut children))
+        .collect()
 }
 
 #[derive(Deserialize)]
@@ -899,8 +2570,29 @@ pub struct AddComment {
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct AddCommentData {
     body: String,
+    /// Makes the new comment a reply; must be a comment on the same article.
+    #[serde(default)]
+    parent_id: Option<i32>,
+}
+
+/// Renders a draft comment the way it will be shown once posted, without saving anything.
+pub async fn preview_comment(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(slug): Path<String>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    Json(AddComment { comment }): Json<AddComment>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    visible_article_id(&pool, &slug, Some(user_id)).await?;
+
+    Ok(Json(json!({
+        "preview": { "html": markdown::render_markdown(&comment.body) }
+    })))
 }
 
 pub async fn add_comment(
@@ -912,12 +2604,37 @@ pub async fn add_comment(
 ) -> AppResult<impl IntoResponse> {
     let user_id = verify_token(&token.0, &key)?;
 
+    visible_article_id(&pool, &slug, Some(user_id)).await?;
+
+    if let Some(parent_id) = comment.parent_id {
+        let parent_found = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM comments
+                WHERE comments.id = $1
+                AND comments.article_id = (SELECT id FROM articles WHERE slug = $2)
+            ) AS "exists!"
+            "#,
+            parent_id,
+            slug,
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        if !parent_found {
+            Err(validation_error(
+                "parentId",
+                "must be a comment on the same article",
+            ))?
+        }
+    }
+
     let comment: Comment = sqlx::query_as!(
         Comment,
         r#"
         WITH comment AS (
-            INSERT INTO comments (body, article_id, author_id)
-            VALUES ($1, (SELECT id FROM articles WHERE slug = $2), $3)
+            INSERT INTO comments (body, article_id, author_id, parent_id)
+            VALUES ($1, (SELECT id FROM articles WHERE slug = $2), $3, $4)
             RETURNING *
         )
         SELECT
@@ -935,28 +2652,31 @@ pub async fn add_comment(
                     WHERE follows.follower_id = $3
                     AND follows.followee_id = users.id
                 ))
-            ) AS "author!: UserProfile"
+            ) AS "author!: UserProfile",
+            NULL::JSONB AS "reactions: SqlJson<BTreeMap<String, i64>>",
+            '{}'::VARCHAR[] AS "my_reactions!",
+            comment.pinned_at,
+            comment.parent_id
         FROM comment INNER JOIN users ON users.id = comment.author_id
         "#,
         comment.body,
         slug,
         user_id,
+        comment.parent_id,
     )
-    .fetch_one(&mut pool.acquire().await.unwrap())
+    .fetch_one(&pool)
     .await?;
 
     Ok(Json(json!({ "comment": comment })))
 }
 
-pub async fn get_comments(
-    State(pool): State<PgPool>,
-    State(key): State<DecodingKey>,
-    Path(slug): Path<String>,
-    token: Option<TypedHeader<Authorization<JWTToken>>>,
-) -> AppResult<impl IntoResponse> {
-    let user_id = token
-        .map(|token| verify_token(&token.0 .0 .0, &key))
-        .transpose()?;
+async fn fetch_comments(
+    pool: &PgPool,
+    slug: &str,
+    user_id: Option<UserId>,
+    comment_id: Option<i32>,
+    author: Option<&str>,
+) -> AppResult<Vec<Comment>> {
     let comments: Vec<Comment> = sqlx::query_as!(
         Comment,
         r#"
@@ -975,52 +2695,406 @@ pub async fn get_comments(
                     WHERE follows.follower_id = $2
                     AND follows.followee_id = users.id
                 ))
-            ) AS "author!: UserProfile"
+            ) AS "author!: UserProfile",
+            (SELECT jsonb_object_agg(counts.emoji, counts.count)
+                FROM (
+                    SELECT comment_reactions.emoji, COUNT(*) AS count
+                    FROM comment_reactions
+                    WHERE comment_reactions.comment_id = comments.id
+                    GROUP BY comment_reactions.emoji
+                ) AS counts
+            ) AS "reactions: SqlJson<BTreeMap<String, i64>>",
+            COALESCE(
+                (SELECT array_agg(comment_reactions.emoji ORDER BY comment_reactions.emoji)
+                    FROM comment_reactions
+                    WHERE comment_reactions.comment_id = comments.id
+                    AND comment_reactions.user_id = $2
+                ),
+                '{}'::VARCHAR[]
+            ) AS "my_reactions!",
+            comments.pinned_at,
+            comments.parent_id
         FROM comments
         INNER JOIN users ON users.id = comments.author_id
-        WHERE comments.article_id = (SELECT id FROM articles WHERE slug = $1)
-        ORDER BY comments.created_at DESC
+        WHERE comments.article_id = (
+                SELECT id FROM articles WHERE slug = $1 AND (published OR author_id = $2)
+            )
+            AND ($3::INT4 IS NULL OR comments.id = $3)
+            AND ($4::VARCHAR IS NULL OR users.username = $4)
+        ORDER BY comments.pinned_at IS NULL, comments.created_at DESC
         "#,
         slug,
         user_id,
+        comment_id,
+        author,
     )
-    .fetch_all(&mut pool.acquire().await.unwrap())
+    .fetch_all(pool)
     .await?;
 
+    Ok(comments)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListCommentsQuery {
+    #[serde(default)]
+    author: Option<String>,
+    /// Nest replies under their parents instead of listing every comment flat.
+    #[serde(default)]
+    tree: bool,
+}
+
+pub async fn get_comments(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(slug): Path<String>,
+    Query(query): Query<ListCommentsQuery>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = token
+        .map(|token| verify_token(&token.0 .0 .0, &key))
+        .transpose()?;
+
+    visible_article_id(&pool, &slug, user_id).await?;
+
+    let comments = fetch_comments(&pool, &slug, user_id, None, query.author.as_deref()).await?;
+
+    if query.tree {
+        return Ok(Json(json!({ "comments": comment_tree(comments) })));
+    }
+
     Ok(Json(json!({ "comments": comments })))
 }
 
 #[derive(Deserialize)]
-pub struct DeleteCommentPath {
+pub struct CountComments {
+    slugs: Vec<String>,
+}
+
+/// Most slugs a single `count_comments` request may ask about.
+const COUNT_COMMENTS_MAX_SLUGS: usize = 100;
+
+/// Comment counts for several articles at once, keyed by slug. Unknown slugs and drafts are left
+/// out.
+pub async fn count_comments(
+    State(pool): State<PgPool>,
+    Json(CountComments { slugs }): Json<CountComments>,
+) -> AppResult<impl IntoResponse> {
+    if slugs.len() > COUNT_COMMENTS_MAX_SLUGS {
+        Err(validation_error("slugs", "too many slugs"))?
+    }
+
+    let mut counts = BTreeMap::new();
+
+    if !slugs.is_empty() {
+        let rows = sqlx::query!(
+            r#"
+            SELECT articles.slug, COUNT(comments.id) AS "count!"
+            FROM articles
+            LEFT JOIN comments ON comments.article_id = articles.id
+            WHERE articles.slug = ANY($1) AND articles.published
+            GROUP BY articles.slug
+            "#,
+            &slugs[..],
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        counts.extend(rows.into_iter().map(|row| (row.slug, row.count)));
+    }
+
+    Ok(Json(json!({ "counts": counts })))
+}
+
+#[derive(Deserialize)]
+pub struct CommentPath {
     slug: String,
     id: i32,
 }
 
-pub async fn delete_comment(
+#[derive(Deserialize)]
+pub struct EditComment {
+    comment: EditCommentData,
+}
+
+#[derive(Deserialize)]
+struct EditCommentData {
+    body: String,
+}
+
+/// Replaces a comment's text. Unlike deleting, only the comment's own author may do this.
+pub async fn edit_comment(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
-    Path(DeleteCommentPath { slug, id }): Path<DeleteCommentPath>,
+    Path(CommentPath { slug, id }): Path<CommentPath>,
     TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    Json(EditComment { comment }): Json<EditComment>,
 ) -> AppResult<impl IntoResponse> {
     let user_id = verify_token(&token.0, &key)?;
 
-    sqlx::query!(
+    if comment.body.trim().is_empty() {
+        Err(validation_error("body", "can't be blank"))?
+    }
+
+    let author_id = sqlx::query_scalar!(
         "
-        DELETE FROM comments
-        WHERE comments.id = $1
-            AND comments.article_id = (SELECT id FROM articles WHERE slug = $2)
-            AND comments.author_id = $3
+        SELECT comments.author_id
+        FROM comments
+        INNER JOIN articles ON articles.id = comments.article_id
+        WHERE comments.id = $1 AND articles.slug = $2
+            AND (articles.published OR articles.author_id = $3)
         ",
         id,
         slug,
         user_id,
     )
-    .execute(&mut pool.acquire().await.unwrap())
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))?;
+
+    if author_id != user_id {
+        Err(AppError::ForbiddenError(json!({
+            "comment": "can only be edited by its author"
+        })))?
+    }
+
+    sqlx::query!(
+        "UPDATE comments SET body = $1, updated_at = NOW() WHERE id = $2",
+        comment.body,
+        id,
+    )
+    .execute(&pool)
     .await?;
 
+    // Missing only if the article went back to drafts since the check above.
+    let comment = fetch_comments(&pool, &slug, Some(user_id), Some(id), None)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))?;
+
+    Ok(Json(json!({ "comment": comment })))
+}
+
+pub async fn delete_comment(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(CommentPath { slug, id }): Path<CommentPath>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let authors = sqlx::query!(
+        "
+        SELECT comments.author_id, articles.author_id AS article_author_id
+        FROM comments
+        INNER JOIN articles ON articles.id = comments.article_id
+        WHERE comments.id = $1 AND articles.slug = $2
+        ",
+        id,
+        slug,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))?;
+
+    // Either the comment's author or the author of the article it was posted on may delete it.
+    if user_id != authors.author_id && user_id != authors.article_author_id {
+        Err(AppError::ForbiddenError(json!({
+            "comment": "can only be deleted by its author or the article's author"
+        })))?
+    }
+
+    sqlx::query!("DELETE FROM comments WHERE id = $1", id)
+        .execute(&pool)
+        .await?;
+
     Ok(Json(json!({})))
 }
 
+/// The id of the article at `slug`, or 404 unless `user_id` may see it: drafts are only visible
+/// to their author.
+async fn visible_article_id(pool: &PgPool, slug: &str, user_id: Option<UserId>) -> AppResult<i32> {
+    sqlx::query_scalar!(
+        "SELECT id FROM articles WHERE slug = $1 AND (published OR author_id = $2)",
+        slug,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "article": ["not found"] })))
+}
+
+/// The author of the article comment `id` was posted on, or 404 if it isn't on `slug`.
+async fn comment_article_author(pool: &PgPool, slug: &str, id: i32) -> AppResult<UserId> {
+    sqlx::query_scalar!(
+        "
+        SELECT articles.author_id
+        FROM comments
+        INNER JOIN articles ON articles.id = comments.article_id
+        WHERE comments.id = $1 AND articles.slug = $2
+        ",
+        id,
+        slug,
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))
+}
+
+/// Pins the comment above all others on its article, replacing the one pinned before.
+/// Only the article's author may pin.
+pub async fn pin_comment(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(CommentPath { slug, id }): Path<CommentPath>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    if comment_article_author(&pool, &slug, id).await? != user_id {
+        Err(AppError::ForbiddenError(json!({
+            "comment": "can only be pinned by the article's author"
+        })))?
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Concurrent pins on the article take turns, so the second one unpins the first instead of
+    // both clearing the old pin and then colliding on the one-pin-per-article index.
+    sqlx::query!("SELECT id FROM articles WHERE slug = $1 FOR UPDATE", slug)
+        .fetch_one(&mut tx)
+        .await?;
+
+    sqlx::query!(
+        "
+        UPDATE comments
+        SET pinned_at = NULL
+        FROM articles
+        WHERE comments.article_id = articles.id
+            AND articles.slug = $1
+            AND comments.pinned_at IS NOT NULL
+        ",
+        slug,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!("UPDATE comments SET pinned_at = NOW() WHERE id = $1", id)
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await?;
+
+    let comment = fetch_comments(&pool, &slug, Some(user_id), Some(id), None)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))?;
+
+    Ok(Json(json!({ "comment": comment })))
+}
+
+pub async fn unpin_comment(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(CommentPath { slug, id }): Path<CommentPath>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    if comment_article_author(&pool, &slug, id).await? != user_id {
+        Err(AppError::ForbiddenError(json!({
+            "comment": "can only be unpinned by the article's author"
+        })))?
+    }
+
+    sqlx::query!("UPDATE comments SET pinned_at = NULL WHERE id = $1", id)
+        .execute(&pool)
+        .await?;
+
+    let comment = fetch_comments(&pool, &slug, Some(user_id), Some(id), None)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))?;
+
+    Ok(Json(json!({ "comment": comment })))
+}
+
+#[derive(Deserialize)]
+pub struct ReactComment {
+    reaction: ReactCommentData,
+}
+
+#[derive(Deserialize, Validate)]
+struct ReactCommentData {
+    #[validate(
+        length(min = 1, message = "emoji can't be blank"),
+        length(max = 8, message = "too long emoji")
+    )]
+    emoji: String,
+}
+
+/// Toggles the requesting user's reaction: reacting twice with the same emoji removes it.
+pub async fn react_comment(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(CommentPath { slug, id }): Path<CommentPath>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    Json(ReactComment { reaction }): Json<ReactComment>,
+) -> AppResult<impl IntoResponse> {
+    reaction.validate()?;
+
+    let user_id = verify_token(&token.0, &key)?;
+
+    let removed = sqlx::query!(
+        "
+        DELETE FROM comment_reactions
+        USING comments, articles
+        WHERE (comment_reactions.comment_id, comment_reactions.user_id, comment_reactions.emoji)
+                = ($1, $2, $3)
+            AND comments.id = comment_reactions.comment_id
+            AND articles.id = comments.article_id
+            AND articles.slug = $4
+            AND (articles.published OR articles.author_id = $2)
+        ",
+        id,
+        user_id,
+        reaction.emoji,
+        slug,
+    )
+    .execute(&pool)
+    .await?;
+
+    if removed.rows_affected() == 0 {
+        sqlx::query!(
+            "
+            INSERT INTO comment_reactions (comment_id, user_id, emoji)
+            SELECT comments.id, $2, $3
+                FROM comments
+                INNER JOIN articles ON articles.id = comments.article_id
+                WHERE comments.id = $1 AND articles.slug = $4
+                    AND (articles.published OR articles.author_id = $2)
+            ON CONFLICT DO NOTHING
+            ",
+            id,
+            user_id,
+            reaction.emoji,
+            slug,
+        )
+        .execute(&pool)
+        .await?;
+    }
+
+    let comment = fetch_comments(&pool, &slug, Some(user_id), Some(id), None)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFoundError(json!({ "comment": ["not found"] })))?;
+
+    Ok(Json(json!({ "comment": comment })))
+}
+
 pub async fn favorite_article(
     State(pool): State<PgPool>,
     State(key): State<DecodingKey>,
@@ -1029,6 +3103,9 @@ pub async fn favorite_article(
 ) -> AppResult<impl IntoResponse> {
     let user_id = verify_token(&token.0, &key)?;
 
+    // The returned article must reflect this change, not whatever a concurrent request did since.
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         "
         INSERT INTO article_favs (article_id, user_id)
@@ -1039,10 +3116,11 @@ pub async fn favorite_article(
         slug,
         user_id
     )
-    .execute(&mut pool.acquire().await.unwrap())
+    .execute(&mut tx)
     .await?;
 
-    let article = get_article_by_slug(&pool, &slug, Some(user_id)).await?;
+    let article = get_article_by_slug(&mut tx, &slug, Some(user_id)).await?;
+    tx.commit().await?;
 
     Ok(Json(json!({ "article": article })))
 }
@@ -1055,6 +3133,8 @@ pub async fn unfavorite_article(
 ) -> AppResult<impl IntoResponse> {
     let user_id = verify_token(&token.0, &key)?;
 
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         "
         DELETE FROM article_favs
@@ -1067,10 +3147,89 @@ pub async fn unfavorite_article(
         slug,
         user_id,
     )
-    .execute(&mut pool.acquire().await.unwrap())
+    .execute(&mut tx)
     .await?;
 
-    let article = get_article_by_slug(&pool, &slug, Some(user_id)).await?;
+    let article = get_article_by_slug(&mut tx, &slug, Some(user_id)).await?;
+    tx.commit().await?;
+
+    Ok(Json(json!({ "article": article })))
+}
+
+/// Most claps one reader can give a single article, however many times they clap.
+const MAX_CLAPS_PER_USER: i32 = 50;
+
+#[derive(Deserialize, Default)]
+pub struct Clap {
+    #[serde(default)]
+    clap: ClapData,
+}
+
+#[derive(Deserialize)]
+struct ClapData {
+    /// Claps to add; several taps in quick succession can be sent together.
+    #[serde(default = "ClapData::default_count")]
+    count: i32,
+}
+
+impl ClapData {
+    fn default_count() -> i32 {
+        1
+    }
+}
+
+impl Default for ClapData {
+    fn default() -> Self {
+        Self {
+            count: Self::default_count(),
+        }
+    }
+}
+
+/// Adds claps from the user to the article. Unlike favoriting this can be repeated; once the
+/// user's total reaches [`MAX_CLAPS_PER_USER`] further claps are ignored rather than rejected.
+pub async fn clap_article(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    Path(slug): Path<String>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    body: Option<Json<Clap>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = verify_token(&token.0, &key)?;
+
+    let Json(Clap { clap }) = body.unwrap_or_default();
+    if clap.count < 1 {
+        Err(validation_error("count", "must be at least 1"))?
+    }
+    let count = clap.count.min(MAX_CLAPS_PER_USER);
+
+    // Claps accumulate per reader, so the upsert adds to the stored count and caps the total;
+    // the article is read back in the same transaction so the caller sees where it landed.
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query!(
+        "
+        INSERT INTO claps (article_id, user_id, count)
+        SELECT articles.id, $2, $3
+            FROM articles
+            WHERE articles.slug = $1 AND articles.published
+        ON CONFLICT (article_id, user_id)
+            DO UPDATE SET count = LEAST(claps.count + EXCLUDED.count, $4)
+        ",
+        slug,
+        user_id,
+        count,
+        MAX_CLAPS_PER_USER,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        Err(AppError::NotFoundError(json!({ "article": ["not found"] })))?
+    }
+
+    let article = get_article_by_slug(&mut tx, &slug, Some(user_id)).await?;
+    tx.commit().await?;
 
     Ok(Json(json!({ "article": article })))
 }
@@ -1086,12 +3245,14 @@ pub async fn get_tags(State(pool): State<PgPool>) -> AppResult<impl IntoResponse
         SELECT tags.name
         FROM tags
         INNER JOIN article_tags ON article_tags.tag_id = tags.id
+        INNER JOIN articles ON articles.id = article_tags.article_id
+        WHERE articles.published
         GROUP BY tags.name
         ORDER BY COUNT(article_tags.tag_id) DESC
         LIMIT 10
         "
     )
-    .fetch_all(&mut pool.acquire().await.unwrap())
+    .fetch_all(&pool)
     .await?;
 
     let tags = tags
@@ -1101,3 +3262,50 @@ pub async fn get_tags(State(pool): State<PgPool>) -> AppResult<impl IntoResponse
 
     Ok(Json(json!({ "tags": tags })))
 }
+
+#[derive(Deserialize)]
+pub struct RecordEvent {
+    event: RecordEventData,
+}
+
+#[derive(Deserialize, Validate)]
+struct RecordEventData {
+    #[validate(
+        length(min = 1, message = "kind can't be blank"),
+        length(max = 32, message = "too long kind")
+    )]
+    kind: String,
+    #[validate(
+        length(min = 1, message = "route can't be blank"),
+        length(max = 255, message = "too long route")
+    )]
+    route: String,
+}
+
+/// Records a client-side analytics event (e.g. a page view) for the current user, if any.
+pub async fn record_event(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    token: Option<TypedHeader<Authorization<JWTToken>>>,
+    Json(RecordEvent { event }): Json<RecordEvent>,
+) -> AppResult<impl IntoResponse> {
+    event.validate()?;
+
+    let user_id = token
+        .map(|token| verify_token(&token.0 .0 .0, &key))
+        .transpose()?;
+
+    sqlx::query!(
+        "
+        INSERT INTO events (user_id, kind, route)
+        VALUES ($1, $2, $3)
+        ",
+        user_id,
+        event.kind,
+        event.route,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(json!({})))
+}
@@ -1,5 +1,5 @@
 use axum::headers::authorization::Credentials;
-use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+use jsonwebtoken::{encode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 
 use crate::{api::UserId, error::AppResult};
@@ -10,8 +10,13 @@ pub struct Claims {
     exp: i64,
 }
 
-pub fn generate_jwt(user_id: UserId, key: &EncodingKey) -> AppResult<String> {
-    let exp = (chrono::Utc::now() + chrono::Duration::days(30)).timestamp();
+/// Signs a token for `user_id` that expires `ttl` from now.
+pub fn generate_jwt(
+    user_id: UserId,
+    key: &EncodingKey,
+    ttl: chrono::Duration,
+) -> AppResult<String> {
+    let exp = (chrono::Utc::now() + ttl).timestamp();
 
     let claims = Claims { user_id, exp };
 
@@ -23,9 +28,36 @@ pub fn generate_jwt(user_id: UserId, key: &EncodingKey) -> AppResult<String> {
 pub fn verify_jwt(token: &str, key: &DecodingKey) -> AppResult<Claims> {
     let header = jsonwebtoken::decode_header(token)?;
 
-    let claims =
-        jsonwebtoken::decode::<Claims>(token, key, &jsonwebtoken::Validation::new(header.alg))?
-            .claims;
+    // Tokens are only ever checked by the server that signed them, so there is no clock skew
+    // to allow for, and a short `token_ttl_days` shouldn't silently gain another minute.
+    let mut validation = jsonwebtoken::Validation::new(header.alg);
+    validation.leeway = 0;
+
+    let claims = jsonwebtoken::decode::<Claims>(token, key, &validation)?.claims;
+    Ok(claims)
+}
+
+/// Like `verify_jwt`, but also accepts a token that expired at most `grace` ago, so a
+/// session can be refreshed shortly after it lapsed. Only expiry is forgiven: a bad
+/// signature or a malformed token fails exactly as in `verify_jwt`.
+pub fn verify_jwt_allow_expired(
+    token: &str,
+    key: &DecodingKey,
+    grace: chrono::Duration,
+) -> AppResult<Claims> {
+    let header = jsonwebtoken::decode_header(token)?;
+
+    let mut validation = jsonwebtoken::Validation::new(header.alg);
+    validation.validate_exp = false;
+
+    let claims = jsonwebtoken::decode::<Claims>(token, key, &validation)?.claims;
+
+    if claims.exp < (chrono::Utc::now() - grace).timestamp() {
+        Err(jsonwebtoken::errors::Error::from(
+            ErrorKind::ExpiredSignature,
+        ))?
+    }
+
     Ok(claims)
 }
 
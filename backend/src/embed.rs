@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    error::{validation_error, AppError, AppResult},
+    Config,
+};
+
+/// Size of the embed card when the consumer doesn't ask for less.
+const DEFAULT_WIDTH: u32 = 600;
+const DEFAULT_HEIGHT: u32 = 200;
+
+/// Avatars are shown at this size in the card, so that's the thumbnail size we report.
+const THUMBNAIL_SIZE: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct EmbedQuery {
+    #[serde(default)]
+    maxwidth: Option<u32>,
+    #[serde(default)]
+    maxheight: Option<u32>,
+    /// Only `json` is offered; oEmbed consumers may still send the parameter.
+    #[serde(default)]
+    format: Option<String>,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An oEmbed (<https://oembed.com>) `rich` response for the article: a small card linking back
+/// to it. Drafts aren't embeddable and answer 404 like unknown slugs.
+pub async fn embed_article(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+    Path(slug): Path<String>,
+    Query(query): Query<EmbedQuery>,
+) -> AppResult<impl IntoResponse> {
+    if query
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        Err(validation_error("format", "only json is supported"))?
+    }
+
+    let article = sqlx::query!(
+        "
+        SELECT articles.title, articles.description, users.username, users.image
+        FROM articles
+        INNER JOIN users ON articles.author_id = users.id
+        WHERE articles.slug = $1 AND articles.published
+        ",
+        slug
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "article": ["not found"] })))?;
+
+    let base = config.public_url.trim_end_matches('/');
+    let url = format!("{base}/#/article/{slug}");
+    let author_url = format!("{base}/#/profile/{}", article.username);
+
+    let width = query
+        .maxwidth
+        .map_or(DEFAULT_WIDTH, |max| max.min(DEFAULT_WIDTH));
+    let height = query
+        .maxheight
+        .map_or(DEFAULT_HEIGHT, |max| max.min(DEFAULT_HEIGHT));
+
+    let html = format!(
+        r#"<blockquote class="conduit-embed" style="max-width: {width}px"><a href="{url}">{title}</a><p>{description}</p><p>by <a href="{author_url}">{author}</a></p></blockquote>"#,
+        url = escape_html(&url),
+        title = escape_html(&article.title),
+        description = escape_html(&article.description),
+        author_url = escape_html(&author_url),
+        author = escape_html(&article.username),
+    );
+
+    let mut embed = json!({
+        "version": "1.0",
+        "type": "rich",
+        "title": article.title,
+        "author_name": article.username,
+        "author_url": author_url,
+        "provider_name": "Conduit",
+        "provider_url": format!("{base}/"),
+        "url": url,
+        "html": html,
+        "width": width,
+        "height": height,
+    });
+    // The spec wants all three thumbnail fields or none of them.
+    if let Some(image) = article.image.filter(|image| !image.is_empty()) {
+        embed["thumbnail_url"] = json!(image);
+        embed["thumbnail_width"] = json!(THUMBNAIL_SIZE);
+        embed["thumbnail_height"] = json!(THUMBNAIL_SIZE);
+    }
+
+    Ok(Json(embed))
+}
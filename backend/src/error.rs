@@ -9,12 +9,16 @@ use serde_json::json;
 pub enum AppError {
     #[error("Invalid request: {0:?}")]
     ValidationError(#[from] validator::ValidationErrors),
-    #[error("Authentication failed: {0:?}")]
-    AuthenticationError(password_hash::Error),
     #[error("JWT error: {0:?}")]
     JwtError(#[from] jsonwebtoken::errors::Error),
+    #[error("Unauthorized request")]
+    UnauthorizedError(serde_json::Value),
     #[error("Forbidden request")]
     ForbiddenError(serde_json::Value),
+    #[error("Not found")]
+    NotFoundError(serde_json::Value),
+    #[error("Conflict")]
+    ConflictError(serde_json::Value),
     #[error("SQL failed: {0:?}")]
     SqlxError(#[from] sqlx::Error),
     #[error("Any error: {0:?}")]
@@ -24,33 +28,53 @@ pub enum AppError {
 // Tell axum how to convert `AppError` into a response.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        log::error!("error: {}", self);
+        let request_id = crate::request_id::current();
+        log::error!(
+            "error: {} (request id {})",
+            self,
+            request_id.as_deref().unwrap_or("none")
+        );
 
-        match self {
-            Self::ValidationError(err) => (
-                StatusCode::UNPROCESSABLE_ENTITY,
-                Json(json!({ "error": err })),
-            ),
-            Self::AuthenticationError(err) => (
-                StatusCode::UNAUTHORIZED,
-                Json(json!({ "error": err.to_string() })),
-            ),
+        let (status, mut body) = match self {
+            Self::ValidationError(err) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, json!({ "error": err }))
+            }
             Self::JwtError(err) => (
                 StatusCode::UNAUTHORIZED,
-                Json(json!({ "error": err.to_string() })),
+                json!({ "error": err.to_string() }),
             ),
-            Self::ForbiddenError(err) => (StatusCode::FORBIDDEN, Json(json!({ "error": err }))),
+            Self::UnauthorizedError(err) => (StatusCode::UNAUTHORIZED, json!({ "error": err })),
+            Self::ForbiddenError(err) => (StatusCode::FORBIDDEN, json!({ "error": err })),
+            Self::NotFoundError(err) => (StatusCode::NOT_FOUND, json!({ "error": err })),
+            Self::ConflictError(err) => (StatusCode::CONFLICT, json!({ "error": err })),
             Self::SqlxError(err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": err.to_string() })),
+                json!({ "error": err.to_string() }),
             ),
             Self::Anyhow(err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": err.to_string() })),
+                json!({ "error": err.to_string() }),
             ),
+        };
+
+        // Lets a user's bug report be matched with the server logs.
+        if let Some(request_id) = request_id {
+            body["requestId"] = json!(request_id);
         }
-        .into_response()
+
+        (status, Json(body)).into_response()
     }
 }
 
 pub type AppResult<T> = std::result::Result<T, AppError>;
+
+/// Builds a 422 for a single field, shaped like the errors `validator` produces.
+pub fn validation_error(field: &'static str, message: &'static str) -> AppError {
+    let mut error = validator::ValidationError::new(field);
+    error.message = Some(message.into());
+
+    let mut errors = validator::ValidationErrors::new();
+    errors.add(field, error);
+
+    AppError::ValidationError(errors)
+}
@@ -1,29 +1,89 @@
 mod api;
 mod auth;
+mod embed;
 mod error;
+mod mail;
+mod markdown;
+mod metrics;
+mod ratelimit;
+mod request_id;
+#[cfg(test)]
+mod tests;
+mod toc;
+mod translate;
+mod upload;
 
-use std::path::PathBuf;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use api::prepare_db;
+use async_trait::async_trait;
 use axum::{
-    extract::FromRef,
-    http::StatusCode,
+    body::Body,
+    extract::{DefaultBodyLimit, FromRef},
+    http::{Request, StatusCode},
+    middleware,
     routing::{delete, get, get_service, post, put},
     Router,
 };
 use axum_extra::routing::SpaRouter;
 use jsonwebtoken::{DecodingKey, EncodingKey};
+use mail::Mailer;
+use metrics::Metrics;
+use ratelimit::RateLimiter;
 use shuttle_secrets::SecretStore;
 use shuttle_service::error::CustomError;
 use sqlx::PgPool;
 use sync_wrapper::SyncWrapper;
-use tower_http::{compression::CompressionLayer, services::ServeDir};
+use tower_http::{
+    compression::CompressionLayer,
+    services::ServeDir,
+    trace::{DefaultOnResponse, TraceLayer},
+    LatencyUnit,
+};
+use tracing::Level;
+use translate::Translator;
+
+/// Server settings that can be tuned through secrets.
+#[derive(Clone)]
+pub struct Config {
+    /// Page size for listings requested without `limit`.
+    pub default_page_size: usize,
+    /// How long newly issued tokens stay valid, in days.
+    pub token_ttl_days: u64,
+    /// Most tags a single article may have.
+    pub max_tags: usize,
+    /// Where the site is served from, for links handed to other sites such as oEmbed cards.
+    pub public_url: String,
+    /// Login and registration attempts allowed per client and minute.
+    pub auth_requests_per_minute: u32,
+    /// The reverse proxy in front of the server, if any. Only its `X-Forwarded-For` is used to
+    /// tell clients apart; everyone else is known by their own address.
+    pub trusted_proxy: Option<IpAddr>,
+}
+
+impl Config {
+    pub fn token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::days(self.token_ttl_days as i64)
+    }
+}
 
 #[derive(Clone)]
 struct AppState {
     pool: PgPool,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    metrics: Arc<Metrics>,
+    translator: Arc<dyn Translator>,
+    /// `None` when no `mailer` secret is set.
+    mailer: Option<Arc<dyn Mailer>>,
+    config: Config,
+    /// Served under `/images`; uploads are written here.
+    images_folder: PathBuf,
 }
 
 impl FromRef<AppState> for PgPool {
@@ -44,67 +104,268 @@ impl FromRef<AppState> for DecodingKey {
     }
 }
 
+impl FromRef<AppState> for Arc<Metrics> {
+    fn from_ref(app_state: &AppState) -> Arc<Metrics> {
+        app_state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Translator> {
+    fn from_ref(app_state: &AppState) -> Arc<dyn Translator> {
+        app_state.translator.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<dyn Mailer>> {
+    fn from_ref(app_state: &AppState) -> Option<Arc<dyn Mailer>> {
+        app_state.mailer.clone()
+    }
+}
+
+impl FromRef<AppState> for Config {
+    fn from_ref(app_state: &AppState) -> Config {
+        app_state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for PathBuf {
+    fn from_ref(app_state: &AppState) -> PathBuf {
+        app_state.images_folder.clone()
+    }
+}
+
 #[shuttle_service::main]
 async fn axum(
     #[shuttle_secrets::Secrets] secret_store: SecretStore,
     #[shuttle_static_folder::StaticFolder(folder = "images")] images_folder: PathBuf,
     #[shuttle_static_folder::StaticFolder(folder = "dist")] dist_folder: PathBuf,
     #[shuttle_aws_rds::Postgres] pool: PgPool,
-) -> shuttle_service::ShuttleAxum {
-    log::info!("xxx: 1");
+) -> Result<AxumService, shuttle_service::Error> {
     let private_key = secret_store.get("private_key").unwrap();
-    log::info!("xxx: 2");
     let public_key = secret_store.get("public_key").unwrap();
-    log::info!("xxx: 3");
 
     let encoding_key = EncodingKey::from_rsa_pem(private_key.as_bytes()).unwrap();
-    log::info!("xxx: 4");
     let decoding_key = DecodingKey::from_rsa_pem(public_key.as_bytes()).unwrap();
-    log::info!("xxx: 5");
 
     prepare_db(&pool).await.map_err(CustomError::new)?;
-    log::info!("xxx: 6");
 
-    let router = Router::new()
-        .route("/api/users/login", post(api::login))
-        .route("/api/users", post(api::registration))
+    let config = Config {
+        default_page_size: secret_store
+            .get("default_page_size")
+            .map(|size| size.parse())
+            .transpose()
+            .map_err(CustomError::new)?
+            .unwrap_or(20),
+        token_ttl_days: secret_store
+            .get("token_ttl_days")
+            .map(|days| days.parse())
+            .transpose()
+            .map_err(CustomError::new)?
+            .unwrap_or(30),
+        max_tags: secret_store
+            .get("max_tags")
+            .map(|max| max.parse())
+            .transpose()
+            .map_err(CustomError::new)?
+            .unwrap_or(10),
+        public_url: secret_store
+            .get("public_url")
+            .unwrap_or_else(|| "http://localhost:8000".to_string()),
+        auth_requests_per_minute: secret_store
+            .get("auth_requests_per_minute")
+            .map(|limit| limit.parse())
+            .transpose()
+            .map_err(CustomError::new)?
+            .unwrap_or(10),
+        trusted_proxy: secret_store
+            .get("trusted_proxy")
+            .map(|proxy| proxy.parse())
+            .transpose()
+            .map_err(CustomError::new)?,
+    };
+
+    let state = AppState {
+        pool,
+        encoding_key,
+        decoding_key,
+        metrics: Arc::new(Metrics::new(secret_store.get("metrics_token"))),
+        translator: translate::from_name(secret_store.get("translator").as_deref())?,
+        mailer: mail::from_name(secret_store.get("mailer").as_deref())?,
+        config,
+        images_folder,
+    };
+
+    Ok(AxumService(SyncWrapper::new(app(state, dist_folder))))
+}
+
+/// Serves the router like `ShuttleAxum` does, but with each connection's address available as
+/// `ConnectInfo`, which the rate limiter tells clients apart by.
+struct AxumService(SyncWrapper<Router>);
+
+#[async_trait]
+impl shuttle_service::Service for AxumService {
+    async fn bind(mut self: Box<Self>, addr: SocketAddr) -> Result<(), shuttle_service::Error> {
+        let router = self.0.into_inner();
+
+        axum::Server::bind(&addr)
+            .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .map_err(CustomError::new)?;
+
+        Ok(())
+    }
+}
+
+/// All routes and middleware around `state`, with the frontend served from `dist_folder`.
+fn app(state: AppState, dist_folder: PathBuf) -> Router {
+    // Posting comments is capped per client to slow down spam.
+    let comment_limiter = Arc::new(RateLimiter::new(
+        10,
+        Duration::from_secs(60),
+        state.config.trusted_proxy,
+    ));
+
+    // Login and registration share a per-client budget to slow down password guessing.
+    let auth_limiter = Arc::new(RateLimiter::new(
+        state.config.auth_requests_per_minute,
+        Duration::from_secs(60),
+        state.config.trusted_proxy,
+    ));
+
+    Router::new()
+        .route(
+            "/api/users/login",
+            post(api::login).route_layer(middleware::from_fn_with_state(
+                auth_limiter.clone(),
+                ratelimit::rate_limit,
+            )),
+        )
+        .route(
+            "/api/users",
+            post(api::registration).route_layer(middleware::from_fn_with_state(
+                auth_limiter,
+                ratelimit::rate_limit,
+            )),
+        )
+        .route("/api/users/refresh", post(api::refresh_token))
         .route("/api/user", get(api::get_current_user))
         .route("/api/user", put(api::update_user))
+        .route("/api/user", delete(api::delete_account))
+        .route("/api/user/email/confirm", post(api::confirm_email))
+        .route("/api/user/badges", get(api::get_badges))
+        .route("/api/users/me/activity", get(api::get_activity))
+        .route("/api/user/preferences", get(api::get_preferences))
+        .route("/api/user/preferences", put(api::update_preferences))
         .route("/api/profiles/:username", get(api::get_profile))
         .route("/api/profiles/:username/follow", post(api::follow_user))
         .route("/api/profiles/:username/follow", delete(api::unfollow_user))
         .route("/api/articles", get(api::list_articles))
         .route("/api/articles/feed", get(api::feed_articles))
+        .route("/api/articles/drafts", get(api::draft_articles))
+        .route("/api/articles/search", get(api::search_articles))
+        .route("/api/articles/comments/count", post(api::count_comments))
+        .route(
+            "/api/articles/favorited-by-following",
+            post(api::favorited_by_following_batch),
+        )
         .route("/api/articles/:slug", get(api::get_article))
+        .route("/api/articles/:slug/raw", get(api::get_raw_article))
+        .route("/api/articles/:slug/toc", get(api::get_article_toc))
+        .route("/api/articles/:slug/embed", get(embed::embed_article))
+        .route(
+            "/api/articles/:slug/translate",
+            post(translate::translate_article),
+        )
+        .route(
+            "/api/articles/:slug/similar-authors",
+            get(api::get_similar_authors),
+        )
+        .route(
+            "/api/articles/:slug/favorited-by-following",
+            get(api::get_favorited_by_following),
+        )
         .route("/api/articles", post(api::create_article))
         .route("/api/articles/:slug", put(api::update_article))
         .route("/api/articles/:slug", delete(api::delete_article))
-        .route("/api/articles/:slug/comments", post(api::add_comment))
+        .route(
+            "/api/articles/:slug/comments",
+            post(api::add_comment).route_layer(middleware::from_fn_with_state(
+                comment_limiter,
+                ratelimit::rate_limit,
+            )),
+        )
         .route("/api/articles/:slug/comments", get(api::get_comments))
+        .route(
+            "/api/articles/:slug/comments/preview",
+            post(api::preview_comment),
+        )
+        .route("/api/articles/:slug/comments/:id", put(api::edit_comment))
         .route(
             "/api/articles/:slug/comments/:id",
             delete(api::delete_comment),
         )
+        .route(
+            "/api/articles/:slug/comments/:id/react",
+            post(api::react_comment),
+        )
+        .route(
+            "/api/articles/:slug/comments/:id/pin",
+            post(api::pin_comment),
+        )
+        .route(
+            "/api/articles/:slug/comments/:id/pin",
+            delete(api::unpin_comment),
+        )
         .route("/api/articles/:slug/favorite", post(api::favorite_article))
+        .route("/api/articles/:slug/clap", post(api::clap_article))
         .route(
             "/api/articles/:slug/favorite",
             delete(api::unfavorite_article),
         )
         .route("/api/tags", get(api::get_tags))
+        .route(
+            "/api/images",
+            post(upload::upload_image).route_layer(DefaultBodyLimit::max(upload::MAX_UPLOAD_BYTES)),
+        )
+        .route("/api/events", post(api::record_event))
         .route("/api/initialize", post(api::initialize))
+        .route("/api/health", get(api::health))
+        .route("/api/healthz/db-latency", get(api::db_latency))
+        .route("/api/metrics", get(metrics::get_metrics))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track_metrics,
+        ))
         .merge(SpaRouter::new("/", dist_folder).index_file("index.html"))
         .nest_service(
             "/images",
-            get_service(ServeDir::new(images_folder)).handle_error(|err| async move {
+            get_service(ServeDir::new(&state.images_folder)).handle_error(|err| async move {
                 (StatusCode::NOT_FOUND, format!("Not Found: {err}"))
             }),
         )
-        .with_state(AppState {
-            pool,
-            encoding_key,
-            decoding_key,
-        })
-        .layer(CompressionLayer::new());
-
-    Ok(SyncWrapper::new(router))
+        .with_state(state)
+        .layer(CompressionLayer::new())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|req: &Request<Body>| {
+                    let request_id = req
+                        .headers()
+                        .get(request_id::REQUEST_ID_HEADER)
+                        .and_then(|id| id.to_str().ok())
+                        .unwrap_or_default();
+                    tracing::info_span!(
+                        "request",
+                        method = %req.method(),
+                        path = %req.uri().path(),
+                        request_id,
+                    )
+                })
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                ),
+        )
+        // Outermost, so the id is already on the request when the trace span is created.
+        .layer(middleware::from_fn(request_id::set_request_id))
 }
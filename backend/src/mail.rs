@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+/// Delivers account emails such as email change confirmations. Implementations are picked
+/// with the `mailer` secret; see `from_name`.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()>;
+}
+
+/// Writes each message to the log instead of sending it, so whoever can read the logs
+/// (a developer, or an operator relaying it by hand) can pass it on.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        log::info!("mail to {to}: {subject}\n{body}");
+        Ok(())
+    }
+}
+
+/// Builds the mailer named by the `mailer` secret. Without one there is no way to reach
+/// users by email, and features that depend on it, like changing the email, are turned off.
+pub fn from_name(name: Option<&str>) -> anyhow::Result<Option<Arc<dyn Mailer>>> {
+    match name {
+        None => Ok(None),
+        Some("log") => Ok(Some(Arc::new(LogMailer))),
+        Some(other) => Err(anyhow::anyhow!("unknown mailer: {other}")),
+    }
+}
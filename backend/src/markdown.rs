@@ -0,0 +1,87 @@
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+
+use crate::toc::Anchors;
+
+/// Link targets allowed through; anything else with a scheme (`javascript:`, `data:`, ...)
+/// is replaced. Relative links and fragments have no scheme and are kept.
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim();
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(i) if url[i..].starts_with(':') => SAFE_SCHEMES
+            .iter()
+            .any(|scheme| url[..i].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+fn sanitize_url(url: CowStr) -> CowStr {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("#")
+    }
+}
+
+/// Renders user-written markdown to HTML that is safe to insert into a page: raw HTML such as
+/// `<script>` is dropped and links may only use the [`SAFE_SCHEMES`]. Headings get the same ids
+/// as the anchors from [`table_of_contents`](crate::toc::table_of_contents).
+pub fn render_markdown(markdown: &str) -> String {
+    let mut events = vec![];
+    let mut anchors = Anchors::default();
+    let mut heading: Option<(usize, String)> = None;
+
+    for event in Parser::new(markdown) {
+        let event = match event {
+            Event::Html(_) | Event::InlineHtml(_) => continue,
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: sanitize_url(dest_url),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: sanitize_url(dest_url),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Heading { .. }) => {
+                heading = Some((events.len(), String::new()));
+                event
+            }
+            Event::Text(ref text) | Event::Code(ref text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(text);
+                }
+                event
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((start, text)) = heading.take() {
+                    if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+                        *id = Some(CowStr::from(anchors.next(&text)));
+                    }
+                }
+                event
+            }
+            event => event,
+        };
+        events.push(event);
+    }
+
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, events.into_iter());
+    html_output
+}
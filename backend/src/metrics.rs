@@ -0,0 +1,166 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, State},
+    headers::{authorization::Bearer, Authorization},
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    TypedHeader,
+};
+use serde_json::json;
+
+use crate::error::{AppError, AppResult};
+
+/// Upper bounds (in seconds) of the request latency histogram buckets.
+const BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+#[derive(Default)]
+struct RouteMetrics {
+    statuses: BTreeMap<u16, u64>,
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Per-route request counters and latency histograms, rendered in the Prometheus text format.
+pub struct Metrics {
+    /// Scraping requires `Authorization: Bearer <token>`; without a token `/api/metrics` is a 404.
+    token: Option<String>,
+    routes: Mutex<BTreeMap<(String, String), RouteMetrics>>,
+}
+
+impl Metrics {
+    pub fn new(token: Option<String>) -> Self {
+        Self {
+            token,
+            routes: Mutex::default(),
+        }
+    }
+
+    fn record(&self, method: &str, route: &str, status: StatusCode, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+
+        let mut routes = self.routes.lock().unwrap();
+        let entry = routes
+            .entry((route.to_string(), method.to_string()))
+            .or_default();
+
+        *entry.statuses.entry(status.as_u16()).or_default() += 1;
+        for (bucket, le) in entry.buckets.iter_mut().zip(BUCKETS) {
+            if secs <= le {
+                *bucket += 1;
+            }
+        }
+        entry.sum += secs;
+        entry.count += 1;
+    }
+
+    fn render(&self) -> String {
+        let routes = self.routes.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP http_requests_total Total number of HTTP requests.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for ((route, method), metrics) in routes.iter() {
+            for (status, count) in &metrics.statuses {
+                writeln!(
+                    out,
+                    r#"http_requests_total{{method="{method}",route="{route}",status="{status}"}} {count}"#
+                )
+                .unwrap();
+            }
+        }
+
+        out.push_str("# HELP http_request_errors_total Total number of HTTP requests answered with a 5xx status.\n");
+        out.push_str("# TYPE http_request_errors_total counter\n");
+        for ((route, method), metrics) in routes.iter() {
+            let errors: u64 = metrics.statuses.range(500..600).map(|(_, n)| n).sum();
+            writeln!(
+                out,
+                r#"http_request_errors_total{{method="{method}",route="{route}"}} {errors}"#
+            )
+            .unwrap();
+        }
+
+        out.push_str("# HELP http_request_duration_seconds HTTP request latency.\n");
+        out.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((route, method), metrics) in routes.iter() {
+            let labels = format!(r#"method="{method}",route="{route}""#);
+            for (le, bucket) in BUCKETS.iter().zip(metrics.buckets) {
+                writeln!(
+                    out,
+                    r#"http_request_duration_seconds_bucket{{{labels},le="{le}"}} {bucket}"#
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                r#"http_request_duration_seconds_bucket{{{labels},le="+Inf"}} {}"#,
+                metrics.count
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "http_request_duration_seconds_sum{{{labels}}} {}",
+                metrics.sum
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "http_request_duration_seconds_count{{{labels}}} {}",
+                metrics.count
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+/// Records every routed request. Must be installed with `route_layer` so `MatchedPath` is known.
+pub async fn track_metrics<B>(
+    State(metrics): State<Arc<Metrics>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let start = Instant::now();
+    let method = req.method().clone();
+    let route = req.extensions().get::<MatchedPath>().map_or_else(
+        || req.uri().path().to_string(),
+        |path| path.as_str().to_string(),
+    );
+
+    let response = next.run(req).await;
+
+    metrics.record(method.as_str(), &route, response.status(), start.elapsed());
+
+    response
+}
+
+pub async fn get_metrics(
+    State(metrics): State<Arc<Metrics>>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> AppResult<impl IntoResponse> {
+    // Without a configured token the endpoint doesn't exist at all, rather than being public.
+    let token = metrics
+        .token
+        .as_ref()
+        .ok_or_else(|| AppError::NotFoundError(json!({ "metrics": ["not found"] })))?;
+
+    if !matches!(bearer, Some(TypedHeader(Authorization(bearer))) if bearer.token() == token) {
+        Err(AppError::ForbiddenError(json!({
+            "metrics": "requires a valid token"
+        })))?
+    }
+
+    Ok((
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    ))
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+/// Once the map holds this many clients, windows that already ended are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Fixed-window request counter per client.
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    /// The reverse proxy whose `X-Forwarded-For` is believed; see `client_key`.
+    trusted_proxy: Option<IpAddr>,
+    clients: Mutex<HashMap<String, Window>>,
+}
+
+struct Quota {
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+    exceeded: bool,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration, trusted_proxy: Option<IpAddr>) -> Self {
+        Self {
+            limit,
+            window,
+            trusted_proxy,
+            clients: Mutex::default(),
+        }
+    }
+
+    fn hit(&self, client: &str) -> Quota {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+
+        if clients.len() >= PRUNE_THRESHOLD {
+            clients.retain(|_, w| now.duration_since(w.started) < self.window);
+        }
+
+        let window = clients.entry(client.to_string()).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.count = 0;
+        }
+
+        let exceeded = window.count >= self.limit;
+        if !exceeded {
+            window.count += 1;
+        }
+
+        Quota {
+            limit: self.limit,
+            remaining: self.limit - window.count,
+            reset: self.window - now.duration_since(window.started),
+            exceeded,
+        }
+    }
+
+    /// The address requests are counted against: the connection's peer, unless the peer is the
+    /// trusted proxy, in which case the hop it appended to `X-Forwarded-For`. Everything left of
+    /// that hop came from the client and can't be believed. Without a peer address, all requests
+    /// share one bucket.
+    fn client_key(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> String {
+        let Some(peer) = peer else {
+            return "unknown".to_string();
+        };
+        if self.trusted_proxy != Some(peer) {
+            return peer.to_string();
+        }
+
+        headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .last()
+            .and_then(|hop| hop.trim().parse::<IpAddr>().ok())
+            .unwrap_or(peer)
+            .to_string()
+    }
+}
+
+/// Counts the request against its client and answers 429 once the quota is used up.
+/// Every response carries `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`
+/// (seconds until the window restarts) so clients can pace themselves.
+pub async fn rate_limit<B>(
+    State(limiter): State<Arc<RateLimiter>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let quota = limiter.hit(&limiter.client_key(peer, req.headers()));
+    let reset_secs = quota.reset.as_secs_f64().ceil() as u64;
+
+    let mut response = if quota.exceeded {
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({ "error": { "requests": "are too frequent, try again later" } })),
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(reset_secs));
+        response
+    } else {
+        next.run(req).await
+    };
+
+    let headers = response.headers_mut();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(quota.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(quota.remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset_secs));
+
+    response
+}
@@ -0,0 +1,30 @@
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled, for code that can't see the request itself such as
+/// `AppError::into_response`. `None` outside of a request.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Gives every request a fresh UUID. It replaces any `X-Request-Id` the client sent, so the
+/// trace span can read it from the request, and is echoed back in the response header.
+pub async fn set_request_id<B>(mut req: Request<B>, next: Next<B>) -> Response {
+    let id = Uuid::new_v4().to_string();
+    let header = HeaderValue::from_str(&id).expect("a UUID is a valid header value");
+    req.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+
+    let mut response = REQUEST_ID.scope(id, next.run(req)).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    response
+}
@@ -0,0 +1,1014 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::{Executor, PgPool};
+
+use super::{test_config, TestApp, TestResponse};
+use crate::Config;
+
+/// Titles of the articles in a listing response, in order.
+fn titles(response: &TestResponse) -> Vec<&str> {
+    response.body["articles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|article| article["title"].as_str().unwrap())
+        .collect()
+}
+
+/// Publishes an article with the given body and returns its slug.
+async fn publish(app: &TestApp, token: &str, title: &str, body: &str, tags: &[&str]) -> String {
+    let response = app
+        .post(
+            "/api/articles",
+            Some(token),
+            json!({ "article": {
+                "title": title,
+                "description": format!("About {title}"),
+                "body": body,
+                "tagList": tags,
+            }}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+    response.body["article"]["slug"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[sqlx::test]
+async fn summary_listings_leave_out_the_body(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Heavy", &[]).await;
+
+    let summary = app.get("/api/articles?format=summary", None).await;
+    assert_eq!(summary.status, StatusCode::OK);
+    let article = &summary.body["articles"][0];
+    assert_eq!(article["slug"], slug);
+    assert_eq!(article["description"], "About Heavy");
+    assert!(article.get("body").is_none());
+
+    let full = app.get("/api/articles", None).await;
+    assert_eq!(full.body["articles"][0]["body"], "All about Heavy.");
+
+    let single = app.get(&format!("/api/articles/{slug}"), None).await;
+    assert_eq!(single.body["article"]["body"], "All about Heavy.");
+}
+
+#[sqlx::test]
+async fn summary_feed_leaves_out_the_body(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    app.follow(&reader, "author").await;
+    app.create_article(&author, "Heavy", &[]).await;
+
+    let feed = app
+        .get("/api/articles/feed?format=summary", Some(&reader))
+        .await;
+    assert_eq!(feed.status, StatusCode::OK);
+    let articles = feed.body["articles"].as_array().unwrap();
+    assert_eq!(articles.len(), 1);
+    assert!(articles[0].get("body").is_none());
+}
+
+#[sqlx::test]
+async fn ids_fetch_keeps_the_requested_order_and_hides_drafts(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let mut ids = vec![];
+    for title in ["First", "Second", "Third"] {
+        let slug = app.create_article(&author, title, &[]).await;
+        let article = app.get(&format!("/api/articles/{slug}"), None).await;
+        ids.push(article.body["article"]["id"].as_i64().unwrap());
+    }
+    let draft = app
+        .post(
+            "/api/articles",
+            Some(&author),
+            json!({ "article": {
+                "title": "Draft",
+                "description": "Not yet",
+                "body": "Work in progress.",
+                "tagList": [],
+                "published": false,
+            }}),
+        )
+        .await;
+    let draft_id = draft.body["article"]["id"].as_i64().unwrap();
+
+    let response = app
+        .get(
+            &format!(
+                "/api/articles?ids={},{},{},{}",
+                ids[1], draft_id, ids[2], ids[0]
+            ),
+            None,
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(titles(&response), ["Second", "Third", "First"]);
+}
+
+#[sqlx::test]
+async fn following_filter_keeps_only_followed_authors(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let reader = app.register("reader").await;
+    let followed = app.register("followed").await;
+    let stranger = app.register("stranger").await;
+    app.follow(&reader, "followed").await;
+    app.create_article(&followed, "Followed post", &[]).await;
+    app.create_article(&stranger, "Stranger post", &[]).await;
+
+    let response = app.get("/api/articles?following=true", Some(&reader)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(titles(&response), ["Followed post"]);
+    assert_eq!(response.body["articlesCount"], 1);
+
+    let everyone = app
+        .get("/api/articles?following=false", Some(&reader))
+        .await;
+    assert_eq!(everyone.body["articlesCount"], 2);
+}
+
+#[sqlx::test]
+async fn following_filter_combines_with_tag(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let reader = app.register("reader").await;
+    let followed = app.register("followed").await;
+    let stranger = app.register("stranger").await;
+    app.follow(&reader, "followed").await;
+    app.create_article(&followed, "Followed rust", &["rust"])
+        .await;
+    app.create_article(&followed, "Followed go", &["go"]).await;
+    app.create_article(&stranger, "Stranger rust", &["rust"])
+        .await;
+
+    let response = app
+        .get("/api/articles?following=true&tag=rust", Some(&reader))
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(titles(&response), ["Followed rust"]);
+}
+
+#[sqlx::test]
+async fn following_filter_requires_authentication(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app.get("/api/articles?following=true", None).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert!(response.body["error"]["following"].is_string());
+}
+
+#[sqlx::test]
+async fn co_favorited_authors_are_suggested(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let carol = app.register("carol").await;
+    let reader = app.register("reader").await;
+    let alice_post = app.create_article(&alice, "Alice writes", &[]).await;
+    let bob_post = app.create_article(&bob, "Bob writes", &[]).await;
+    app.create_article(&carol, "Carol writes", &[]).await;
+    app.favorite(&reader, &alice_post).await;
+    app.favorite(&reader, &bob_post).await;
+    app.follow(&reader, "bob").await;
+
+    let response = app
+        .get(
+            &format!("/api/articles/{alice_post}/similar-authors"),
+            Some(&reader),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let profiles = response.body["profiles"].as_array().unwrap();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0]["username"], "bob");
+    assert_eq!(profiles[0]["following"], true);
+}
+
+#[sqlx::test]
+async fn near_duplicate_titles_come_back_with_a_warning(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let other = app.register("other").await;
+    let original = app
+        .create_article(&author, "Getting started with Rust", &[])
+        .await;
+    let article = |title: &str| {
+        json!({ "article": {
+            "title": title,
+            "description": "Again",
+            "body": "Again.",
+            "tagList": [],
+        }})
+    };
+
+    let response = app
+        .post(
+            "/api/articles",
+            Some(&author),
+            article("Getting started with Rust again"),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.body["article"]["title"],
+        "Getting started with Rust again"
+    );
+    let warnings = response.body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["slug"], original);
+
+    let forced = app
+        .post(
+            "/api/articles?force=true",
+            Some(&author),
+            article("Getting started with Rust once more"),
+        )
+        .await;
+    assert_eq!(forced.status, StatusCode::OK);
+    assert!(forced.body.get("warnings").is_none());
+
+    let unrelated = app
+        .post(
+            "/api/articles",
+            Some(&author),
+            article("Baking sourdough bread"),
+        )
+        .await;
+    assert!(unrelated.body.get("warnings").is_none());
+
+    let someone_else = app
+        .post(
+            "/api/articles",
+            Some(&other),
+            article("Getting started with Rust too"),
+        )
+        .await;
+    assert!(someone_else.body.get("warnings").is_none());
+}
+
+#[sqlx::test]
+async fn search_combines_with_tag_and_ranks_by_relevance(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    publish(
+        &app,
+        &author,
+        "Passing mention",
+        "Ferris shows up once.",
+        &["rust"],
+    )
+    .await;
+    publish(
+        &app,
+        &author,
+        "All about Ferris",
+        "Ferris the crab. Ferris is the Rust mascot, and Ferris is orange.",
+        &["rust"],
+    )
+    .await;
+    publish(
+        &app,
+        &author,
+        "Ferris elsewhere",
+        "Ferris, Ferris, Ferris.",
+        &["go"],
+    )
+    .await;
+    publish(
+        &app,
+        &author,
+        "Unrelated",
+        "Nothing to see here.",
+        &["rust"],
+    )
+    .await;
+
+    let response = app
+        .get(
+            "/api/articles?q=ferris&tag=rust&sort=relevance&limit=1",
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(titles(&response), ["All about Ferris"]);
+    assert_eq!(response.body["articlesCount"], 2);
+
+    let next = app
+        .get(
+            "/api/articles?q=ferris&tag=rust&sort=relevance&limit=1&offset=1",
+            None,
+        )
+        .await;
+    assert_eq!(titles(&next), ["Passing mention"]);
+
+    let unsorted = app.get("/api/articles?sort=relevance", None).await;
+    assert_eq!(unsorted.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[sqlx::test]
+async fn listings_without_limit_use_the_configured_page_size(pool: PgPool) {
+    let config = Config {
+        default_page_size: 2,
+        ..test_config()
+    };
+    let app = TestApp::with_config(pool, config).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    app.follow(&reader, "author").await;
+    for title in ["One", "Two", "Three"] {
+        app.create_article(&author, title, &[]).await;
+    }
+
+    let listing = app.get("/api/articles", None).await;
+    assert_eq!(titles(&listing), ["Three", "Two"]);
+    assert_eq!(listing.headers["x-limit"], "2");
+    assert_eq!(listing.body["articlesCount"], 3);
+
+    let feed = app.get("/api/articles/feed", Some(&reader)).await;
+    assert_eq!(titles(&feed), ["Three", "Two"]);
+
+    let explicit = app.get("/api/articles?limit=3", None).await;
+    assert_eq!(titles(&explicit), ["Three", "Two", "One"]);
+}
+
+#[sqlx::test]
+async fn out_of_range_limits_fall_back_or_are_capped(pool: PgPool) {
+    let config = Config {
+        default_page_size: 2,
+        ..test_config()
+    };
+    let app = TestApp::with_config(pool, config).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    app.follow(&reader, "author").await;
+    for title in ["One", "Two", "Three"] {
+        app.create_article(&author, title, &[]).await;
+    }
+
+    for limit in ["0", "-5"] {
+        let listing = app.get(&format!("/api/articles?limit={limit}"), None).await;
+        assert_eq!(listing.status, StatusCode::OK, "limit={limit}");
+        assert_eq!(listing.headers["x-limit"], "2", "limit={limit}");
+        assert_eq!(titles(&listing), ["Three", "Two"]);
+
+        let feed = app
+            .get(&format!("/api/articles/feed?limit={limit}"), Some(&reader))
+            .await;
+        assert_eq!(feed.headers["x-limit"], "2", "limit={limit}");
+        assert_eq!(titles(&feed), ["Three", "Two"]);
+    }
+
+    let huge = app.get("/api/articles?limit=100000000", None).await;
+    assert_eq!(huge.status, StatusCode::OK);
+    assert_eq!(huge.headers["x-limit"], "100");
+    assert_eq!(titles(&huge), ["Three", "Two", "One"]);
+
+    let huge_feed = app
+        .get("/api/articles/feed?limit=100000000", Some(&reader))
+        .await;
+    assert_eq!(huge_feed.headers["x-limit"], "100");
+    assert_eq!(titles(&huge_feed), ["Three", "Two", "One"]);
+}
+
+#[sqlx::test]
+async fn raw_article_shows_internal_fields_to_admins(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let admin = app.register("admin").await;
+    let author = app.register("author").await;
+    sqlx::query!("UPDATE users SET is_admin = TRUE WHERE username = 'admin'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let slug = app.create_draft(&author, "Hidden").await;
+
+    let response = app
+        .get(&format!("/api/articles/{slug}/raw"), Some(&admin))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let raw = &response.body["article"];
+    assert_eq!(raw["slug"], slug);
+    let author_id = app.user_id("author").await;
+    assert_eq!(raw["author_id"], author_id);
+    assert_eq!(raw["published"], false);
+    assert_eq!(raw["version"], 1);
+    assert!(raw["created_at"].is_string());
+    assert!(raw["updated_at"].is_string());
+
+    let forbidden = app
+        .get(&format!("/api/articles/{slug}/raw"), Some(&author))
+        .await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+
+    let missing = app.get("/api/articles/missing/raw", Some(&admin)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn favorite_responses_reflect_the_applied_action(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let slug = app.create_article(&author, "Popular", &[]).await;
+    let uri = format!("/api/articles/{slug}/favorite");
+
+    let (alice_fav, bob_fav) = futures::join!(
+        app.post(&uri, Some(&alice), json!({})),
+        app.post(&uri, Some(&bob), json!({})),
+    );
+    for response in [&alice_fav, &bob_fav] {
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["article"]["favorited"], true);
+        assert!(response.body["article"]["favoritesCount"].as_i64().unwrap() >= 1);
+    }
+
+    let article_uri = format!("/api/articles/{slug}");
+    let (alice_unfav, bob_view) = futures::join!(
+        app.delete(&uri, Some(&alice)),
+        app.get(&article_uri, Some(&bob)),
+    );
+    assert_eq!(alice_unfav.status, StatusCode::OK);
+    assert_eq!(alice_unfav.body["article"]["favorited"], false);
+    assert_eq!(bob_view.body["article"]["favorited"], true);
+
+    let article = app.get(&article_uri, None).await;
+    assert_eq!(article.body["article"]["favoritesCount"], 1);
+}
+
+#[sqlx::test]
+async fn author_filter_matches_filtering_by_author_id(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    app.create_article(&alice, "Alice one", &["rust"]).await;
+    app.create_article(&bob, "Bob one", &["rust"]).await;
+    app.create_article(&alice, "Alice two", &["go"]).await;
+    let alice_id = app.user_id("alice").await;
+
+    let by_name = app.get("/api/articles?author=alice", None).await;
+    let by_id = app
+        .get(&format!("/api/articles?author_id={alice_id}"), None)
+        .await;
+    assert_eq!(titles(&by_name), ["Alice two", "Alice one"]);
+    assert_eq!(by_name.body, by_id.body);
+
+    let with_tag = app.get("/api/articles?author=alice&tag=rust", None).await;
+    assert_eq!(titles(&with_tag), ["Alice one"]);
+
+    let unknown = app.get("/api/articles?author=nobody", None).await;
+    assert_eq!(unknown.status, StatusCode::OK);
+    assert_eq!(unknown.body["articlesCount"], 0);
+
+    let contradicting = app
+        .get(
+            &format!("/api/articles?author=bob&author_id={alice_id}"),
+            None,
+        )
+        .await;
+    assert_eq!(contradicting.body["articles"], json!([]));
+}
+
+#[sqlx::test]
+async fn cursor_pages_are_stable_when_articles_are_added(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    for title in ["A", "B", "C", "D"] {
+        app.create_article(&author, title, &[]).await;
+    }
+
+    let first = app.get("/api/articles?limit=2&cursor=", None).await;
+    assert_eq!(titles(&first), ["D", "C"]);
+    let cursor = first.body["nextCursor"].as_str().unwrap().to_string();
+
+    app.create_article(&author, "E", &[]).await;
+
+    let second = app
+        .get(&format!("/api/articles?limit=2&cursor={cursor}"), None)
+        .await;
+    assert_eq!(titles(&second), ["B", "A"]);
+    let cursor = second.body["nextCursor"].as_str().unwrap().to_string();
+
+    let last = app
+        .get(&format!("/api/articles?limit=2&cursor={cursor}"), None)
+        .await;
+    assert_eq!(last.body["articles"], json!([]));
+    assert!(last.body["nextCursor"].is_null());
+
+    // Offset pagination, by contrast, shifts by the new article.
+    let offset = app.get("/api/articles?limit=2&offset=2", None).await;
+    assert_eq!(titles(&offset), ["C", "B"]);
+}
+
+#[sqlx::test]
+async fn updates_can_not_blank_out_title_or_body(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Keep me", &[]).await;
+    let uri = format!("/api/articles/{slug}");
+
+    let title = app
+        .put(
+            &uri,
+            Some(&author),
+            json!({ "article": { "title": "   ", "version": 1 } }),
+        )
+        .await;
+    assert_eq!(title.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(title.body["error"]["title"].is_array());
+
+    let body = app
+        .put(
+            &uri,
+            Some(&author),
+            json!({ "article": { "body": "", "version": 1 } }),
+        )
+        .await;
+    assert_eq!(body.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.body["error"]["body"].is_array());
+
+    // Leaving the fields out keeps them as they were.
+    let description = app
+        .put(
+            &uri,
+            Some(&author),
+            json!({ "article": { "description": "New description", "version": 1 } }),
+        )
+        .await;
+    assert_eq!(description.status, StatusCode::OK);
+    let article = &description.body["article"];
+    assert_eq!(article["title"], "Keep me");
+    assert_eq!(article["body"], "All about Keep me.");
+    assert_eq!(article["description"], "New description");
+}
+
+#[sqlx::test]
+async fn min_favorites_leaves_out_unfavorited_articles(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let once = app.create_article(&author, "Liked once", &[]).await;
+    let twice = app.create_article(&author, "Liked twice", &[]).await;
+    app.create_article(&author, "Never liked", &[]).await;
+    app.favorite(&alice, &once).await;
+    app.favorite(&alice, &twice).await;
+    app.favorite(&bob, &twice).await;
+
+    let response = app.get("/api/articles?min_favorites=1", None).await;
+    assert_eq!(titles(&response), ["Liked twice", "Liked once"]);
+    assert_eq!(response.body["articlesCount"], 2);
+
+    let response = app
+        .get("/api/articles?min_favorites=2&sort=most_favorited", None)
+        .await;
+    assert_eq!(titles(&response), ["Liked twice"]);
+}
+
+#[sqlx::test]
+async fn sort_modes_order_the_listing(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let one = app.create_article(&author, "One", &[]).await;
+    let two = app.create_article(&author, "Two", &[]).await;
+    app.create_article(&author, "Three", &[]).await;
+    let four = app.create_article(&author, "Four", &[]).await;
+    app.favorite(&alice, &two).await;
+    app.favorite(&bob, &two).await;
+    app.favorite(&alice, &one).await;
+    app.favorite(&alice, &four).await;
+
+    for (sort, expected) in [
+        ("", ["Four", "Three", "Two", "One"]),
+        ("?sort=newest", ["Four", "Three", "Two", "One"]),
+        ("?sort=oldest", ["One", "Two", "Three", "Four"]),
+        // Ties on favorites go to the newer article.
+        ("?sort=most_favorited", ["Two", "Four", "One", "Three"]),
+    ] {
+        let response = app.get(&format!("/api/articles{sort}"), None).await;
+        assert_eq!(response.status, StatusCode::OK, "{sort}");
+        assert_eq!(titles(&response), expected, "{sort}");
+    }
+
+    let unknown = app.get("/api/articles?sort=random", None).await;
+    assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn favorited_by_following_lists_only_followed_users(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let friend = app.register("friend").await;
+    let stranger = app.register("stranger").await;
+    let slug = app.create_article(&author, "Social proof", &[]).await;
+    app.follow(&reader, "friend").await;
+    app.favorite(&friend, &slug).await;
+    app.favorite(&stranger, &slug).await;
+    let uri = format!("/api/articles/{slug}/favorited-by-following");
+
+    let response = app.get(&uri, Some(&reader)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let profiles = response.body["profiles"].as_array().unwrap();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0]["username"], "friend");
+    assert_eq!(profiles[0]["following"], true);
+
+    let batch = app
+        .post(
+            "/api/articles/favorited-by-following",
+            Some(&reader),
+            json!({ "slugs": [&slug] }),
+        )
+        .await;
+    assert_eq!(batch.body["profiles"][&slug][0]["username"], "friend");
+
+    let anonymous = app.get(&uri, None).await;
+    assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+}
+
+/// Makes every later attempt to link a tag to an article fail inside the database.
+async fn break_article_tags(app: &TestApp) {
+    app.pool
+        .execute(
+            "
+            CREATE FUNCTION fail_article_tags() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'article_tags is broken';
+            END
+            $$ LANGUAGE plpgsql;
+            CREATE TRIGGER fail_article_tags BEFORE INSERT ON article_tags
+                FOR EACH ROW EXECUTE FUNCTION fail_article_tags();
+            ",
+        )
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn failed_create_leaves_no_article_behind(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    break_article_tags(&app).await;
+
+    let response = app
+        .post(
+            "/api/articles",
+            Some(&author),
+            json!({ "article": {
+                "title": "Half written",
+                "description": "Oops",
+                "body": "Oops.",
+                "tagList": ["orphan"],
+            }}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let leftovers = sqlx::query_scalar!(
+        r#"SELECT (SELECT COUNT(*) FROM articles) + (SELECT COUNT(*) FROM tags) AS "count!""#
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(leftovers, 0);
+}
+
+#[sqlx::test]
+async fn failed_update_changes_nothing(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Original", &["old"]).await;
+    break_article_tags(&app).await;
+
+    let response = app
+        .put(
+            &format!("/api/articles/{slug}"),
+            Some(&author),
+            json!({ "article": { "title": "Renamed", "tagList": ["new"], "version": 1 } }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let article = app.get(&format!("/api/articles/{slug}"), None).await;
+    assert_eq!(article.body["article"]["title"], "Original");
+    assert_eq!(article.body["article"]["tagList"], json!(["old"]));
+    assert_eq!(article.body["article"]["version"], 1);
+}
+
+#[sqlx::test]
+async fn updated_since_returns_changes_and_tombstones(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let edited = app.create_article(&author, "Edited later", &[]).await;
+    let unpublished = app.create_article(&author, "Unpublished later", &[]).await;
+    let deleted = app.create_article(&author, "Deleted later", &[]).await;
+    let synced = app.get(&format!("/api/articles/{deleted}"), None).await;
+    let since = synced.body["article"]["updatedAt"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    app.create_article(&author, "Created later", &[]).await;
+    app.put(
+        &format!("/api/articles/{edited}"),
+        Some(&author),
+        json!({ "article": { "description": "Edited", "version": 1 } }),
+    )
+    .await;
+    app.delete(&format!("/api/articles/{deleted}"), Some(&author))
+        .await;
+    let unpublished_uri = format!("/api/articles/{unpublished}");
+    let set_published = |published: bool, version: i32| {
+        app.put(
+            &unpublished_uri,
+            Some(&author),
+            json!({ "article": { "published": published, "version": version } }),
+        )
+    };
+    assert_eq!(set_published(false, 1).await.status, StatusCode::OK);
+
+    let response = app
+        .get(&format!("/api/articles?updated_since={since}"), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(titles(&response), ["Created later", "Edited later"]);
+    let tombstones: Vec<_> = response.body["deleted"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tombstone| tombstone["slug"].as_str().unwrap())
+        .collect();
+    assert_eq!(tombstones, [&deleted, &unpublished]);
+
+    // Published again, it's an update rather than a deletion.
+    assert_eq!(set_published(true, 2).await.status, StatusCode::OK);
+    let response = app
+        .get(&format!("/api/articles?updated_since={since}"), None)
+        .await;
+    assert_eq!(
+        titles(&response),
+        ["Created later", "Edited later", "Unpublished later"]
+    );
+    assert_eq!(response.body["deleted"].as_array().unwrap().len(), 1);
+    assert_eq!(response.body["deleted"][0]["slug"], deleted);
+
+    let invalid = app.get("/api/articles?updated_since=yesterday", None).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[sqlx::test]
+async fn unknown_slugs_and_usernames_are_not_found(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let article = app.get("/api/articles/does-not-exist", None).await;
+    assert_eq!(article.status, StatusCode::NOT_FOUND);
+    assert_eq!(article.body["error"]["article"], json!(["not found"]));
+
+    let profile = app.get("/api/profiles/nobody", None).await;
+    assert_eq!(profile.status, StatusCode::NOT_FOUND);
+    assert!(profile.body["error"]["profile"].is_array());
+}
+
+#[sqlx::test]
+async fn toc_lists_nested_headings_with_unique_anchors(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let body = "# Guide\n\nIntro.\n\n## Setup\n\n### Install `cargo`\n\n## Usage\n\n\
+                ### Setup\n\n## Setup\n\nNot a heading: # Setup\n";
+    let slug = publish(&app, &author, "Guide", body, &[]).await;
+
+    let response = app.get(&format!("/api/articles/{slug}/toc"), None).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(
+        response.body["toc"],
+        json!([
+            { "level": 1, "text": "Guide", "anchor": "guide" },
+            { "level": 2, "text": "Setup", "anchor": "setup" },
+            { "level": 3, "text": "Install cargo", "anchor": "install-cargo" },
+            { "level": 2, "text": "Usage", "anchor": "usage" },
+            { "level": 3, "text": "Setup", "anchor": "setup-1" },
+            { "level": 2, "text": "Setup", "anchor": "setup-2" },
+        ])
+    );
+}
+
+#[sqlx::test]
+async fn drafts_are_not_found_for_anyone_but_their_author(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_draft(&author, "Secret plans").await;
+    app.comment(&author, &slug, "Note to self").await;
+
+    let comment = json!({ "comment": { "body": "Found it" } });
+    for token in [None, Some(reader.as_str())] {
+        for path in ["", "/toc", "/comments", "/similar-authors"] {
+            let response = app.get(&format!("/api/articles/{slug}{path}"), token).await;
+            assert_eq!(response.status, StatusCode::NOT_FOUND, "GET {path}");
+            assert_eq!(response.body["error"]["article"], json!(["not found"]));
+        }
+    }
+    for path in ["/comments", "/comments/preview", "/translate?lang=de"] {
+        let response = app
+            .post(
+                &format!("/api/articles/{slug}{path}"),
+                Some(&reader),
+                comment.clone(),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "POST {path}");
+    }
+
+    let counts = app
+        .post(
+            "/api/articles/comments/count",
+            None,
+            json!({ "slugs": [&slug] }),
+        )
+        .await;
+    assert_eq!(counts.body["counts"], json!({}));
+
+    // The author still sees everything.
+    for path in ["/toc", "/comments"] {
+        let response = app
+            .get(&format!("/api/articles/{slug}{path}"), Some(&author))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "GET {path}");
+    }
+    let translated = app
+        .post(
+            &format!("/api/articles/{slug}/translate?lang=de"),
+            Some(&author),
+            json!({}),
+        )
+        .await;
+    assert_eq!(translated.status, StatusCode::OK, "{}", translated.body);
+}
+
+#[sqlx::test]
+async fn too_many_or_too_long_tags_are_rejected(pool: PgPool) {
+    let config = Config {
+        max_tags: 2,
+        ..test_config()
+    };
+    let app = TestApp::with_config(pool, config).await;
+    let author = app.register("author").await;
+    let longest = "t".repeat(32);
+    let too_long = "t".repeat(33);
+
+    let create = |tags: Vec<&str>| {
+        json!({ "article": {
+            "title": "Tagged",
+            "description": "Tags",
+            "body": "Tags.",
+            "tagList": tags,
+        }})
+    };
+
+    let too_many = app
+        .post("/api/articles", Some(&author), create(vec!["a", "b", "c"]))
+        .await;
+    assert_eq!(too_many.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        too_many.body["error"]["tagList"][0]["message"],
+        "has too many tags"
+    );
+
+    let long = app
+        .post("/api/articles", Some(&author), create(vec![&too_long]))
+        .await;
+    assert_eq!(long.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        long.body["error"]["tagList"][0]["message"],
+        "tags can't be longer than 32 characters"
+    );
+
+    let created = app
+        .post("/api/articles", Some(&author), create(vec!["a", &longest]))
+        .await;
+    assert_eq!(created.status, StatusCode::OK, "{}", created.body);
+    let slug = created.body["article"]["slug"].as_str().unwrap();
+
+    let uri = format!("/api/articles/{slug}");
+    for tags in [vec!["a", "b", "c"], vec![too_long.as_str()]] {
+        let update = app
+            .put(
+                &uri,
+                Some(&author),
+                json!({ "article": { "tagList": tags, "version": 1 } }),
+            )
+            .await;
+        assert_eq!(update.status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // The rejected updates left the tags alone.
+    let article = app.get(&uri, None).await;
+    let mut tags: Vec<&str> = article.body["article"]["tagList"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tag| tag.as_str().unwrap())
+        .collect();
+    tags.sort_unstable();
+    assert_eq!(tags, ["a", longest.as_str()]);
+}
+
+#[sqlx::test]
+async fn concurrent_edits_from_the_same_version_conflict(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Shared draft", &[]).await;
+    let uri = format!("/api/articles/{slug}");
+
+    let loaded = app.get(&uri, Some(&author)).await;
+    assert_eq!(loaded.body["article"]["version"], 1);
+
+    let (first, second) = futures::join!(
+        app.put(
+            &uri,
+            Some(&author),
+            json!({ "article": { "body": "First editor", "version": 1 } }),
+        ),
+        app.put(
+            &uri,
+            Some(&author),
+            json!({ "article": { "body": "Second editor", "version": 1 } }),
+        ),
+    );
+
+    let (saved, rejected) = if first.status == StatusCode::OK {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    assert_eq!(saved.status, StatusCode::OK, "{}", saved.body);
+    assert_eq!(saved.body["article"]["version"], 2);
+    assert_eq!(rejected.status, StatusCode::CONFLICT);
+    assert_eq!(
+        rejected.body["error"]["article"],
+        json!(["was modified, please reload"])
+    );
+
+    // The loser's text didn't overwrite the winner's.
+    let article = app.get(&uri, None).await;
+    assert_eq!(
+        article.body["article"]["body"],
+        saved.body["article"]["body"]
+    );
+    assert_eq!(article.body["article"]["version"], 2);
+
+    let stale = app
+        .put(
+            &uri,
+            Some(&author),
+            json!({ "article": { "body": "From an old tab", "version": 1 } }),
+        )
+        .await;
+    assert_eq!(stale.status, StatusCode::CONFLICT);
+}
+
+#[sqlx::test]
+async fn claps_add_up_to_the_per_user_cap(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let other = app.register("other").await;
+    let slug = app.create_article(&author, "Applause", &[]).await;
+    let uri = format!("/api/articles/{slug}/clap");
+    let clap = |count: i32| json!({ "clap": { "count": count } });
+
+    let single = app.post(&uri, Some(&reader), json!({})).await;
+    assert_eq!(single.status, StatusCode::OK, "{}", single.body);
+    assert_eq!(single.body["article"]["clapsFromMe"], 1);
+
+    let more = app.post(&uri, Some(&reader), clap(30)).await;
+    assert_eq!(more.body["article"]["clapsFromMe"], 31);
+
+    // Going past the cap is clamped, not an error, and stays clamped.
+    for _ in 0..2 {
+        let capped = app.post(&uri, Some(&reader), clap(30)).await;
+        assert_eq!(capped.status, StatusCode::OK, "{}", capped.body);
+        assert_eq!(capped.body["article"]["clapsFromMe"], 50);
+        assert_eq!(capped.body["article"]["clapsTotal"], 50);
+    }
+
+    let burst = app.post(&uri, Some(&other), clap(1000)).await;
+    assert_eq!(burst.status, StatusCode::OK, "{}", burst.body);
+    assert_eq!(burst.body["article"]["clapsFromMe"], 50);
+    assert_eq!(burst.body["article"]["clapsTotal"], 100);
+
+    let zero = app.post(&uri, Some(&other), clap(0)).await;
+    assert_eq!(zero.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
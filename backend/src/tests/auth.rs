@@ -0,0 +1,20 @@
+use std::{thread, time::Duration};
+
+use jsonwebtoken::{DecodingKey, EncodingKey};
+
+use super::{PRIVATE_KEY, PUBLIC_KEY};
+use crate::auth::{generate_jwt, verify_jwt};
+
+#[test]
+fn token_stops_verifying_once_its_ttl_passes() {
+    let encoding_key = EncodingKey::from_rsa_pem(PRIVATE_KEY.as_bytes()).unwrap();
+    let decoding_key = DecodingKey::from_rsa_pem(PUBLIC_KEY.as_bytes()).unwrap();
+
+    let token = generate_jwt(7, &encoding_key, chrono::Duration::seconds(1)).unwrap();
+    assert_eq!(verify_jwt(&token, &decoding_key).unwrap().user_id, 7);
+
+    // `exp` has whole-second precision, so wait past the next full second.
+    thread::sleep(Duration::from_millis(2100));
+
+    assert!(verify_jwt(&token, &decoding_key).is_err());
+}
@@ -0,0 +1,594 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::TestApp;
+
+#[sqlx::test]
+async fn comment_author_can_delete_own_comment(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Moderation", &[]).await;
+    let id = app.comment(&reader, &slug, "Nice post").await;
+
+    let response = app
+        .delete(
+            &format!("/api/articles/{slug}/comments/{id}"),
+            Some(&reader),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(comments.body["comments"].as_array().unwrap().len(), 0);
+}
+
+#[sqlx::test]
+async fn article_author_can_delete_comments_on_their_article(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Moderation", &[]).await;
+    let id = app.comment(&reader, &slug, "Spam").await;
+
+    let response = app
+        .delete(
+            &format!("/api/articles/{slug}/comments/{id}"),
+            Some(&author),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(comments.body["comments"].as_array().unwrap().len(), 0);
+}
+
+#[sqlx::test]
+async fn unrelated_user_cannot_delete_comment(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let stranger = app.register("stranger").await;
+    let slug = app.create_article(&author, "Moderation", &[]).await;
+    let id = app.comment(&reader, &slug, "Nice post").await;
+
+    let response = app
+        .delete(
+            &format!("/api/articles/{slug}/comments/{id}"),
+            Some(&stranger),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(comments.body["comments"].as_array().unwrap().len(), 1);
+}
+
+#[sqlx::test]
+async fn deleting_a_missing_comment_is_not_found(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Moderation", &[]).await;
+    let other = app.create_article(&author, "Elsewhere", &[]).await;
+    let id = app.comment(&author, &slug, "Nice post").await;
+
+    for uri in [
+        format!("/api/articles/{slug}/comments/{}", id + 1),
+        format!("/api/articles/{other}/comments/{id}"),
+    ] {
+        let response = app.delete(&uri, Some(&author)).await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{uri}");
+        assert_eq!(response.body["error"]["comment"], json!(["not found"]));
+    }
+
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(comments.body["comments"].as_array().unwrap().len(), 1);
+}
+
+#[sqlx::test]
+async fn reacting_twice_with_the_same_emoji_toggles_it_off(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Reactions", &[]).await;
+    let id = app.comment(&author, &slug, "Thanks for reading").await;
+    let uri = format!("/api/articles/{slug}/comments/{id}/react");
+    let thumbs_up = json!({ "reaction": { "emoji": "👍" } });
+
+    let first = app.post(&uri, Some(&reader), thumbs_up.clone()).await;
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(first.body["comment"]["reactions"], json!({ "👍": 1 }));
+    assert_eq!(first.body["comment"]["myReactions"], json!(["👍"]));
+
+    let other = app.post(&uri, Some(&author), thumbs_up.clone()).await;
+    assert_eq!(other.body["comment"]["reactions"], json!({ "👍": 2 }));
+
+    let second = app.post(&uri, Some(&reader), thumbs_up).await;
+    assert_eq!(second.status, StatusCode::OK);
+    assert_eq!(second.body["comment"]["reactions"], json!({ "👍": 1 }));
+    assert!(second.body["comment"].get("myReactions").is_none());
+}
+
+#[sqlx::test]
+async fn a_user_can_not_double_count_an_emoji(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Reactions", &[]).await;
+    let id = app.comment(&author, &slug, "Thanks for reading").await;
+    let uri = format!("/api/articles/{slug}/comments/{id}/react");
+
+    let party = json!({ "reaction": { "emoji": "🎉" } });
+
+    let (first, second) = futures::join!(
+        app.post(&uri, Some(&reader), party.clone()),
+        app.post(&uri, Some(&reader), party),
+    );
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(second.status, StatusCode::OK);
+
+    let count = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM comment_reactions WHERE comment_id = $1",
+        id as i32
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert!(count.unwrap() <= 1);
+
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    let reactions = &comments.body["comments"][0]["reactions"];
+    assert!(reactions.is_null() || *reactions == json!({ "🎉": 1 }));
+}
+
+#[sqlx::test]
+async fn reacting_to_a_missing_comment_is_not_found(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Reactions", &[]).await;
+
+    let response = app
+        .post(
+            &format!("/api/articles/{slug}/comments/4242/react"),
+            Some(&author),
+            json!({ "reaction": { "emoji": "👍" } }),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.body["error"]["comment"], json!(["not found"]));
+}
+
+#[sqlx::test]
+async fn comments_can_be_filtered_by_author(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    app.register("lurker").await;
+    let slug = app.create_article(&author, "Filtering", &[]).await;
+    app.comment(&reader, &slug, "First!").await;
+    app.comment(&author, &slug, "Thanks").await;
+    app.comment(&reader, &slug, "Follow-up question").await;
+
+    let response = app
+        .get(
+            &format!("/api/articles/{slug}/comments?author=reader"),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let comments = response.body["comments"].as_array().unwrap();
+    assert_eq!(comments.len(), 2);
+    assert!(comments
+        .iter()
+        .all(|comment| comment["author"]["username"] == "reader"));
+
+    let response = app
+        .get(
+            &format!("/api/articles/{slug}/comments?author=lurker"),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["comments"], json!([]));
+
+    let response = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(response.body["comments"].as_array().unwrap().len(), 3);
+}
+
+#[sqlx::test]
+async fn comment_counts_come_back_per_slug(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let busy = app.create_article(&author, "Busy", &[]).await;
+    let quiet = app.create_article(&author, "Quiet", &[]).await;
+    app.comment(&author, &busy, "One").await;
+    app.comment(&author, &busy, "Two").await;
+
+    let response = app
+        .post(
+            "/api/articles/comments/count",
+            None,
+            json!({ "slugs": [&busy, &quiet, "missing"] }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["counts"], json!({ &busy: 2, &quiet: 0 }));
+
+    let article = app.get(&format!("/api/articles/{busy}"), None).await;
+    assert_eq!(article.body["article"]["commentsCount"], 2);
+}
+
+#[sqlx::test]
+async fn comment_counts_for_no_slugs_are_empty(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app
+        .post("/api/articles/comments/count", None, json!({ "slugs": [] }))
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["counts"], json!({}));
+}
+
+#[sqlx::test]
+async fn rate_limit_headers_count_down(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Chatty", &[]).await;
+    let uri = format!("/api/articles/{slug}/comments");
+    let comment = json!({ "comment": { "body": "Hello" } });
+
+    let first = app.post(&uri, Some(&author), comment.clone()).await;
+    let second = app.post(&uri, Some(&author), comment).await;
+
+    assert_eq!(first.headers["x-ratelimit-limit"], "10");
+    assert_eq!(first.headers["x-ratelimit-remaining"], "9");
+    assert_eq!(second.headers["x-ratelimit-remaining"], "8");
+    let reset: u64 = second.headers["x-ratelimit-reset"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&reset));
+
+    // Routes without a limiter don't get the headers.
+    let listing = app.get(&uri, None).await;
+    assert!(listing.headers.get("x-ratelimit-limit").is_none());
+}
+
+/// Comment bodies in the order `get_comments` returns them.
+async fn comment_bodies(app: &TestApp, slug: &str) -> Vec<String> {
+    let response = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    response.body["comments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| comment["body"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[sqlx::test]
+async fn pinned_comment_comes_first(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "FAQ", &[]).await;
+    let answer = app.comment(&author, &slug, "The answer").await;
+    let question = app.comment(&reader, &slug, "A question").await;
+    app.comment(&reader, &slug, "Latest").await;
+
+    let response = app
+        .post(
+            &format!("/api/articles/{slug}/comments/{answer}/pin"),
+            Some(&author),
+            json!({}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body["comment"]["pinnedAt"].is_string());
+    assert_eq!(
+        comment_bodies(&app, &slug).await,
+        ["The answer", "Latest", "A question"]
+    );
+
+    // Pinning another comment takes the pin away from the first.
+    app.post(
+        &format!("/api/articles/{slug}/comments/{question}/pin"),
+        Some(&author),
+        json!({}),
+    )
+    .await;
+    assert_eq!(
+        comment_bodies(&app, &slug).await,
+        ["A question", "Latest", "The answer"]
+    );
+
+    let response = app
+        .delete(
+            &format!("/api/articles/{slug}/comments/{question}/pin"),
+            Some(&author),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body["comment"].get("pinnedAt").is_none());
+    assert_eq!(
+        comment_bodies(&app, &slug).await,
+        ["Latest", "A question", "The answer"]
+    );
+}
+
+#[sqlx::test]
+async fn concurrent_pins_leave_one_comment_pinned(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "FAQ", &[]).await;
+    let first = app.comment(&author, &slug, "First").await;
+    let second = app.comment(&author, &slug, "Second").await;
+    let first_uri = format!("/api/articles/{slug}/comments/{first}/pin");
+    let second_uri = format!("/api/articles/{slug}/comments/{second}/pin");
+
+    for _ in 0..5 {
+        let (a, b) = futures::join!(
+            app.post(&first_uri, Some(&author), json!({})),
+            app.post(&second_uri, Some(&author), json!({})),
+        );
+        assert_eq!(a.status, StatusCode::OK, "{}", a.body);
+        assert_eq!(b.status, StatusCode::OK, "{}", b.body);
+
+        let comments = app
+            .get(&format!("/api/articles/{slug}/comments"), None)
+            .await;
+        let pinned = comments.body["comments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|comment| comment["pinnedAt"].is_string())
+            .count();
+        assert_eq!(pinned, 1);
+    }
+}
+
+#[sqlx::test]
+async fn only_the_article_author_can_pin(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "FAQ", &[]).await;
+    let id = app.comment(&reader, &slug, "Pin me").await;
+    let uri = format!("/api/articles/{slug}/comments/{id}/pin");
+
+    let pin = app.post(&uri, Some(&reader), json!({})).await;
+    assert_eq!(pin.status, StatusCode::FORBIDDEN);
+
+    app.post(&uri, Some(&author), json!({})).await;
+    let unpin = app.delete(&uri, Some(&reader)).await;
+    assert_eq!(unpin.status, StatusCode::FORBIDDEN);
+
+    let missing = app
+        .post(
+            &format!("/api/articles/{slug}/comments/4242/pin"),
+            Some(&author),
+            json!({}),
+        )
+        .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn tree_mode_nests_replies_under_their_parents(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Threads", &[]).await;
+    let uri = format!("/api/articles/{slug}/comments");
+    let reply =
+        |body: &str, parent: i64| json!({ "comment": { "body": body, "parentId": parent } });
+
+    let question = app.comment(&reader, &slug, "Question").await;
+    let other = app.comment(&reader, &slug, "Another thread").await;
+    let answer = app
+        .post(&uri, Some(&author), reply("First answer", question))
+        .await;
+    assert_eq!(answer.status, StatusCode::OK);
+    let answer = answer.body["comment"]["id"].as_i64().unwrap();
+    app.post(&uri, Some(&reader), reply("Second answer", question))
+        .await;
+    app.post(&uri, Some(&reader), reply("Thanks", answer)).await;
+
+    let response = app.get(&format!("{uri}?tree=true"), None).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let threads = response.body["comments"].as_array().unwrap();
+    assert_eq!(threads.len(), 2);
+    assert_eq!(threads[0]["comment"]["id"], other);
+    assert_eq!(threads[0]["replies"], json!([]));
+
+    let thread = &threads[1];
+    assert_eq!(thread["comment"]["id"], question);
+    let replies = thread["replies"].as_array().unwrap();
+    let bodies: Vec<_> = replies
+        .iter()
+        .map(|reply| reply["comment"]["body"].as_str().unwrap())
+        .collect();
+    assert_eq!(bodies, ["First answer", "Second answer"]);
+    assert_eq!(replies[0]["replies"][0]["comment"]["body"], "Thanks");
+
+    let flat = app.get(&uri, None).await;
+    assert_eq!(flat.body["comments"].as_array().unwrap().len(), 5);
+}
+
+#[sqlx::test]
+async fn replies_survive_their_parent_being_deleted(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let asker = app.register("asker").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Threads", &[]).await;
+    let uri = format!("/api/articles/{slug}/comments");
+    let reply =
+        |body: &str, parent: i64| json!({ "comment": { "body": body, "parentId": parent } });
+
+    let moderated = app.comment(&asker, &slug, "Off topic").await;
+    let abandoned = app.comment(&asker, &slug, "Question").await;
+    app.post(&uri, Some(&reader), reply("Reply to removed", moderated))
+        .await;
+    app.post(&uri, Some(&reader), reply("Answer", abandoned))
+        .await;
+
+    // One parent removed by the article's author, the other with its author's account.
+    let removed = app
+        .delete(&format!("{uri}/{moderated}"), Some(&author))
+        .await;
+    assert_eq!(removed.status, StatusCode::OK, "{}", removed.body);
+    let deleted = app.delete("/api/user", Some(&asker)).await;
+    assert_eq!(deleted.status, StatusCode::OK, "{}", deleted.body);
+
+    let response = app.get(&format!("{uri}?tree=true"), None).await;
+    let threads = response.body["comments"].as_array().unwrap();
+    let mut bodies: Vec<_> = threads
+        .iter()
+        .map(|thread| {
+            assert_eq!(thread["comment"]["parentId"], json!(null));
+            assert_eq!(thread["replies"], json!([]));
+            thread["comment"]["body"].as_str().unwrap()
+        })
+        .collect();
+    bodies.sort_unstable();
+    assert_eq!(bodies, ["Answer", "Reply to removed"]);
+}
+
+#[sqlx::test]
+async fn preview_renders_markdown_without_scripts(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Previewed", &[]).await;
+    let uri = format!("/api/articles/{slug}/comments/preview");
+
+    let body = "**Nice** post, see [this](https://example.com) \
+                and [that](javascript:alert(1)).\n\n<script>alert(1)</script>\n";
+    let response = app
+        .post(&uri, Some(&author), json!({ "comment": { "body": body } }))
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+    let html = response.body["preview"]["html"].as_str().unwrap();
+    assert_eq!(
+        html,
+        "<p><strong>Nice</strong> post, see <a href=\"https://example.com\">this</a> \
+         and <a href=\"#\">that</a>.</p>\n"
+    );
+
+    // Previewing saves nothing.
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(comments.body["comments"], json!([]));
+}
+
+#[sqlx::test]
+async fn only_the_comment_author_can_edit_it(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Editable", &[]).await;
+    let id = app.comment(&reader, &slug, "Frist").await;
+    let uri = format!("/api/articles/{slug}/comments/{id}");
+    let edit = json!({ "comment": { "body": "First" } });
+
+    let edited = app.put(&uri, Some(&reader), edit.clone()).await;
+    assert_eq!(edited.status, StatusCode::OK, "{}", edited.body);
+    assert_eq!(edited.body["comment"]["body"], "First");
+
+    let forbidden = app.put(&uri, Some(&author), edit.clone()).await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+
+    let missing = app
+        .put(
+            &format!("/api/articles/{slug}/comments/{}", id + 1),
+            Some(&reader),
+            edit.clone(),
+        )
+        .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.body["error"]["comment"], json!(["not found"]));
+
+    // Once the article is a draft again, the comment is out of the commenter's reach.
+    let unpublished = app
+        .put(
+            &format!("/api/articles/{slug}"),
+            Some(&author),
+            json!({ "article": { "published": false, "version": 1 } }),
+        )
+        .await;
+    assert_eq!(unpublished.status, StatusCode::OK, "{}", unpublished.body);
+    let hidden = app
+        .put(
+            &uri,
+            Some(&reader),
+            json!({ "comment": { "body": "Edited" } }),
+        )
+        .await;
+    assert_eq!(hidden.status, StatusCode::NOT_FOUND);
+    assert_eq!(hidden.body["error"]["comment"], json!(["not found"]));
+
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), Some(&author))
+        .await;
+    assert_eq!(comments.body["comments"][0]["body"], "First");
+}
+
+#[sqlx::test]
+async fn reactions_outside_the_callers_reach_are_left_alone(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Reactions", &[]).await;
+    let other = app.create_article(&author, "Elsewhere", &[]).await;
+    let id = app.comment(&author, &slug, "React to me").await;
+    let thumbs_up = json!({ "reaction": { "emoji": "👍" } });
+    let react = |slug: &str| format!("/api/articles/{slug}/comments/{id}/react");
+
+    let added = app
+        .post(&react(&slug), Some(&reader), thumbs_up.clone())
+        .await;
+    assert_eq!(added.body["comment"]["reactions"]["👍"], 1);
+
+    // Under another article's slug the comment doesn't exist.
+    let mismatched = app
+        .post(&react(&other), Some(&reader), thumbs_up.clone())
+        .await;
+    assert_eq!(mismatched.status, StatusCode::NOT_FOUND);
+
+    // Nor while the article is a draft the reader can't see.
+    let article_uri = format!("/api/articles/{slug}");
+    let set_published = |published: bool, version: i32| {
+        app.put(
+            &article_uri,
+            Some(&author),
+            json!({ "article": { "published": published, "version": version } }),
+        )
+    };
+    assert_eq!(set_published(false, 1).await.status, StatusCode::OK);
+    let hidden = app.post(&react(&slug), Some(&reader), thumbs_up).await;
+    assert_eq!(hidden.status, StatusCode::NOT_FOUND);
+    assert_eq!(set_published(true, 2).await.status, StatusCode::OK);
+
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), None)
+        .await;
+    assert_eq!(comments.body["comments"][0]["reactions"]["👍"], 1);
+}
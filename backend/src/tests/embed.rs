@@ -0,0 +1,75 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::TestApp;
+
+#[sqlx::test]
+async fn embed_has_the_oembed_fields_and_links_the_canonical_url(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Embeddable <b>", &[]).await;
+
+    let response = app
+        .get(&format!("/api/articles/{slug}/embed?format=json"), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    let embed = &response.body;
+
+    let url = format!("http://localhost:8000/#/article/{slug}");
+    assert_eq!(embed["version"], "1.0");
+    assert_eq!(embed["type"], "rich");
+    assert_eq!(embed["title"], "Embeddable <b>");
+    assert_eq!(embed["author_name"], "author");
+    assert_eq!(
+        embed["author_url"],
+        "http://localhost:8000/#/profile/author"
+    );
+    assert_eq!(embed["provider_url"], "http://localhost:8000/");
+    assert_eq!(embed["url"], url);
+    assert_eq!(embed["width"], 600);
+    assert_eq!(embed["height"], 200);
+
+    let html = embed["html"].as_str().unwrap();
+    assert!(html.contains(&format!(r#"<a href="{url}">"#)), "{html}");
+    assert!(html.contains("Embeddable &lt;b&gt;"), "{html}");
+
+    // No avatar, so no thumbnail fields at all.
+    assert!(embed.get("thumbnail_url").is_none());
+}
+
+#[sqlx::test]
+async fn embed_reports_the_avatar_and_respects_size_limits(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Embeddable", &[]).await;
+    let updated = app
+        .put(
+            "/api/user",
+            Some(&author),
+            json!({ "user": { "image": "https://example.com/me.png" } }),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK, "{}", updated.body);
+
+    let response = app
+        .get(
+            &format!("/api/articles/{slug}/embed?maxwidth=300&maxheight=1000"),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["thumbnail_url"], "https://example.com/me.png");
+    assert_eq!(response.body["thumbnail_width"], 100);
+    assert_eq!(response.body["thumbnail_height"], 100);
+    assert_eq!(response.body["width"], 300);
+    assert_eq!(response.body["height"], 200);
+
+    let xml = app
+        .get(&format!("/api/articles/{slug}/embed?format=xml"), None)
+        .await;
+    assert_eq!(xml.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let draft = app.create_draft(&author, "Not yet").await;
+    let hidden = app.get(&format!("/api/articles/{draft}/embed"), None).await;
+    assert_eq!(hidden.status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+use super::TestApp;
+
+#[sqlx::test]
+async fn exhausted_pool_answers_500_instead_of_panicking(pool: PgPool) {
+    let tiny = PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_millis(200))
+        .connect_with(pool.connect_options().clone())
+        .await
+        .unwrap();
+    let app = TestApp::with_state(pool, |state| state.pool = tiny.clone()).await;
+
+    let held = tiny.acquire().await.unwrap();
+    let response = app.get("/api/tags", None).await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.body["error"].is_string());
+
+    drop(held);
+    let response = app.get("/api/tags", None).await;
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn health_is_ok_with_a_live_pool(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app.get("/api/health", None).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, json!({ "status": "ok" }));
+}
+
+#[sqlx::test]
+async fn health_is_degraded_without_a_connection(pool: PgPool) {
+    let tiny = PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_millis(200))
+        .connect_with(pool.connect_options().clone())
+        .await
+        .unwrap();
+    let app = TestApp::with_state(pool, |state| state.pool = tiny.clone()).await;
+
+    let _held = tiny.acquire().await.unwrap();
+    let response = app.get("/api/health", None).await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.body, json!({ "status": "degraded" }));
+}
+
+#[sqlx::test]
+async fn db_latency_reports_timings_and_pool_numbers_to_admins(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let admin = app.register("admin").await;
+    let reader = app.register("reader").await;
+    sqlx::query!("UPDATE users SET is_admin = TRUE WHERE username = 'admin'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app.get("/api/healthz/db-latency", Some(&admin)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+    let latency = &response.body["latency"];
+    let ms = |field: &str| latency[field].as_f64().unwrap();
+    for field in ["acquireMs", "queryMs", "totalMs"] {
+        assert!((0.0..10_000.0).contains(&ms(field)), "{field}: {latency}");
+    }
+    assert!(ms("totalMs") >= ms("queryMs"));
+
+    let stats = &response.body["pool"];
+    let count = |field: &str| stats[field].as_u64().unwrap();
+    assert!(count("size") >= 1, "{stats}");
+    assert!(count("idle") <= count("size"), "{stats}");
+    assert_eq!(count("inUse"), count("size") - count("idle"));
+
+    let forbidden = app.get("/api/healthz/db-latency", Some(&reader)).await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+}
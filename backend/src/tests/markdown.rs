@@ -0,0 +1,35 @@
+use crate::{markdown::render_markdown, toc::table_of_contents};
+
+/// The `id` attributes in `html`, in document order.
+fn heading_ids(html: &str) -> Vec<&str> {
+    html.split(" id=\"")
+        .skip(1)
+        .map(|rest| rest.split('"').next().unwrap())
+        .collect()
+}
+
+#[test]
+fn rendered_heading_ids_match_toc_anchors() {
+    let markdown = "# Intro\n\n## What's New in 2.0?\n\n### Intro\n\nText.\n\n## Intro\n\n\
+                    ## `code` & more\n\n## !!!\n";
+
+    let html = render_markdown(markdown);
+    let anchors: Vec<String> = table_of_contents(markdown)
+        .into_iter()
+        .map(|entry| entry.anchor)
+        .collect();
+
+    assert_eq!(
+        anchors,
+        [
+            "intro",
+            "whats-new-in-20",
+            "intro-1",
+            "intro-2",
+            "code-more",
+            "section"
+        ]
+    );
+    assert_eq!(heading_ids(&html), anchors);
+    assert!(html.contains(r#"<h2 id="whats-new-in-20">What's New in 2.0?</h2>"#));
+}
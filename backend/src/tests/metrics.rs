@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use sqlx::PgPool;
+
+use super::TestApp;
+use crate::metrics::Metrics;
+
+async fn app_with_token(pool: PgPool) -> TestApp {
+    TestApp::with_state(pool, |state| {
+        state.metrics = Arc::new(Metrics::new(Some("scrape-secret".to_string())))
+    })
+    .await
+}
+
+fn scrape_request(authorization: &str) -> Request<Body> {
+    Request::get("/api/metrics")
+        .header(header::AUTHORIZATION, authorization)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn scrape_reports_requests_per_route(pool: PgPool) {
+    let app = app_with_token(pool).await;
+    app.get("/api/tags", None).await;
+    app.get("/api/tags", None).await;
+    app.get("/api/articles/missing", None).await;
+
+    let response = app.send(scrape_request("Bearer scrape-secret")).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.headers[header::CONTENT_TYPE],
+        "text/plain; version=0.0.4"
+    );
+    let text = response.body.as_str().unwrap();
+    assert!(text.contains(r#"http_requests_total{method="GET",route="/api/tags",status="200"} 2"#));
+    assert!(text.contains(
+        r#"http_requests_total{method="GET",route="/api/articles/:slug",status="404"} 1"#
+    ));
+    assert!(text.contains("# TYPE http_request_errors_total counter"));
+    assert!(
+        text.contains(r#"http_request_duration_seconds_count{method="GET",route="/api/tags"} 2"#)
+    );
+}
+
+#[sqlx::test]
+async fn scrape_requires_the_token(pool: PgPool) {
+    let app = app_with_token(pool).await;
+
+    let anonymous = app.get("/api/metrics", None).await;
+    assert_eq!(anonymous.status, StatusCode::FORBIDDEN);
+
+    let wrong = app.send(scrape_request("Bearer guess")).await;
+    assert_eq!(wrong.status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn metrics_are_hidden_without_a_configured_token(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app.get("/api/metrics", None).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
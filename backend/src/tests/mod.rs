@@ -0,0 +1,299 @@
+//! Request-level tests that drive the full router against a real database.
+//!
+//! `#[sqlx::test]` creates a fresh database for every test, so `DATABASE_URL` has to point at a
+//! Postgres server the tests may create databases on.
+
+mod articles;
+mod auth;
+mod comments;
+mod embed;
+mod health;
+mod markdown;
+mod metrics;
+mod ratelimit;
+mod translate;
+mod users;
+
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    Router,
+};
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+use crate::{
+    api::prepare_db, mail::Mailer, metrics::Metrics, translate::EchoTranslator, AppState, Config,
+};
+
+/// The address requests come from unless a test sets its own `ConnectInfo`.
+pub const CLIENT_ADDR: ([u8; 4], u16) = ([127, 0, 0, 1], 50_000);
+
+/// A throwaway key pair, only ever used to sign tokens in tests.
+const PRIVATE_KEY: &str = include_str!("private_key.pem");
+const PUBLIC_KEY: &str = include_str!("public_key.pem");
+
+pub fn test_config() -> Config {
+    Config {
+        default_page_size: 20,
+        token_ttl_days: 30,
+        max_tags: 10,
+        public_url: "http://localhost:8000".to_string(),
+        auth_requests_per_minute: 1000,
+        trusted_proxy: None,
+    }
+}
+
+/// Keeps every message instead of sending it, so tests can read tokens back.
+#[derive(Default)]
+pub struct TestMailer {
+    sent: Mutex<Vec<(String, String)>>,
+}
+
+impl TestMailer {
+    /// The body of the latest message sent to `to`.
+    pub fn last_sent_to(&self, to: &str) -> Option<String> {
+        let sent = self.sent.lock().unwrap();
+        sent.iter()
+            .rev()
+            .find(|(recipient, _)| recipient == to)
+            .map(|(_, body)| body.clone())
+    }
+}
+
+#[async_trait]
+impl Mailer for TestMailer {
+    async fn send(&self, to: &str, _subject: &str, body: &str) -> anyhow::Result<()> {
+        let mut sent = self.sent.lock().unwrap();
+        sent.push((to.to_string(), body.to_string()));
+        Ok(())
+    }
+}
+
+pub struct TestApp {
+    pub pool: PgPool,
+    pub mailer: Arc<TestMailer>,
+    router: Router,
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+impl TestApp {
+    pub async fn new(pool: PgPool) -> Self {
+        Self::with_config(pool, test_config()).await
+    }
+
+    pub async fn with_config(pool: PgPool, config: Config) -> Self {
+        Self::with_state(pool, |state| state.config = config).await
+    }
+
+    /// Builds the app from the test defaults after letting `customize` adjust them.
+    pub async fn with_state(pool: PgPool, customize: impl FnOnce(&mut AppState)) -> Self {
+        prepare_db(&pool).await.unwrap();
+
+        let mailer = Arc::new(TestMailer::default());
+        let mut state = AppState {
+            pool: pool.clone(),
+            encoding_key: EncodingKey::from_rsa_pem(PRIVATE_KEY.as_bytes()).unwrap(),
+            decoding_key: DecodingKey::from_rsa_pem(PUBLIC_KEY.as_bytes()).unwrap(),
+            metrics: Arc::new(Metrics::new(None)),
+            translator: Arc::new(EchoTranslator),
+            mailer: Some(mailer.clone()),
+            config: test_config(),
+            images_folder: std::env::temp_dir(),
+        };
+        customize(&mut state);
+
+        Self {
+            pool,
+            mailer,
+            router: crate::app(state, PathBuf::from("dist")),
+        }
+    }
+
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> TestResponse {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Token {token}"));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        self.send(request).await
+    }
+
+    /// Sends a request built by hand, for when the helpers' headers aren't enough.
+    pub async fn send(&self, mut request: Request<Body>) -> TestResponse {
+        if request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .is_none()
+        {
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(CLIENT_ADDR)));
+        }
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        // Non-JSON bodies, like the metrics text, come back as a plain string.
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    pub async fn get(&self, uri: &str, token: Option<&str>) -> TestResponse {
+        self.request(Method::GET, uri, token, None).await
+    }
+
+    pub async fn post(&self, uri: &str, token: Option<&str>, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, token, Some(body)).await
+    }
+
+    pub async fn put(&self, uri: &str, token: Option<&str>, body: Value) -> TestResponse {
+        self.request(Method::PUT, uri, token, Some(body)).await
+    }
+
+    pub async fn delete(&self, uri: &str, token: Option<&str>) -> TestResponse {
+        self.request(Method::DELETE, uri, token, None).await
+    }
+
+    /// Registers `username` with a matching email and returns their token.
+    pub async fn register(&self, username: &str) -> String {
+        let response = self
+            .post(
+                "/api/users",
+                None,
+                json!({ "user": {
+                    "username": username,
+                    "email": format!("{username}@example.com"),
+                    "password": "password123",
+                }}),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        response.body["user"]["token"].as_str().unwrap().to_string()
+    }
+
+    /// Publishes an article and returns its slug.
+    pub async fn create_article(&self, token: &str, title: &str, tags: &[&str]) -> String {
+        let response = self
+            .post(
+                "/api/articles",
+                Some(token),
+                json!({ "article": {
+                    "title": title,
+                    "description": format!("About {title}"),
+                    "body": format!("All about {title}."),
+                    "tagList": tags,
+                }}),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        response.body["article"]["slug"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    pub async fn user_id(&self, username: &str) -> i32 {
+        sqlx::query_scalar!("SELECT id FROM users WHERE username = $1", username)
+            .fetch_one(&self.pool)
+            .await
+            .unwrap()
+    }
+
+    /// Saves an unpublished article and returns its slug.
+    pub async fn create_draft(&self, token: &str, title: &str) -> String {
+        let response = self
+            .post(
+                "/api/articles",
+                Some(token),
+                json!({ "article": {
+                    "title": title,
+                    "description": format!("About {title}"),
+                    "body": format!("All about {title}."),
+                    "tagList": [],
+                    "published": false,
+                }}),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        response.body["article"]["slug"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    pub async fn follow(&self, token: &str, username: &str) {
+        let response = self
+            .post(
+                &format!("/api/profiles/{username}/follow"),
+                Some(token),
+                json!({}),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    }
+
+    pub async fn favorite(&self, token: &str, slug: &str) {
+        let response = self
+            .post(
+                &format!("/api/articles/{slug}/favorite"),
+                Some(token),
+                json!({}),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    }
+
+    /// Comments on the article and returns the comment's id.
+    pub async fn comment(&self, token: &str, slug: &str, body: &str) -> i64 {
+        let response = self
+            .post(
+                &format!("/api/articles/{slug}/comments"),
+                Some(token),
+                json!({ "comment": { "body": body } }),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        response.body["comment"]["id"].as_i64().unwrap()
+    }
+}
@@ -0,0 +1,104 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Method, Request, StatusCode},
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::{test_config, TestApp, TestResponse};
+use crate::Config;
+
+/// An app that allows two logins per client and minute.
+async fn app_with_limit(pool: PgPool, trusted_proxy: Option<IpAddr>) -> TestApp {
+    TestApp::with_config(
+        pool,
+        Config {
+            auth_requests_per_minute: 2,
+            trusted_proxy,
+            ..test_config()
+        },
+    )
+    .await
+}
+
+/// A failed login from `peer`, optionally claiming to be forwarded for someone else.
+async fn login_from(app: &TestApp, peer: [u8; 4], forwarded_for: Option<&str>) -> TestResponse {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/users/login")
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(forwarded_for) = forwarded_for {
+        request = request.header("x-forwarded-for", forwarded_for);
+    }
+    let mut request = request
+        .body(Body::from(
+            json!({ "user": { "email": "nobody@example.com", "password": "guess" } }).to_string(),
+        ))
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from((peer, 40_000))));
+
+    app.send(request).await
+}
+
+#[sqlx::test]
+async fn repeated_logins_are_rejected_with_retry_after(pool: PgPool) {
+    let app = app_with_limit(pool, None).await;
+
+    for _ in 0..2 {
+        let response = login_from(&app, [203, 0, 113, 1], None).await;
+        assert_ne!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let limited = login_from(&app, [203, 0, 113, 1], None).await;
+    assert_eq!(limited.status, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = limited.headers[header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+    assert_eq!(limited.headers["x-ratelimit-remaining"], "0");
+
+    // Other clients have their own budget.
+    let other = login_from(&app, [203, 0, 113, 2], None).await;
+    assert_ne!(other.status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[sqlx::test]
+async fn forwarded_for_is_ignored_from_untrusted_peers(pool: PgPool) {
+    let app = app_with_limit(pool, None).await;
+
+    // A client can't earn a fresh budget by making up addresses.
+    for (i, spoofed) in ["198.51.100.1", "198.51.100.2", "198.51.100.3"]
+        .iter()
+        .enumerate()
+    {
+        let response = login_from(&app, [203, 0, 113, 1], Some(spoofed)).await;
+        let limited = response.status == StatusCode::TOO_MANY_REQUESTS;
+        assert_eq!(limited, i == 2, "request {i}");
+    }
+}
+
+#[sqlx::test]
+async fn trusted_proxy_hop_identifies_the_client(pool: PgPool) {
+    let proxy = [10, 0, 0, 1];
+    let app = app_with_limit(pool, Some(IpAddr::from(proxy))).await;
+
+    // Only the right-most hop was added by the proxy; whatever precedes it is the client's word.
+    for spoofed in ["198.51.100.1", "198.51.100.2"] {
+        let forwarded = format!("{spoofed}, 203.0.113.1");
+        let response = login_from(&app, proxy, Some(&forwarded)).await;
+        assert_ne!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    }
+    let limited = login_from(&app, proxy, Some("198.51.100.3, 203.0.113.1")).await;
+    assert_eq!(limited.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Clients behind the same proxy are still told apart.
+    let other = login_from(&app, proxy, Some("203.0.113.2")).await;
+    assert_ne!(other.status, StatusCode::TOO_MANY_REQUESTS);
+}
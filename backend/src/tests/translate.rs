@@ -0,0 +1,56 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::TestApp;
+
+#[sqlx::test]
+async fn echo_translation_is_cached_until_the_article_changes(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let reader = app.register("reader").await;
+    let slug = app.create_article(&author, "Hello", &[]).await;
+    let uri = format!("/api/articles/{slug}/translate?lang=de");
+
+    let first = app.post(&uri, Some(&reader), json!({})).await;
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(
+        first.body["translation"],
+        json!({
+            "lang": "de",
+            "title": "Hello",
+            "description": "About Hello",
+            "body": "All about Hello.",
+            "cached": false,
+        })
+    );
+
+    let second = app.post(&uri, Some(&reader), json!({})).await;
+    assert_eq!(second.body["translation"]["cached"], true);
+
+    app.put(
+        &format!("/api/articles/{slug}"),
+        Some(&author),
+        json!({ "article": { "body": "Edited.", "version": 1 } }),
+    )
+    .await;
+    let after_edit = app.post(&uri, Some(&reader), json!({})).await;
+    assert_eq!(after_edit.body["translation"]["cached"], false);
+    assert_eq!(after_edit.body["translation"]["body"], "Edited.");
+}
+
+#[sqlx::test]
+async fn invalid_lang_is_rejected(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let author = app.register("author").await;
+    let slug = app.create_article(&author, "Hello", &[]).await;
+
+    let bad_lang = app
+        .post(
+            &format!("/api/articles/{slug}/translate?lang=-x"),
+            Some(&author),
+            json!({}),
+        )
+        .await;
+    assert_eq!(bad_lang.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,435 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::{TestApp, TestResponse};
+
+async fn login(app: &TestApp, email: &str) -> TestResponse {
+    app.post(
+        "/api/users/login",
+        None,
+        json!({ "user": { "email": email, "password": "password123" } }),
+    )
+    .await
+}
+
+async fn change_email(app: &TestApp, token: &str, email: &str) -> TestResponse {
+    app.put(
+        "/api/user",
+        Some(token),
+        json!({ "user": { "email": email } }),
+    )
+    .await
+}
+
+/// Pulls the token out of the confirmation mail sent to `email`.
+fn mailed_token(app: &TestApp, email: &str) -> String {
+    let body = app.mailer.last_sent_to(email).unwrap();
+    let (_, rest) = body.split_once("token is ").unwrap();
+    rest.split_whitespace().next().unwrap().to_string()
+}
+
+#[sqlx::test]
+async fn email_change_waits_for_confirmation(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+
+    let response = change_email(&app, &alice, "alice@new.example.com").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["user"]["email"], "alice@example.com");
+    assert_eq!(
+        response.body["emailChange"],
+        json!({ "email": "alice@new.example.com" })
+    );
+
+    // Until confirmed, only the old address signs in.
+    assert_eq!(
+        login(&app, "alice@example.com").await.status,
+        StatusCode::OK
+    );
+    assert_ne!(
+        login(&app, "alice@new.example.com").await.status,
+        StatusCode::OK
+    );
+
+    let token = mailed_token(&app, "alice@new.example.com");
+    let confirmed = app
+        .post(
+            "/api/user/email/confirm",
+            Some(&alice),
+            json!({ "confirmation": { "token": token } }),
+        )
+        .await;
+    assert_eq!(confirmed.status, StatusCode::OK);
+    assert_eq!(confirmed.body["user"]["email"], "alice@new.example.com");
+
+    assert_eq!(
+        login(&app, "alice@new.example.com").await.status,
+        StatusCode::OK
+    );
+    assert_ne!(
+        login(&app, "alice@example.com").await.status,
+        StatusCode::OK
+    );
+}
+
+#[sqlx::test]
+async fn email_change_rejects_a_wrong_token(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    change_email(&app, &alice, "alice@new.example.com").await;
+
+    let response = app
+        .post(
+            "/api/user/email/confirm",
+            Some(&alice),
+            json!({ "confirmation": { "token": "guess" } }),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        login(&app, "alice@example.com").await.status,
+        StatusCode::OK
+    );
+}
+
+#[sqlx::test]
+async fn email_change_to_a_taken_address_is_rejected(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    app.register("bob").await;
+
+    let response = change_email(&app, &alice, "Bob@Example.com").await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.body["error"]["email"][0]["message"],
+        "has already been taken"
+    );
+    assert!(app.mailer.last_sent_to("bob@example.com").is_none());
+}
+
+#[sqlx::test]
+async fn failed_profile_update_leaves_no_pending_email_change(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    app.register("bob").await;
+
+    let response = app
+        .put(
+            "/api/user",
+            Some(&alice),
+            json!({ "user": {
+                "username": "bob",
+                "email": "alice@new.example.com",
+                "bio": "Not saved",
+            }}),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.body["error"]["username"][0]["message"],
+        "has already been taken"
+    );
+    assert!(app.mailer.last_sent_to("alice@new.example.com").is_none());
+    let pending = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM email_changes WHERE user_id = $1"#,
+        app.user_id("alice").await,
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(pending, 0);
+
+    let current = app.get("/api/user", Some(&alice)).await;
+    assert_eq!(current.body["user"]["username"], "alice");
+    assert_eq!(current.body["user"]["bio"], json!(null));
+}
+
+#[sqlx::test]
+async fn email_taken_while_pending_fails_confirmation(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    change_email(&app, &alice, "carol@example.com").await;
+    let token = mailed_token(&app, "carol@example.com");
+    app.register("carol").await;
+
+    let response = app
+        .post(
+            "/api/user/email/confirm",
+            Some(&alice),
+            json!({ "confirmation": { "token": token } }),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.body["error"]["email"][0]["message"],
+        "has already been taken"
+    );
+}
+
+#[sqlx::test]
+async fn email_change_needs_a_mailer(pool: PgPool) {
+    let app = TestApp::with_state(pool, |state| state.mailer = None).await;
+    let alice = app.register("alice").await;
+
+    let response = change_email(&app, &alice, "alice@new.example.com").await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        login(&app, "alice@example.com").await.status,
+        StatusCode::OK
+    );
+}
+
+#[sqlx::test]
+async fn deleting_an_account_cleans_up_what_it_touched(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let slug = app.create_article(&alice, "Still here", &[]).await;
+    app.follow(&bob, "alice").await;
+    app.follow(&alice, "bob").await;
+    app.favorite(&bob, &slug).await;
+    app.comment(&bob, &slug, "Bye").await;
+
+    let response = app.delete("/api/user", Some(&bob)).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let leftovers = sqlx::query_scalar!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM follows) + (SELECT COUNT(*) FROM article_favs)
+            AS "count!"
+        "#
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(leftovers, 0);
+
+    let article = app
+        .get(&format!("/api/articles/{slug}"), Some(&alice))
+        .await;
+    assert_eq!(article.status, StatusCode::OK);
+    assert_eq!(article.body["article"]["favoritesCount"], 0);
+    let comments = app
+        .get(&format!("/api/articles/{slug}/comments"), Some(&alice))
+        .await;
+    assert_eq!(comments.status, StatusCode::OK);
+    assert_eq!(comments.body["comments"], json!([]));
+    let feed = app.get("/api/articles/feed", Some(&alice)).await;
+    assert_eq!(feed.status, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn schema_replaces_foreign_keys_only_once(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let constraints = || {
+        sqlx::query!(
+            r#"
+            SELECT conname AS "name!", oid::INT8 AS "oid!", confdeltype::TEXT AS "on_delete!", convalidated
+            FROM pg_constraint
+            WHERE contype = 'f' AND confrelid = 'users'::regclass
+            ORDER BY conname
+            "#
+        )
+        .fetch_all(&app.pool)
+    };
+    let before = constraints().await.unwrap();
+
+    crate::api::prepare_db(&app.pool).await.unwrap();
+
+    let after = constraints().await.unwrap();
+    assert_eq!(before.len(), after.len());
+    for (before, after) in before.iter().zip(&after) {
+        assert_eq!(before.oid, after.oid, "{} was recreated", after.name);
+        assert!(after.convalidated, "{} isn't validated", after.name);
+        if after.name != "events_user_id_fkey" {
+            assert_eq!(after.on_delete, "c", "{} doesn't cascade", after.name);
+        }
+    }
+}
+
+#[sqlx::test]
+async fn badges_count_drafts_and_pending_email_changes(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    app.create_draft(&alice, "First draft").await;
+    app.create_draft(&alice, "Second draft").await;
+    app.create_article(&alice, "Published", &[]).await;
+    app.create_draft(&bob, "Bob's draft").await;
+    change_email(&app, &alice, "alice@new.example.com").await;
+
+    let response = app.get("/api/user/badges", Some(&alice)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.body["badges"],
+        json!({ "drafts": 2, "pendingEmailChanges": 1 })
+    );
+
+    let response = app.get("/api/user/badges", Some(&bob)).await;
+    assert_eq!(
+        response.body["badges"],
+        json!({ "drafts": 1, "pendingEmailChanges": 0 })
+    );
+}
+
+#[sqlx::test]
+async fn badges_require_authentication(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app.get("/api/user/badges", None).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn preferences_round_trip(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+
+    let initial = app.get("/api/user/preferences", Some(&alice)).await;
+    assert_eq!(initial.status, StatusCode::OK);
+    assert_eq!(initial.body["preferences"], json!({}));
+
+    let settings = json!({ "theme": "dark", "density": "compact", "pageSize": 10 });
+    let saved = app
+        .put(
+            "/api/user/preferences",
+            Some(&alice),
+            json!({ "preferences": settings }),
+        )
+        .await;
+    assert_eq!(saved.status, StatusCode::OK);
+    assert_eq!(saved.body["preferences"], settings);
+
+    let loaded = app.get("/api/user/preferences", Some(&alice)).await;
+    assert_eq!(loaded.body["preferences"], settings);
+}
+
+#[sqlx::test]
+async fn oversized_preferences_are_rejected(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let alice = app.register("alice").await;
+
+    let response = app
+        .put(
+            "/api/user/preferences",
+            Some(&alice),
+            json!({ "preferences": { "notes": "x".repeat(20 * 1024) } }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let not_an_object = app
+        .put(
+            "/api/user/preferences",
+            Some(&alice),
+            json!({ "preferences": [1, 2, 3] }),
+        )
+        .await;
+    assert_eq!(not_an_object.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let loaded = app.get("/api/user/preferences", Some(&alice)).await;
+    assert_eq!(loaded.body["preferences"], json!({}));
+}
+
+async fn register_with_email(app: &TestApp, username: &str, email: &str) -> TestResponse {
+    app.post(
+        "/api/users",
+        None,
+        json!({ "user": {
+            "username": username,
+            "email": email,
+            "password": "password123",
+        }}),
+    )
+    .await
+}
+
+#[sqlx::test]
+async fn duplicate_email_is_rejected_whatever_its_case(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let first = register_with_email(&app, "alice", "alice@example.com").await;
+    assert_eq!(first.status, StatusCode::OK);
+
+    for email in [
+        "alice@example.com",
+        "Alice@Example.COM",
+        "  alice@example.com ",
+    ] {
+        let response = register_with_email(&app, "other", email).await;
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY, "{email}");
+        assert_eq!(
+            response.body["error"]["email"][0]["message"],
+            "has already been taken"
+        );
+    }
+}
+
+#[sqlx::test]
+async fn mixed_case_email_is_stored_normalized_and_signs_in(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let response = register_with_email(&app, "alice", " Alice@Example.com ").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["user"]["email"], "alice@example.com");
+
+    for email in [
+        "alice@example.com",
+        "ALICE@EXAMPLE.COM",
+        " Alice@example.com",
+    ] {
+        assert_eq!(login(&app, email).await.status, StatusCode::OK, "{email}");
+    }
+}
+
+#[sqlx::test]
+async fn activity_lists_mixed_actions_newest_first(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let me = app.register("me").await;
+    let other = app.register("other").await;
+    let theirs = app.create_article(&other, "Their post", &[]).await;
+
+    let mine = app.create_article(&me, "My post", &[]).await;
+    app.follow(&me, "other").await;
+    app.favorite(&me, &theirs).await;
+    let comment_id = app.comment(&me, &theirs, "Great read").await;
+    // Someone else's activity stays out of my feed.
+    app.comment(&other, &mine, "Thanks for reading").await;
+
+    let response = app.get("/api/users/me/activity", Some(&me)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(response.body["activityCount"], 4);
+
+    let activity = response.body["activity"].as_array().unwrap();
+    let types: Vec<&str> = activity
+        .iter()
+        .map(|item| item["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, ["comment", "favorite", "follow", "article"]);
+    assert_eq!(activity[0]["comment"]["id"], comment_id);
+    assert_eq!(activity[0]["article"]["slug"], theirs);
+    assert_eq!(activity[1]["article"]["slug"], theirs);
+    assert_eq!(activity[2]["profile"]["username"], "other");
+    assert_eq!(activity[3]["article"]["slug"], mine);
+
+    let created: Vec<DateTime<Utc>> = activity
+        .iter()
+        .map(|item| item["createdAt"].as_str().unwrap().parse().unwrap())
+        .collect();
+    assert!(
+        created.windows(2).all(|pair| pair[0] >= pair[1]),
+        "{created:?}"
+    );
+
+    let second_page = app
+        .get("/api/users/me/activity?limit=2&offset=2", Some(&me))
+        .await;
+    assert_eq!(second_page.body["activity"], json!(activity[2..]));
+}
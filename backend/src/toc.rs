@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct TocEntry {
+    /// 1 for `#`, up to 6 for `######`.
+    pub level: u8,
+    pub text: String,
+    /// Fragment id for the heading, unique within the article.
+    pub anchor: String,
+}
+
+/// Lowercases `text` and joins its words with dashes, dropping other punctuation,
+/// e.g. "What's New in 2.0?" becomes "whats-new-in-20".
+/// The frontend's `render_markdown` repeats these rules for heading ids; change both together.
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Hands out unique anchors for the headings of one document, in order. Repeats get `-1`,
+/// `-2`, ... appended, the same way GitHub numbers them.
+#[derive(Default)]
+pub struct Anchors {
+    used: HashSet<String>,
+}
+
+impl Anchors {
+    pub fn next(&mut self, heading_text: &str) -> String {
+        let base = slugify(heading_text);
+        let mut anchor = base.clone();
+        let mut n = 0;
+        while !self.used.insert(anchor.clone()) {
+            n += 1;
+            anchor = format!("{base}-{n}");
+        }
+        anchor
+    }
+}
+
+/// The headings of `markdown` in document order, with the anchors `render_markdown` gives them.
+pub fn table_of_contents(markdown: &str) -> Vec<TocEntry> {
+    let mut entries = vec![];
+    let mut anchors = Anchors::default();
+    let mut heading: Option<(u8, String)> = None;
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((level as u8, String::new()))
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((level, text)) = heading.take() else {
+                    continue;
+                };
+
+                entries.push(TocEntry {
+                    level,
+                    anchor: anchors.next(&text),
+                    text: text.trim().to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
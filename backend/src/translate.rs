@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, Query, State},
+    headers::Authorization,
+    response::IntoResponse,
+    Json, TypedHeader,
+};
+use jsonwebtoken::DecodingKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    auth::{self, JWTToken},
+    error::{validation_error, AppError, AppResult},
+};
+
+/// The translatable parts of an article.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArticleText {
+    pub title: String,
+    pub description: String,
+    pub body: String,
+}
+
+/// Turns article text into another language. Implementations are picked with the
+/// `translator` secret; see `EchoTranslator` for the default.
+#[async_trait]
+pub trait Translator: Send + Sync {
+    /// `lang` is a language tag such as `de` or `pt-BR`, already checked by `parse_lang`.
+    async fn translate(&self, text: &ArticleText, lang: &str) -> anyhow::Result<ArticleText>;
+}
+
+/// Hands the text back unchanged. Stands in until a real provider is configured.
+pub struct EchoTranslator;
+
+#[async_trait]
+impl Translator for EchoTranslator {
+    async fn translate(&self, text: &ArticleText, _lang: &str) -> anyhow::Result<ArticleText> {
+        Ok(text.clone())
+    }
+}
+
+/// Builds the translator named by the `translator` secret, defaulting to `echo`.
+pub fn from_name(name: Option<&str>) -> anyhow::Result<Arc<dyn Translator>> {
+    match name {
+        None | Some("echo") => Ok(Arc::new(EchoTranslator)),
+        Some(other) => Err(anyhow::anyhow!("unknown translator: {other}")),
+    }
+}
+
+/// Accepts BCP 47-style tags: letters, digits and dashes, e.g. `de` or `zh-Hant`.
+fn parse_lang(lang: &str) -> AppResult<String> {
+    let valid = (2..=16).contains(&lang.len())
+        && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !lang.starts_with('-');
+
+    if !valid {
+        Err(validation_error(
+            "lang",
+            "must be a language tag like de or pt-BR",
+        ))?
+    }
+
+    Ok(lang.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranslateQuery {
+    #[serde(default)]
+    lang: String,
+}
+
+/// Translates the article into `lang`. Results are cached per article and language until the
+/// article is edited again. Requires authentication since real providers bill per request.
+pub async fn translate_article(
+    State(pool): State<PgPool>,
+    State(key): State<DecodingKey>,
+    State(translator): State<Arc<dyn Translator>>,
+    Path(slug): Path<String>,
+    Query(query): Query<TranslateQuery>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+) -> AppResult<impl IntoResponse> {
+    let user_id = auth::verify_jwt(&token.0, &key)?.user_id;
+
+    let lang = parse_lang(&query.lang)?;
+
+    let article = sqlx::query!(
+        "
+        SELECT id, title, description, body, updated_at
+        FROM articles
+        WHERE slug = $1 AND (published OR author_id = $2)
+        ",
+        slug,
+        user_id,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError(json!({ "article": ["not found"] })))?;
+
+    let cached = sqlx::query_as!(
+        ArticleText,
+        "
+        SELECT title, description, body
+        FROM article_translations
+        WHERE article_id = $1 AND lang = $2 AND translated_at >= $3
+        ",
+        article.id,
+        lang,
+        article.updated_at,
+    )
+    .fetch_optional(&pool)
+    .await?;
+
+    let (translation, is_cached) = match cached {
+        Some(translation) => (translation, true),
+        None => {
+            let text = ArticleText {
+                title: article.title,
+                description: article.description,
+                body: article.body,
+            };
+            let translation = translator.translate(&text, &lang).await?;
+
+            sqlx::query!(
+                "
+                INSERT INTO article_translations (article_id, lang, title, description, body)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (article_id, lang) DO UPDATE
+                    SET (title, description, body, translated_at) =
+                        (EXCLUDED.title, EXCLUDED.description, EXCLUDED.body, NOW())
+                ",
+                article.id,
+                lang,
+                translation.title,
+                translation.description,
+                translation.body,
+            )
+            .execute(&pool)
+            .await?;
+
+            (translation, false)
+        }
+    };
+
+    Ok(Json(json!({
+        "translation": {
+            "lang": lang,
+            "title": translation.title,
+            "description": translation.description,
+            "body": translation.body,
+            "cached": is_cached,
+        }
+    })))
+}
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use axum::{
+    extract::{multipart::MultipartError, Multipart, State},
+    headers::Authorization,
+    response::IntoResponse,
+    Json, TypedHeader,
+};
+use jsonwebtoken::DecodingKey;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::{self, JWTToken},
+    error::{validation_error, AppResult},
+};
+
+/// Uploaded images must be smaller than this.
+const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Body limit for uploads: the image plus room for the multipart headers around it, so the
+/// size check below gets to answer before the limit cuts the request off.
+pub const MAX_UPLOAD_BYTES: usize = MAX_IMAGE_BYTES + 16 * 1024;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = &[0xFF, 0xD8, 0xFF];
+
+/// The extension to store an upload under, if it is a PNG or JPEG. The declared type has to
+/// match the file's own signature, so other files sent as `image/png` don't get through.
+fn image_extension(content_type: Option<&str>, data: &[u8]) -> Option<&'static str> {
+    match content_type? {
+        "image/png" if data.starts_with(PNG_SIGNATURE) => Some("png"),
+        "image/jpeg" if data.starts_with(JPEG_SIGNATURE) => Some("jpg"),
+        _ => None,
+    }
+}
+
+/// Stores the multipart field `image` in the images folder under a fresh name and answers with
+/// the URL it is served from, e.g. for use as a profile picture.
+pub async fn upload_image(
+    State(key): State<DecodingKey>,
+    State(images_folder): State<PathBuf>,
+    TypedHeader(Authorization(token)): TypedHeader<Authorization<JWTToken>>,
+    mut multipart: Multipart,
+) -> AppResult<impl IntoResponse> {
+    auth::verify_jwt(&token.0, &key)?;
+
+    let unreadable = |_: MultipartError| validation_error("image", "could not be read");
+
+    let mut field = loop {
+        match multipart.next_field().await.map_err(unreadable)? {
+            Some(field) if field.name() == Some("image") => break field,
+            Some(_) => continue,
+            None => Err(validation_error("image", "is missing"))?,
+        }
+    };
+
+    let content_type = field.content_type().map(str::to_string);
+
+    // Read piecewise so an oversized file is turned away without buffering all of it.
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(unreadable)? {
+        if data.len() + chunk.len() >= MAX_IMAGE_BYTES {
+            Err(validation_error("image", "must be smaller than 2 MB"))?
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let extension = image_extension(content_type.as_deref(), &data)
+        .ok_or_else(|| validation_error("image", "must be a PNG or JPEG image"))?;
+
+    let name = format!("{}.{extension}", Uuid::new_v4());
+    tokio::fs::write(images_folder.join(&name), &data)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+
+    Ok(Json(json!({ "url": format!("/images/{name}") })))
+}
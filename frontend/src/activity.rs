@@ -0,0 +1,163 @@
+use std::rc::Rc;
+
+use chrono::{DateTime, Local};
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+use yew_router::prelude::*;
+
+use crate::{
+    api::{Activity, ActivityKind, ActivityResp, ApiError, ApiRequest},
+    auth::AuthContext,
+    route::Route,
+    time::relative_time,
+};
+
+/// Comments are quoted in the timeline up to this many characters.
+const COMMENT_EXCERPT_CHARS: usize = 140;
+
+fn excerpt(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(COMMENT_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}\u{2026}", body[..end].trim_end()),
+        None => body.to_string(),
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct ActivityTimelineProps {
+    pub limit: usize,
+}
+
+/// The signed-in user's own articles, comments, favorites and follows, newest first.
+#[function_component]
+pub fn ActivityTimeline(props: &ActivityTimelineProps) -> Html {
+    let ActivityTimelineProps { limit } = props;
+
+    let auth = use_context::<AuthContext>().unwrap();
+    let cur_page = use_state_eq(|| 0);
+
+    let activity = {
+        let auth = auth.clone();
+        let limit = *limit;
+        let cur_page = cur_page.clone();
+        use_async(async move {
+            let resp: ActivityResp = ApiRequest::get("/api/users/me/activity")
+                .query([("limit", limit.to_string())])
+                .query([("offset", (*cur_page * limit).to_string())])
+                .auth(auth.user())
+                .json_response()
+                .await?;
+            Ok::<_, Rc<ApiError>>(Rc::new(resp))
+        })
+    };
+
+    use_effect_with((auth.clone(), *cur_page), {
+        let activity = activity.clone();
+        move |_| activity.run()
+    });
+
+    if let Some(err) = &activity.error {
+        return html! {
+            <div class="article-preview">{format!("Couldn't load your activity: {err}")}</div>
+        };
+    }
+
+    let Some(resp) = activity.data.as_deref() else {
+        return html! { <div class="article-preview">{"Loading activity..."}</div> };
+    };
+
+    if resp.activity.is_empty() {
+        return html! {
+            <div class="article-preview">
+                {"No activity yet. Articles you publish, comments, favorites and follows will show up here."}
+            </div>
+        };
+    }
+
+    let pages = (resp.activity_count + limit - 1) / limit;
+
+    html! {
+        <>
+        <ul class="activity-timeline">
+            { for resp.activity.iter().map(|entry| html! { <ActivityItem entry={entry.clone()} /> }) }
+        </ul>
+        if pages >= 2 {
+            <nav>
+                <ul class="pagination">
+                {
+                    for (0..pages).map(|page| {
+                        html!{
+                            <li class={classes!("page-item", if page == *cur_page {Some("active")} else {None})}>
+                                <a class="page-link ng-binding" href="javascript:void(0);"
+                                    onclick={ let cur_page = cur_page.clone(); move |_| cur_page.set(page) }>
+                                    {page + 1}
+                                </a>
+                            </li>
+                        }
+                    })
+                }
+                </ul>
+            </nav>
+        }
+        </>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+struct ActivityItemProps {
+    entry: Activity,
+}
+
+#[function_component]
+fn ActivityItem(props: &ActivityItemProps) -> Html {
+    let ActivityItemProps { entry } = props;
+
+    let article_link = || match &entry.article {
+        Some(article) => html! {
+            <Link<Route> to={Route::Article { slug: article.slug.clone() }}>{&article.title}</Link<Route>>
+        },
+        None => html! { <span class="activity-removed">{"a deleted article"}</span> },
+    };
+
+    let (icon, what) = match entry.kind {
+        ActivityKind::Article => ("ion-compose", html! { <>{"Published "}{article_link()}</> }),
+        ActivityKind::Comment => (
+            "ion-chatbubble",
+            html! {
+                <>
+                    {"Commented on "}{article_link()}
+                    if let Some(comment) = &entry.comment {
+                        <blockquote class="activity-excerpt">{excerpt(&comment.body)}</blockquote>
+                    }
+                </>
+            },
+        ),
+        ActivityKind::Favorite => ("ion-heart", html! { <>{"Favorited "}{article_link()}</> }),
+        ActivityKind::Follow => (
+            "ion-person-add",
+            match &entry.profile {
+                Some(profile) => html! {
+                    <>
+                        {"Followed "}
+                        <Link<Route> to={Route::Profile { username: profile.username.clone() }}>
+                            {&profile.username}
+                        </Link<Route>>
+                    </>
+                },
+                None => html! { {"Followed someone"} },
+            },
+        ),
+    };
+
+    html! {
+        <li class="activity-item">
+            <i class={classes!("activity-icon", icon)}></i>
+            <div class="activity-body">
+                <div>{what}</div>
+                <span class="date-posted" title={DateTime::<Local>::from(entry.created_at).to_rfc2822()}>
+                    {relative_time(entry.created_at)}
+                </span>
+            </div>
+        </li>
+    }
+}
@@ -0,0 +1,85 @@
+use serde_json::json;
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+use yew_router::prelude::*;
+
+use crate::{api::ApiRequest, auth::AuthContext, preferences::use_preferences, route::Route};
+
+/// Navigations that follow each other faster than this only report the last page.
+const PAGE_VIEW_DEBOUNCE_MILLIS: u32 = 1000;
+
+/// Reports a page view to `POST /api/events` whenever `route` changes.
+/// Sending happens in the background and any failure is only logged, so navigation never waits on it.
+#[hook]
+pub fn use_page_view(route: &Route) {
+    let auth = use_context::<AuthContext>().unwrap();
+    let do_not_track = use_preferences().current.do_not_track;
+
+    // The debounced callback reads whatever was stored here last, not what it was created with.
+    let latest = use_mut_ref(|| (route.to_path(), None));
+    *latest.borrow_mut() = (route.to_path(), auth.user().cloned());
+
+    let send = use_debounce(
+        {
+            let latest = latest.clone();
+            move || {
+                let (path, user) = latest.borrow().clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = ApiRequest::post("/api/events")
+                        .auth(user.as_ref())
+                        .background()
+                        .json(&json!({
+                            "event": {
+                                "kind": "page_view",
+                                "route": path,
+                            }
+                        }))
+                        .json_response::<serde_json::Value>()
+                        .await;
+
+                    if let Err(err) = result {
+                        log::warn!("failed to record page view: {err:?}");
+                    }
+                });
+            }
+        },
+        PAGE_VIEW_DEBOUNCE_MILLIS,
+    );
+
+    use_effect_with(route.clone(), move |_| {
+        if !do_not_track {
+            send.run();
+        }
+    });
+}
+
+#[derive(PartialEq, Properties)]
+pub struct PageViewProps {
+    pub route: Route,
+}
+
+/// Renders nothing; exists so `switch` (a plain function) can use `use_page_view`.
+#[function_component]
+pub fn PageView(props: &PageViewProps) -> Html {
+    use_page_view(&props.route);
+    Html::default()
+}
+
+#[function_component]
+pub fn DoNotTrackToggle() -> Html {
+    let preferences = use_preferences();
+    let enabled = preferences.current.do_not_track;
+
+    let onclick = Callback::from(move |e: MouseEvent| {
+        e.prevent_default();
+        let mut next = preferences.current.clone();
+        next.do_not_track = !enabled;
+        preferences.set.emit(next);
+    });
+
+    html! {
+        <a href="" class="attribution" {onclick}>
+            { if enabled { " Page view tracking is off." } else { " Turn off page view tracking." } }
+        </a>
+    }
+}
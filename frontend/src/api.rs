@@ -1,12 +1,28 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
 
 use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::json;
 use validator::ValidationError;
+use yew::Callback;
+
+use crate::loading;
 
 const DEFAULT_USER_IMAGE: &str = "/images/smiley-cyrus.jpeg";
 
+/// Client-side input limits. The server still validates on its own.
+pub const COMMENT_MAX_LENGTH: usize = 1000;
+pub const BIO_MAX_LENGTH: usize = 500;
+/// The server's default `max_tags`; a deployment configured lower still gets the final say.
+pub const MAX_TAGS: usize = 10;
+pub const TAG_MAX_LENGTH: usize = 32;
+/// The server's cap on how many claps one reader can give an article.
+pub const MAX_CLAPS_PER_USER: u32 = 50;
+
 #[derive(Deserialize)]
 struct JsonError<T> {
     error: T,
@@ -25,7 +41,10 @@ pub enum ApiError {
     #[error("{0}")]
     AppError(serde_json::Value),
     #[error("{0}")]
-    SerdeError(serde_json::Error)
+    SerdeError(serde_json::Error),
+    /// The server answered 401: the request needed a session and had none, or an expired one.
+    #[error("your session expired, please sign in again")]
+    Unauthorized,
 }
 
 impl ApiError {
@@ -44,13 +63,26 @@ impl ApiError {
             ApiError::AppError(json) => {
                 log::error!("{json:?}");
 
-                json.as_object()
-                    .unwrap()
-                    .iter()
-                    .map(|(key, value)| format!("{key} {}", value.as_str().unwrap()))
-                    .collect()
+                match json.as_object() {
+                    Some(errors) => errors
+                        .iter()
+                        .flat_map(|(key, value)| match value {
+                            serde_json::Value::String(message) => vec![format!("{key} {message}")],
+                            serde_json::Value::Array(messages) => messages
+                                .iter()
+                                .map(|message| match message.as_str() {
+                                    Some(message) => format!("{key} {message}"),
+                                    None => format!("{key} {message}"),
+                                })
+                                .collect(),
+                            value => vec![format!("{key} {value}")],
+                        })
+                        .collect(),
+                    None => vec![json.to_string()],
+                }
             }
-            ApiError::SerdeError(_) => todo!(),
+            ApiError::SerdeError(err) => vec![format!("unexpected response: {err}")],
+            ApiError::Unauthorized => vec![self.to_string()],
         }
     }
 }
@@ -88,11 +120,21 @@ pub struct Article {
     pub title: String,
     pub description: String,
     pub body: String,
+    #[serde(default)]
     pub tag_list: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub favorited: bool,
     pub favorites_count: u32,
+    #[serde(default)]
+    pub comments_count: u32,
+    #[serde(default)]
+    pub claps_total: u32,
+    #[serde(default)]
+    pub claps_from_me: u32,
+    /// Sent back when saving edits, so the server can reject them if the article changed since.
+    #[serde(default)]
+    pub version: Option<i32>,
     pub author: UserProfile,
 }
 
@@ -101,13 +143,67 @@ pub struct ArticleResp {
     pub article: Article,
 }
 
+/// An article's text in another language, from `POST /api/articles/:slug/translate`.
+#[derive(PartialEq, Debug, Clone, Deserialize)]
+pub struct Translation {
+    pub lang: String,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct TranslationResp {
+    pub translation: Translation,
+}
+
+/// A heading of an article, from `GET /api/articles/:slug/toc`.
+#[derive(PartialEq, Debug, Clone, Deserialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    /// The `id` the heading gets in the rendered body.
+    pub anchor: String,
+}
+
+#[derive(Deserialize)]
+pub struct TocResp {
+    pub toc: Vec<TocEntry>,
+}
+
+/// An article as returned by the listing endpoints with `?format=summary`, i.e. without `body`.
+#[allow(dead_code)]
+#[derive(PartialEq, Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleSummary {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    #[serde(default)]
+    pub tag_list: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub favorited: bool,
+    pub favorites_count: u32,
+    #[serde(default)]
+    pub comments_count: u32,
+    pub author: UserProfile,
+}
+
 #[allow(dead_code)]
 #[derive(PartialEq, Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UserProfile {
     pub username: String,
     pub bio: Option<String>,
     pub image: Option<String>,
+    #[serde(default)]
     pub following: bool,
+    /// Only sent by `GET /api/profiles/:username`, not with article authors.
+    #[serde(default)]
+    pub articles_count: Option<u32>,
+    #[serde(default)]
+    pub followers_count: Option<u32>,
 }
 
 impl UserProfile {
@@ -121,13 +217,66 @@ pub struct UserProfileResp {
     pub profile: UserProfile,
 }
 
+#[derive(Deserialize)]
+pub struct ProfilesResp {
+    pub profiles: Vec<UserProfile>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MultipleArticle {
-    pub articles: Vec<Article>,
+    pub articles: Vec<ArticleSummary>,
     pub articles_count: usize,
 }
 
+/// One entry of `GET /api/users/me/activity`; `kind` says which of the other parts are set.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Activity {
+    #[serde(rename = "type")]
+    pub kind: ActivityKind,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub article: Option<ActivityArticle>,
+    #[serde(default)]
+    pub comment: Option<ActivityComment>,
+    #[serde(default)]
+    pub profile: Option<ActivityProfile>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Article,
+    Comment,
+    Favorite,
+    Follow,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct ActivityArticle {
+    pub slug: String,
+    pub title: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct ActivityComment {
+    pub id: i32,
+    pub body: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct ActivityProfile {
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityResp {
+    pub activity: Vec<Activity>,
+    pub activity_count: usize,
+}
+
 #[derive(Clone, PartialEq, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Comment {
@@ -136,11 +285,30 @@ pub struct Comment {
     pub updated_at: DateTime<Utc>,
     pub body: String,
     pub author: UserProfile,
+    #[serde(default)]
+    pub reactions: BTreeMap<String, u32>,
+    #[serde(default)]
+    pub my_reactions: Vec<String>,
+    /// Set while the article's author has this comment pinned above the others.
+    #[serde(default)]
+    pub pinned_at: Option<DateTime<Utc>>,
+    /// The comment this one replies to.
+    #[serde(default)]
+    pub parent_id: Option<i32>,
 }
 
+/// A comment with its replies, oldest reply first.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct CommentNode {
+    pub comment: Comment,
+    #[serde(default)]
+    pub replies: Vec<CommentNode>,
+}
+
+/// Response of `GET /api/articles/:slug/comments?tree=true`.
 #[derive(Deserialize)]
-pub struct CommentsResp {
-    pub comments: Vec<Comment>,
+pub struct CommentTreeResp {
+    pub comments: Vec<CommentNode>,
 }
 
 #[derive(Deserialize)]
@@ -148,33 +316,90 @@ pub struct CommentResp {
     pub comment: Comment,
 }
 
+/// A draft comment rendered by `POST /api/articles/:slug/comments/preview`.
+#[derive(Deserialize)]
+pub struct CommentPreview {
+    pub html: String,
+}
+
+#[derive(Deserialize)]
+pub struct CommentPreviewResp {
+    pub preview: CommentPreview,
+}
+
+/// Followed users who favorited each article, keyed by slug.
+#[derive(Deserialize)]
+pub struct LikedByResp {
+    pub profiles: HashMap<String, Vec<UserProfile>>,
+}
+
+/// Counts shown next to header links.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badges {
+    #[serde(default)]
+    pub pending_email_changes: u32,
+    #[serde(default)]
+    pub drafts: u32,
+}
+
+#[derive(Deserialize)]
+pub struct BadgesResp {
+    pub badges: Badges,
+}
+
 #[derive(Deserialize)]
 pub struct TagsResp {
     pub tags: Vec<String>,
 }
 
+thread_local! {
+    static UNAUTHORIZED_HANDLER: RefCell<Option<Callback<()>>> = RefCell::new(None);
+}
+
+/// Sets what happens when a request that carried a token is answered with 401,
+/// i.e. the stored session is no longer valid.
+pub fn set_unauthorized_handler(handler: Option<Callback<()>>) {
+    UNAUTHORIZED_HANDLER.with(|h| *h.borrow_mut() = handler);
+}
+
 // TODO: Replace this with reqwest
-pub struct ApiRequest(reqwest::RequestBuilder);
+pub struct ApiRequest {
+    req: reqwest::RequestBuilder,
+    // Only requests made on behalf of a signed-in user may end the session on 401;
+    // anonymous ones hitting auth-only endpoints must not.
+    with_token: bool,
+    /// Left out of the loading indicator, see [`ApiRequest::background`].
+    background: bool,
+}
 
 impl ApiRequest {
+    fn new(req: reqwest::RequestBuilder) -> Self {
+        Self {
+            req,
+            with_token: false,
+            background: false,
+        }
+    }
+
     pub fn get(url: impl AsRef<str>) -> Self {
         let client = reqwest::Client::new();
-        Self(client.get(url.as_ref()))
+        Self::new(client.get(url.as_ref()))
     }
 
     pub fn post(url: impl AsRef<str>) -> Self {
         let client = reqwest::Client::new();
-        Self(client.post(url.as_ref()))
+        Self::new(client.post(url.as_ref()))
     }
 
     pub fn put(url: impl AsRef<str>) -> Self {
         let client = reqwest::Client::new();
-        Self(client.put(url.as_ref()))
+        Self::new(client.put(url.as_ref()))
     }
 
     pub fn delete(url: impl AsRef<str>) -> Self {
         let client = reqwest::Client::new();
-        Self(client.delete(url.as_ref()))
+        Self::new(client.delete(url.as_ref()))
     }
 
     pub fn query<'a, T, V>(self, params: T) -> Self
@@ -182,38 +407,71 @@ impl ApiRequest {
         T: IntoIterator<Item = (&'a str, V)> + Serialize,
         V: AsRef<str>,
     {
-        Self(self.0.query(&params))
+        Self {
+            req: self.req.query(&params),
+            ..self
+        }
     }
 
     pub fn auth(self, auth: Option<&UserAuth>) -> Self {
         if let Some(auth) = auth {
-            Self(
-                self.0
+            Self {
+                req: self
+                    .req
                     .header("Authorization", &format!("Token {}", auth.token)),
-            )
+                with_token: true,
+                ..self
+            }
         } else {
             self
         }
     }
 
+    /// For requests the user didn't ask for, like polling, which shouldn't show as loading.
+    pub fn background(self) -> Self {
+        Self {
+            background: true,
+            ..self
+        }
+    }
+
     pub fn json(self, json: &impl Serialize) -> Self {
-        Self(self.0.json(json))
+        Self {
+            req: self.req.json(json),
+            ..self
+        }
     }
 
     pub async fn json_response<T: DeserializeOwned>(self) -> Result<T, ApiError> {
-        // log::info!("Request: {:?}", self.0);
+        // log::info!("Request: {:?}", self.req);
+        let _loading = (!self.background).then(loading::track);
 
-        let resp = self.0.send().await.map_err(|err| {
+        let resp = self.req.send().await.map_err(|err| {
             log::error!("Network error: {err:?}");
             ApiError::NetworkError(err)
         })?;
 
         let status = resp.status();
+        if status == 401 {
+            if self.with_token {
+                if let Some(handler) = UNAUTHORIZED_HANDLER.with(|h| h.borrow().clone()) {
+                    handler.emit(());
+                }
+            }
+            return Err(ApiError::Unauthorized);
+        }
+
         if status == 422 {
-            let json: JsonError<ValidationErrors> = resp.json().await.unwrap();
+            let json: JsonError<ValidationErrors> = resp.json().await?;
             return Err(ApiError::ValidationError(json.error));
         }
 
+        // Someone else saved first, e.g. a stale article edit.
+        if status == 409 {
+            let json: JsonError<serde_json::Value> = resp.json().await?;
+            return Err(ApiError::AppError(json.error));
+        }
+
         let data = resp.json::<T>().await.map_err(|err| {
             log::error!("Response json error: {err:?}");
             ApiError::NetworkError(err)
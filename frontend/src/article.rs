@@ -1,15 +1,31 @@
-use std::rc::Rc;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::{Rc, Weak},
+};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Utc};
+use gloo_timers::callback::Timeout;
 use serde_json::json;
-use web_sys::{Element, HtmlTextAreaElement};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{Element, HtmlSelectElement, HtmlTextAreaElement};
 use yew::prelude::*;
 use yew_hooks::prelude::*;
 use yew_router::prelude::*;
 
 use crate::{
-    api::{ApiError, ApiRequest, ArticleResp, Comment, CommentResp, CommentsResp, UserProfileResp},
+    api::{
+        ApiError, ApiRequest, ArticleResp, Comment, CommentNode, CommentPreviewResp, CommentResp,
+        CommentTreeResp, TocEntry, TocResp, Translation, TranslationResp, UserAuth, UserProfile,
+        UserProfileResp, COMMENT_MAX_LENGTH, MAX_CLAPS_PER_USER,
+    },
+    counter::{enforce_max_length, CharCounter},
+    export::ExportMarkdownButton,
+    markdown::{render_markdown, RenderedHtml},
+    reading_list::SaveForLaterButton,
     route::Route,
+    time::relative_time,
+    toc::{self, TableOfContents},
 };
 
 #[derive(PartialEq, Properties)]
@@ -44,7 +60,102 @@ pub fn Article(props: &ArticleProps) -> Html {
         UseAsyncOptions::enable_auto(),
     );
 
-    let comments = use_state_ptr_eq(|| vec![]);
+    let toc = use_state_eq(|| Rc::new(Vec::<TocEntry>::new()));
+
+    use_effect_with(slug.clone(), {
+        let toc = toc.clone();
+        // Drafts are only found for their author, so the TOC request needs the token too.
+        let user = auth.user().cloned();
+        move |slug| {
+            toc.set(Rc::default());
+            let url = format!("/api/articles/{slug}/toc");
+            spawn_local(async move {
+                match ApiRequest::get(url)
+                    .auth(user.as_ref())
+                    .json_response::<TocResp>()
+                    .await
+                {
+                    Ok(resp) => toc.set(Rc::new(resp.toc)),
+                    Err(err) => log::warn!("failed to load table of contents: {err:?}"),
+                }
+            });
+        }
+    });
+
+    // `None` shows the original. Fetched translations are kept, so switching back and forth
+    // between languages doesn't ask the server again.
+    let lang = use_state_eq(|| None::<String>);
+    let translations = use_map(HashMap::<String, Translation>::new());
+    let translating = use_state_eq(|| false);
+    let translation_notice = use_state_eq(|| None::<&'static str>);
+
+    use_effect_with(slug.clone(), {
+        let lang = lang.clone();
+        let translations = translations.clone();
+        let translation_notice = translation_notice.clone();
+        move |_| {
+            lang.set(None);
+            translations.set(HashMap::new());
+            translation_notice.set(None);
+        }
+    });
+
+    let on_lang = {
+        let lang = lang.clone();
+        let translations = translations.clone();
+        let translating = translating.clone();
+        let translation_notice = translation_notice.clone();
+        let slug = slug.clone();
+        let auth = auth.clone();
+        Callback::from(move |selected: Option<String>| {
+            translation_notice.set(None);
+
+            let Some(selected) = selected else {
+                lang.set(None);
+                return;
+            };
+            if translations.current().contains_key(&selected) {
+                lang.set(Some(selected));
+                return;
+            }
+
+            translating.set(true);
+            let lang = lang.clone();
+            let translations = translations.clone();
+            let translating = translating.clone();
+            let translation_notice = translation_notice.clone();
+            let user = auth.user().cloned();
+            let url = format!("/api/articles/{slug}/translate?lang={selected}");
+            spawn_local(async move {
+                match ApiRequest::post(url)
+                    .auth(user.as_ref())
+                    .json_response::<TranslationResp>()
+                    .await
+                {
+                    Ok(resp) => {
+                        translations.insert(selected.clone(), resp.translation);
+                        lang.set(Some(selected));
+                    }
+                    Err(err) => {
+                        log::warn!("failed to translate article: {err:?}");
+                        lang.set(None);
+                        translation_notice.set(Some(
+                            "Translation isn't available right now, showing the original.",
+                        ));
+                    }
+                }
+                translating.set(false);
+            });
+        })
+    };
+
+    let translation = (*lang)
+        .as_ref()
+        .and_then(|lang| translations.current().get(lang).cloned());
+
+    // A list handle rather than plain state, so in-flight posts can insert into and reconcile
+    // against the current comments instead of a render-time snapshot.
+    let comments = use_list(Vec::<CommentNode>::new());
 
     let reload_comments = use_async_with_options(
         {
@@ -52,10 +163,11 @@ pub fn Article(props: &ArticleProps) -> Html {
             let auth = auth.clone();
             let comments = comments.clone();
             async move {
-                let c: CommentsResp = ApiRequest::get(format!("/api/articles/{slug}/comments"))
-                    .auth(auth.user())
-                    .json_response()
-                    .await?;
+                let c: CommentTreeResp =
+                    ApiRequest::get(format!("/api/articles/{slug}/comments?tree=true"))
+                        .auth(auth.user())
+                        .json_response()
+                        .await?;
 
                 comments.set(c.comments);
 
@@ -66,36 +178,199 @@ pub fn Article(props: &ArticleProps) -> Html {
     );
 
     let comment_ref = use_node_ref();
+    let comment_len = use_state_eq(|| 0);
+    let comment_truncated = use_state_eq(|| false);
+
+    let on_comment_input = {
+        let comment_len = comment_len.clone();
+        let comment_truncated = comment_truncated.clone();
+        Callback::from(move |e: InputEvent| {
+            let el: HtmlTextAreaElement = e.target_unchecked_into();
+            let (len, truncated) = enforce_max_length(&el, COMMENT_MAX_LENGTH);
+            comment_len.set(len);
+            comment_truncated.set(truncated);
+        })
+    };
 
-    let post_comment = use_async({
-        let comment_ref = comment_ref.clone();
+    // Ids for comments shown before the server has assigned a real one.
+    let next_pending_id = use_mut_ref(|| 0);
+
+    // Shows the comment right away under its parent, or on top; once the server answers, the
+    // placeholder is swapped for the real comment in the same spot, so nothing is refetched.
+    let submit_comment = {
+        let comments = comments.clone();
         let reload_comments = reload_comments.clone();
         let slug = slug.clone();
         let auth = auth.clone();
 
-        async move {
-            let comment = comment_ref.cast::<HtmlTextAreaElement>().unwrap().value();
-            let comment = comment.trim_start();
+        Callback::from(move |new: NewComment| {
+            let NewComment {
+                body,
+                parent_id,
+                restore,
+                settled,
+            } = new;
+            let Some(user) = auth.user().cloned() else {
+                return;
+            };
 
-            if comment == "" {
-                return Ok(());
+            let pending_id = {
+                let mut next = next_pending_id.borrow_mut();
+                *next -= 1;
+                *next
+            };
+
+            let now = Utc::now();
+            let mut next = comments.current().clone();
+            insert_comment(
+                &mut next,
+                Comment {
+                    id: pending_id,
+                    created_at: now,
+                    updated_at: now,
+                    body: body.clone(),
+                    author: UserProfile {
+                        username: user.username.clone(),
+                        bio: user.bio.clone(),
+                        image: user.image.clone(),
+                        following: false,
+                        articles_count: None,
+                        followers_count: None,
+                    },
+                    reactions: BTreeMap::new(),
+                    my_reactions: vec![],
+                    pinned_at: None,
+                    parent_id,
+                },
+            );
+            comments.set(next);
+
+            let comments = comments.clone();
+            let reload_comments = reload_comments.clone();
+            let slug = slug.clone();
+            spawn_local(async move {
+                let resp = ApiRequest::post(format!("/api/articles/{slug}/comments"))
+                    .auth(Some(&user))
+                    .json(&json!({
+                        "comment": {
+                            "body": body,
+                            "parentId": parent_id,
+                        }
+                    }))
+                    .json_response::<CommentResp>()
+                    .await;
+                settled.emit(());
+
+                let mut next = comments.current().clone();
+                match resp {
+                    Ok(resp) => replace_comment(&mut next, pending_id, resp.comment),
+                    Err(err) => {
+                        log::error!("failed to post comment: {err:?}");
+                        remove_comment(&mut next, pending_id);
+                        restore.emit(body);
+                        reload_comments.run();
+                    }
+                }
+                comments.set(next);
+            });
+        })
+    };
+
+    // `Some` while the comment form shows the rendered comment instead of the textarea.
+    let comment_preview = use_state_eq(|| None::<String>);
+
+    let on_comment_write = {
+        let comment_preview = comment_preview.clone();
+        Callback::from(move |_| comment_preview.set(None))
+    };
+
+    let on_comment_preview = {
+        let comment_ref = comment_ref.clone();
+        let comment_preview = comment_preview.clone();
+        let slug = slug.clone();
+        let auth = auth.clone();
+
+        Callback::from(move |_| {
+            let Some(el) = comment_ref.cast::<HtmlTextAreaElement>() else {
+                return;
+            };
+
+            let body = el.value();
+            if body.trim().is_empty() {
+                comment_preview.set(Some("<p>Nothing to preview.</p>".to_string()));
+                return;
             }
 
-            let _resp: CommentResp = ApiRequest::post(format!("/api/articles/{slug}/comments"))
-                .auth(auth.user())
-                .json(&json!({
-                    "comment": {
-                        "body": comment
+            let comment_preview = comment_preview.clone();
+            let user = auth.user().cloned();
+            let url = format!("/api/articles/{slug}/comments/preview");
+            spawn_local(async move {
+                match ApiRequest::post(url)
+                    .auth(user.as_ref())
+                    .json(&json!({ "comment": { "body": &body } }))
+                    .json_response::<CommentPreviewResp>()
+                    .await
+                {
+                    Ok(resp) => comment_preview.set(Some(resp.preview.html)),
+                    // The local renderer follows the same rules, so it makes a fine stand-in.
+                    Err(err) => {
+                        log::warn!("failed to preview comment: {err:?}");
+                        comment_preview.set(Some(render_markdown(&body)));
                     }
-                }))
-                .json_response()
-                .await?;
+                }
+            });
+        })
+    };
 
-            reload_comments.run();
+    // The top-level form stays open after posting, so it waits for the answer before the next one.
+    let posting_comment = use_state_eq(|| false);
 
-            Ok::<_, Rc<ApiError>>(())
-        }
-    });
+    let post_comment = {
+        let posting_comment = posting_comment.clone();
+        let comment_ref = comment_ref.clone();
+        let comment_len = comment_len.clone();
+        let comment_preview = comment_preview.clone();
+        let submit_comment = submit_comment.clone();
+
+        Callback::from(move |_| {
+            let Some(el) = comment_ref.cast::<HtmlTextAreaElement>() else {
+                return;
+            };
+
+            let body = el.value();
+            if *posting_comment || body.trim().is_empty() {
+                return;
+            }
+
+            posting_comment.set(true);
+            el.set_value("");
+            comment_len.set(0);
+            comment_preview.set(None);
+
+            // Give the text back if nothing new was typed meanwhile.
+            let restore = {
+                let comment_len = comment_len.clone();
+                Callback::from(move |body: String| {
+                    if el.value().is_empty() {
+                        el.set_value(&body);
+                        comment_len.set(body.chars().count());
+                    }
+                })
+            };
+
+            let settled = {
+                let posting_comment = posting_comment.clone();
+                Callback::from(move |_| posting_comment.set(false))
+            };
+
+            submit_comment.emit(NewComment {
+                body,
+                parent_id: None,
+                restore,
+                settled,
+            });
+        })
+    };
 
     let delete_comment_id = use_state_ptr_eq(|| None);
 
@@ -130,6 +405,65 @@ pub fn Article(props: &ArticleProps) -> Html {
         })
     };
 
+    // Applied right away; the server's timestamp replaces ours, or the list is refetched on error.
+    let on_pin_comment = {
+        let comments = comments.clone();
+        let reload_comments = reload_comments.clone();
+        let slug = slug.clone();
+        let auth = auth.clone();
+        Callback::from(move |(id, pin): (i32, bool)| {
+            let mut next = comments.current().clone();
+            apply_pin(&mut next, id, pin.then(Utc::now));
+            comments.set(next);
+
+            let comments = comments.clone();
+            let reload_comments = reload_comments.clone();
+            let user = auth.user().cloned();
+            let url = format!("/api/articles/{slug}/comments/{id}/pin");
+            spawn_local(async move {
+                let req = if pin {
+                    ApiRequest::post(url)
+                } else {
+                    ApiRequest::delete(url)
+                };
+
+                match req.auth(user.as_ref()).json_response::<CommentResp>().await {
+                    Ok(resp) => {
+                        let mut next = comments.current().clone();
+                        apply_pin(&mut next, id, resp.comment.pinned_at);
+                        comments.set(next);
+                    }
+                    Err(err) => {
+                        log::error!("failed to update pin: {err:?}");
+                        reload_comments.run();
+                    }
+                }
+            });
+        })
+    };
+
+    // An edited comment keeps its place and replies; only the comment itself is swapped.
+    let on_comment_edited = {
+        let comments = comments.clone();
+        Callback::from(move |comment: Comment| {
+            let mut next = comments.current().clone();
+            if let Some(node) = find_comment_mut(&mut next, comment.id) {
+                node.comment = comment;
+            }
+            comments.set(next);
+        })
+    };
+
+    let is_article_author = matches!(
+        (auth.user(), article.as_ref()),
+        (Some(user), Some(article)) if user.username == article.author.username
+    );
+
+    let on_comments_stale = {
+        let reload_comments = reload_comments.clone();
+        Callback::from(move |_| reload_comments.run())
+    };
+
     use_effect_with(auth.clone(), move |_| reload_article.run());
     use_effect_with(auth.clone(), move |_| reload_comments.run());
 
@@ -137,14 +471,25 @@ pub fn Article(props: &ArticleProps) -> Html {
         <div class="article-page">
         <div class="banner">
           <div class="container">
-            <ArticleBanner article={article.clone()} />
+            <ArticleBanner article={article.clone()} translation={translation.clone()} />
           </div>
         </div>
 
         <div class="container page">
           <div class="row article-content">
             <div class="col-md-12">
-                <ArticleContent article={article.clone()} />
+                if auth.is_authorized() {
+                    <LanguageSelector
+                        lang={(*lang).clone()}
+                        translating={*translating}
+                        notice={*translation_notice}
+                        onchange={on_lang} />
+                }
+                // The headings are the original's, so there's nothing to link to in a translation.
+                <ArticleContent
+                    article={article.clone()}
+                    toc={if translation.is_none() { (*toc).clone() } else { Rc::default() }}
+                    translation={translation} />
             </div>
           </div>
 
@@ -159,11 +504,31 @@ pub fn Article(props: &ArticleProps) -> Html {
               if auth.is_authorized() {
                 <form class="card comment-form">
                     <div class="card-block">
-                    <textarea ref={comment_ref} class="form-control" placeholder="Write a comment..." rows="3"></textarea>
+                    <ul class="nav nav-tabs comment-form-tabs">
+                        <li class="nav-item">
+                            <a class={classes!("nav-link", comment_preview.is_none().then_some("active"))}
+                                onclick={on_comment_write} href="javascript:void(0);">{"Write"}</a>
+                        </li>
+                        <li class="nav-item">
+                            <a class={classes!("nav-link", comment_preview.is_some().then_some("active"))}
+                                onclick={on_comment_preview} href="javascript:void(0);">{"Preview"}</a>
+                        </li>
+                    </ul>
+                    // Only hidden while previewing, so the draft stays in the textarea.
+                    <textarea ref={comment_ref} oninput={on_comment_input} hidden={comment_preview.is_some()} class="form-control" placeholder="Write a comment... (markdown supported)" rows="3"></textarea>
+                    if let Some(html) = (*comment_preview).clone() {
+                        <RenderedHtml class="comment-preview" html={html} />
+                    }
+                    <CharCounter len={*comment_len} max={COMMENT_MAX_LENGTH} truncated={*comment_truncated} />
                     </div>
                     <div class="card-footer">
                     <img src={auth.user().map(|u| u.image().to_string())} class="comment-author-img" />
-                    <button onclick={move |_| post_comment.run()} class="btn btn-sm btn-primary">{"Post Comment"}</button>
+                    <button onclick={post_comment} type="button" class="btn btn-sm btn-primary" disabled={*posting_comment}>
+                        if *posting_comment {
+                            <span class="btn-spinner" aria-hidden="true"></span>{" "}
+                        }
+                        {"Post Comment"}
+                    </button>
                     </div>
                 </form>
               } else {
@@ -176,8 +541,18 @@ pub fn Article(props: &ArticleProps) -> Html {
               }
 
               {
-                for comments.iter().map(|comment| html!{
-                    <CommentCard comment={comment.clone()} on_delete={on_delete_comment.clone()} />
+                for comments.current().iter().map(|node| html!{
+                    <CommentThread
+                        key={node.comment.id}
+                        slug={slug.clone()}
+                        node={node.clone()}
+                        depth={0}
+                        can_pin={is_article_author}
+                        on_delete={on_delete_comment.clone()}
+                        on_pin={on_pin_comment.clone()}
+                        on_edited={on_comment_edited.clone()}
+                        on_reply={submit_comment.clone()}
+                        on_stale={on_comments_stale.clone()} />
                 })
               }
             </div>
@@ -187,6 +562,15 @@ pub fn Article(props: &ArticleProps) -> Html {
     }
 }
 
+/// The button's icon, or a spinner in its place while the button's request is running.
+fn button_icon(icon: &'static str, loading: bool) -> Html {
+    if loading {
+        html! { <span class="btn-spinner" aria-hidden="true"></span> }
+    } else {
+        html! { <i class={icon}></i> }
+    }
+}
+
 #[derive(PartialEq, Properties)]
 struct FollowButtonProps {
     article: UseStatePtrEqHandle<Option<crate::api::Article>>,
@@ -208,15 +592,12 @@ fn FollowButton(props: &FollowButtonProps) -> Html {
         let navigator = navigator.clone();
         let auth = auth.clone();
         async move {
-            if auth.is_unauthorized() || article.is_none() {
+            let Some(cur) = article.as_ref().filter(|_| !auth.is_unauthorized()) else {
                 navigator.push(&Route::Register);
                 return Ok::<_, Rc<ApiError>>(());
-            }
+            };
 
-            let url = format!(
-                "/api/profiles/{}/follow",
-                article.as_ref().unwrap().author.username
-            );
+            let url = format!("/api/profiles/{}/follow", cur.author.username);
 
             let req = if *following {
                 ApiRequest::delete(url)
@@ -225,7 +606,7 @@ fn FollowButton(props: &FollowButtonProps) -> Html {
             };
 
             let p: UserProfileResp = req.auth(auth.user()).json_response().await?;
-            let mut a = article.as_ref().unwrap().clone();
+            let mut a = cur.clone();
             a.author = p.profile;
             article.set(Some(a));
 
@@ -237,17 +618,19 @@ fn FollowButton(props: &FollowButtonProps) -> Html {
         return html! {};
     };
 
+    let loading = follow.loading;
+
     if article.author.following {
         html! {
-            <button onclick={move |_| follow.run()} class="btn btn-sm btn-secondary">
-                <i class="ion-plus-round"></i>
+            <button onclick={move |_| follow.run()} disabled={loading} class="btn btn-sm btn-secondary">
+                {button_icon("ion-plus-round", loading)}
                 {format!("  Unfollow {}", article.author.username)}
             </button>
         }
     } else {
         html! {
-            <button onclick={move |_| follow.run()} class="btn btn-sm btn-outline-secondary">
-                <i class="ion-plus-round"></i>
+            <button onclick={move |_| follow.run()} disabled={loading} class="btn btn-sm btn-outline-secondary">
+                {button_icon("ion-plus-round", loading)}
                 {format!("  Follow {} ", article.author.username)}
             </button>
         }
@@ -274,12 +657,12 @@ fn FavoriteButton(props: &FavoriteButtonProps) -> Html {
         let article = article.clone();
         let auth = auth.clone();
         async move {
-            if auth.is_unauthorized() || article.is_none() {
+            let Some(cur) = article.as_ref().filter(|_| !auth.is_unauthorized()) else {
                 navigator.push(&Route::Register);
                 return Ok::<_, Rc<ApiError>>(());
-            }
+            };
 
-            let url = format!("/api/articles/{}/favorite", article.as_ref().unwrap().slug);
+            let url = format!("/api/articles/{}/favorite", cur.slug);
 
             let req = if *favorited {
                 ApiRequest::delete(url)
@@ -298,18 +681,20 @@ fn FavoriteButton(props: &FavoriteButtonProps) -> Html {
         return html! {};
     };
 
+    let loading = favorite.loading;
+
     if article.favorited {
         html! {
-            <button onclick={move |_| favorite.run() } class="btn btn-sm btn-primary">
-                <i class="ion-heart"></i>
+            <button onclick={move |_| favorite.run() } disabled={loading} class="btn btn-sm btn-primary">
+                {button_icon("ion-heart", loading)}
                 {format!("  Unfavorite Post ")}
                 <span class="counter">{format!("({})", article.favorites_count)}</span>
             </button>
         }
     } else {
         html! {
-            <button onclick={move |_| favorite.run() } class="btn btn-sm btn-outline-primary">
-                <i class="ion-heart"></i>
+            <button onclick={move |_| favorite.run() } disabled={loading} class="btn btn-sm btn-outline-primary">
+                {button_icon("ion-heart", loading)}
                 {format!("  Favorite Post ")}
                 <span class="counter">{format!("({})", article.favorites_count)}</span>
             </button>
@@ -317,6 +702,157 @@ fn FavoriteButton(props: &FavoriteButtonProps) -> Html {
     }
 }
 
+/// Taps closer together than this are sent as a single request.
+const CLAP_BATCH_MILLIS: u32 = 800;
+
+/// Claps tapped but not yet confirmed by the server.
+#[derive(Default)]
+struct ClapBatch {
+    /// Waiting for the taps to pause.
+    unsent: u32,
+    /// Sent, response pending.
+    in_flight: u32,
+    slug: String,
+    user: Option<UserAuth>,
+    /// Sends `unsent` when it fires; replaced on every tap.
+    timer: Option<Timeout>,
+}
+
+impl ClapBatch {
+    fn pending(&self) -> u32 {
+        self.unsent + self.in_flight
+    }
+}
+
+/// Sends the unsent claps, then replaces the article with the server's copy so the totals
+/// match what was actually stored (e.g. clamped at the cap, or others clapping meanwhile).
+fn send_claps(
+    batch: &Rc<RefCell<ClapBatch>>,
+    article: &UseStatePtrEqHandle<Option<crate::api::Article>>,
+    update: &UseForceUpdateHandle,
+) {
+    let (count, slug, user) = {
+        let mut batch = batch.borrow_mut();
+        let count = std::mem::take(&mut batch.unsent);
+        batch.in_flight += count;
+        (count, batch.slug.clone(), batch.user.clone())
+    };
+    if count == 0 {
+        return;
+    }
+
+    let batch = batch.clone();
+    let article = article.clone();
+    let update = update.clone();
+    spawn_local(async move {
+        let result = ApiRequest::post(format!("/api/articles/{slug}/clap"))
+            .auth(user.as_ref())
+            .json(&json!({ "clap": { "count": count } }))
+            .json_response::<ArticleResp>()
+            .await;
+
+        batch.borrow_mut().in_flight -= count;
+        match result {
+            Ok(resp) => article.set(Some(resp.article)),
+            // The optimistic claps simply disappear again.
+            Err(err) => log::error!("failed to clap: {err:?}"),
+        }
+        update.force_update();
+    });
+}
+
+#[derive(PartialEq, Properties)]
+struct ClapButtonProps {
+    article: UseStatePtrEqHandle<Option<crate::api::Article>>,
+}
+
+/// Each tap adds a clap right away; the server hears about them once the taps pause for
+/// [`CLAP_BATCH_MILLIS`], as one request carrying the count.
+#[function_component]
+fn ClapButton(props: &ClapButtonProps) -> Html {
+    let ClapButtonProps { article } = props;
+
+    let auth = use_context::<crate::auth::AuthContext>().unwrap();
+    let navigator = use_navigator().unwrap();
+
+    let batch = use_mut_ref(ClapBatch::default);
+    let update = use_force_update();
+    // Keys the "+N" bubble so its animation restarts on every tap.
+    let taps = use_state_eq(|| 0u32);
+
+    // Claps still waiting for the pause are sent when leaving the article rather than dropped.
+    use_effect_with((), {
+        let batch = batch.clone();
+        let article = article.clone();
+        let update = update.clone();
+        move |_| move || send_claps(&batch, &article, &update)
+    });
+
+    let Some(cur) = article.as_ref() else {
+        return html! {};
+    };
+
+    let pending = batch.borrow().pending();
+    let mine = cur.claps_from_me + pending;
+    let total = cur.claps_total + pending;
+    let at_cap = mine >= MAX_CLAPS_PER_USER;
+
+    let onclick = {
+        let article = article.clone();
+        let batch = batch.clone();
+        let update = update.clone();
+        let taps = taps.clone();
+        let auth = auth.clone();
+        Callback::from(move |_| {
+            let Some(user) = auth.user() else {
+                navigator.push(&Route::Login);
+                return;
+            };
+            let Some(cur) = article.as_ref() else {
+                return;
+            };
+
+            let mut state = batch.borrow_mut();
+            if cur.claps_from_me + state.pending() >= MAX_CLAPS_PER_USER {
+                return;
+            }
+            state.unsent += 1;
+            state.slug = cur.slug.clone();
+            state.user = Some(user.clone());
+
+            // Weak, so that a timer still waiting when the page goes away doesn't keep it alive.
+            let weak: Weak<RefCell<ClapBatch>> = Rc::downgrade(&batch);
+            let article = article.clone();
+            let update = update.clone();
+            state.timer = Some(Timeout::new(CLAP_BATCH_MILLIS, move || {
+                if let Some(batch) = weak.upgrade() {
+                    send_claps(&batch, &article, &update);
+                }
+            }));
+
+            taps.set(*taps + 1);
+        })
+    };
+
+    let title = if auth.user().is_none() {
+        "Sign in to clap"
+    } else if at_cap {
+        "You've given all your claps"
+    } else {
+        "Clap"
+    };
+
+    html! {
+        <button {onclick} {title} disabled={at_cap && auth.user().is_some()}
+            class={classes!("btn", "btn-sm", "clap-button", if mine > 0 { "btn-primary" } else { "btn-outline-primary" })}>
+            {"\u{1f44f} "}{total}
+            if pending > 0 {
+                <span key={*taps} class="clap-burst">{format!("+{mine}")}</span>
+            }
+        </button>
+    }
+}
+
 #[derive(PartialEq, Properties)]
 struct EditButtonProps {
     article: UseStatePtrEqHandle<Option<crate::api::Article>>,
@@ -326,10 +862,12 @@ struct EditButtonProps {
 fn EditButton(props: &EditButtonProps) -> Html {
     let EditButtonProps { article } = props;
 
-    let slug = article.as_ref().map(|a| a.slug.clone());
+    let Some(slug) = article.as_ref().map(|a| a.slug.clone()) else {
+        return html! {};
+    };
 
     html! {
-        <Link<Route> to={Route::Editor{ slug: slug.unwrap() }} classes="btn btn-outline-secondary btn-sm" >
+        <Link<Route> to={Route::Editor{ slug }} classes="btn btn-outline-secondary btn-sm" >
             <i class="ion-edit"></i>{" Edit Article "}
         </Link<Route>>
     }
@@ -367,9 +905,11 @@ fn DeleteButton(props: &DeleteButtonProps) -> Html {
         }
     });
 
+    let loading = delete.loading;
+
     html! {
-        <button onclick={move |_| delete.run() } class="btn btn-outline-danger btn-sm">
-            <i class="ion-trash-a"></i>{" Delete Article "}
+        <button onclick={move |_| delete.run() } disabled={loading} class="btn btn-outline-danger btn-sm">
+            {button_icon("ion-trash-a", loading)}{" Delete Article "}
         </button>
     }
 }
@@ -392,6 +932,8 @@ pub fn ArticleMeta(props: &ArticleMetaProps) -> Html {
     };
 
     let date = DateTime::<Local>::from(article.created_at).format("%B %e, %Y").to_string();
+    // Saving right after publishing (e.g. fixing a typo) doesn't count as an edit.
+    let edited = article.updated_at - article.created_at > chrono::Duration::minutes(1);
 
     let my_article = article_state
         .as_ref()
@@ -408,7 +950,14 @@ pub fn ArticleMeta(props: &ArticleMetaProps) -> Html {
                 <Link<Route> to={Route::Profile {username: article.author.username.clone()}} classes="author">
                     {&article.author.username}
                 </Link<Route>>
-                <span class="date">{date}</span>
+                <span class="date">
+                    {date}
+                    if edited {
+                        <span title={DateTime::<Local>::from(article.updated_at).to_rfc2822()}>
+                            {format!(" (edited {})", relative_time(article.updated_at))}
+                        </span>
+                    }
+                </span>
             </div>
 
             if !my_article {
@@ -419,8 +968,10 @@ pub fn ArticleMeta(props: &ArticleMetaProps) -> Html {
             { "  " }
             if !my_article {
                 <FavoriteButton article={article_state.clone()} />
+                { "  " }
+                <ClapButton article={article_state.clone()} />
             } else {
-                <DeleteButton slug={article_state.as_ref().unwrap().slug.clone()} />
+                <DeleteButton slug={article.slug.clone()} />
             }
         </div>
     }
@@ -429,12 +980,16 @@ pub fn ArticleMeta(props: &ArticleMetaProps) -> Html {
 #[derive(PartialEq, Properties)]
 pub struct ArticleBannerProps {
     article: UseStatePtrEqHandle<Option<crate::api::Article>>,
+    /// Shown instead of the original title when set.
+    #[prop_or_default]
+    translation: Option<Translation>,
 }
 
 #[function_component]
 pub fn ArticleBanner(props: &ArticleBannerProps) -> Html {
     let ArticleBannerProps {
         article: article_state,
+        translation,
     } = props;
 
     let Some(article) = article_state.as_ref() else {
@@ -443,8 +998,11 @@ pub fn ArticleBanner(props: &ArticleBannerProps) -> Html {
 
     html! {
         <>
-        <h1>{&article.title}</h1>
+        <h1>{translation.as_ref().map_or(&article.title, |t| &t.title)}</h1>
         <ArticleMeta article={article_state.clone()}/>
+        <SaveForLaterButton slug={article.slug.clone()} />
+        {" "}
+        <ExportMarkdownButton article={article.clone()} />
         </>
     }
 }
@@ -452,27 +1010,59 @@ pub fn ArticleBanner(props: &ArticleBannerProps) -> Html {
 #[derive(PartialEq, Properties)]
 pub struct ArticleContentProps {
     article: UseStatePtrEqHandle<Option<crate::api::Article>>,
+    /// Rendered instead of the original body when set.
+    #[prop_or_default]
+    translation: Option<Translation>,
+    /// Headings of the original body, shown as a sidebar when there are several.
+    #[prop_or_default]
+    toc: Rc<Vec<TocEntry>>,
 }
 
 #[function_component]
 pub fn ArticleContent(props: &ArticleContentProps) -> Html {
-    let ArticleContentProps { article } = props;
+    let ArticleContentProps {
+        article,
+        translation,
+        toc,
+    } = props;
 
     let content_ref = use_node_ref();
+    let active_heading = use_state_eq(|| None::<String>);
+
+    let body = match translation {
+        Some(translation) => Some(translation.body.clone()),
+        None => article.as_ref().map(|a| a.body.clone()),
+    };
 
-    if let Some(article) = article.as_ref() {
-        use pulldown_cmark::{html, Parser};
-        let parser = Parser::new(&article.body);
-        let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
+    // Rendered after the `div` is mounted, so the ref is always attached by then.
+    use_effect_with((body, toc.clone()), {
+        let content_ref = content_ref.clone();
+        let active_heading = active_heading.clone();
+        move |(body, toc)| {
+            let mut scroll_spy = None;
+            if let (Some(body), Some(el)) = (body, content_ref.cast::<Element>()) {
+                el.set_inner_html(&render_markdown(body));
+
+                if toc::worth_showing(toc) {
+                    let on_active = Callback::from(move |anchor| active_heading.set(Some(anchor)));
+                    scroll_spy = toc::watch(toc, on_active);
+                }
+            }
 
-        let el = content_ref.cast::<Element>().unwrap();
-        el.set_inner_html(&html_output);
-    }
+            move || drop(scroll_spy)
+        }
+    });
+
+    let show_toc = toc::worth_showing(toc);
 
     html! {
         <>
-        <div ref={content_ref}></div>
+        <div class={classes!(show_toc.then_some("article-with-toc"))}>
+            if show_toc {
+                <TableOfContents entries={toc.clone()} active={(*active_heading).clone()} />
+            }
+            <div class="article-body" ref={content_ref}></div>
+        </div>
         if let Some(article) = article.as_ref() {
             <ul class="tag-list">
             {
@@ -490,28 +1080,473 @@ pub fn ArticleContent(props: &ArticleContentProps) -> Html {
     }
 }
 
+/// Languages offered for translation, as (tag, name).
+const TRANSLATION_LANGUAGES: [(&str, &str); 6] = [
+    ("de", "Deutsch"),
+    ("es", "Español"),
+    ("fr", "Français"),
+    ("ja", "日本語"),
+    ("pt-BR", "Português (Brasil)"),
+    ("zh-Hans", "简体中文"),
+];
+
+#[derive(PartialEq, Properties)]
+struct LanguageSelectorProps {
+    lang: Option<String>,
+    translating: bool,
+    notice: Option<&'static str>,
+    /// `None` asks for the original.
+    onchange: Callback<Option<String>>,
+}
+
+#[function_component]
+fn LanguageSelector(props: &LanguageSelectorProps) -> Html {
+    let LanguageSelectorProps {
+        lang,
+        translating,
+        notice,
+        onchange,
+    } = props;
+
+    let on_select = {
+        let onchange = onchange.clone();
+        Callback::from(move |e: Event| {
+            let el: HtmlSelectElement = e.target_unchecked_into();
+            let value = el.value();
+            onchange.emit((!value.is_empty()).then_some(value));
+        })
+    };
+
+    let on_show_original = {
+        let onchange = onchange.clone();
+        Callback::from(move |_| onchange.emit(None))
+    };
+
+    let selected = lang.as_deref().unwrap_or_default();
+
+    html! {
+        <div class="article-translate">
+            <select class="form-control form-control-sm" onchange={on_select} disabled={*translating}>
+                <option value="" selected={selected.is_empty()}>{"Original language"}</option>
+                {
+                    for TRANSLATION_LANGUAGES.iter().map(|(tag, name)| html! {
+                        <option value={*tag} selected={selected == *tag}>{*name}</option>
+                    })
+                }
+            </select>
+            if *translating {
+                <span class="article-translate-status">{"Translating..."}</span>
+            } else if lang.is_some() {
+                <button type="button" class="btn btn-sm btn-outline-secondary" onclick={on_show_original}>
+                    {"Show original"}
+                </button>
+            }
+            if let Some(notice) = notice {
+                <span class="article-translate-status text-muted">{*notice}</span>
+            }
+        </div>
+    }
+}
+
+/// Pins (`Some`) or unpins (`None`) top-level comment `id`, keeping pinned comments first and
+/// at most one pinned.
+fn apply_pin(comments: &mut [CommentNode], id: i32, pinned_at: Option<DateTime<Utc>>) {
+    for node in comments.iter_mut() {
+        if node.comment.id == id {
+            node.comment.pinned_at = pinned_at;
+        } else if pinned_at.is_some() {
+            node.comment.pinned_at = None;
+        }
+    }
+
+    comments.sort_by_key(|node| {
+        (
+            node.comment.pinned_at.is_none(),
+            std::cmp::Reverse(node.comment.created_at),
+        )
+    });
+}
+
+/// A comment form's text on its way to `submit_comment`.
+struct NewComment {
+    body: String,
+    parent_id: Option<i32>,
+    /// Gets the text back if posting fails.
+    restore: Callback<String>,
+    /// Told once the server has answered, either way.
+    settled: Callback<()>,
+}
+
+fn find_comment_mut(nodes: &mut [CommentNode], id: i32) -> Option<&mut CommentNode> {
+    for node in nodes {
+        if node.comment.id == id {
+            return Some(node);
+        }
+        if let Some(found) = find_comment_mut(&mut node.replies, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Replies go last under their parent, matching the server's oldest-first order; everything
+/// else goes on top. A reply whose parent is gone meanwhile is dropped.
+fn insert_comment(nodes: &mut Vec<CommentNode>, comment: Comment) {
+    let node = CommentNode {
+        comment,
+        replies: vec![],
+    };
+
+    match node.comment.parent_id {
+        Some(parent_id) => {
+            if let Some(parent) = find_comment_mut(nodes, parent_id) {
+                parent.replies.push(node);
+            }
+        }
+        None => nodes.insert(0, node),
+    }
+}
+
+fn replace_comment(nodes: &mut [CommentNode], id: i32, comment: Comment) {
+    if let Some(node) = find_comment_mut(nodes, id) {
+        node.comment = comment;
+    }
+}
+
+fn remove_comment(nodes: &mut Vec<CommentNode>, id: i32) {
+    nodes.retain(|node| node.comment.id != id);
+    for node in nodes {
+        remove_comment(&mut node.replies, id);
+    }
+}
+
+/// Deeper replies line up with their parent instead of shifting further right.
+const MAX_INDENT_DEPTH: usize = 3;
+
+/// Threads with more direct replies than this start out collapsed.
+const COLLAPSE_REPLIES_OVER: usize = 3;
+
+#[derive(PartialEq, Properties)]
+struct CommentThreadProps {
+    slug: String,
+    node: CommentNode,
+    depth: usize,
+    /// Whether the viewer wrote the article; only top-level comments can be pinned.
+    can_pin: bool,
+    on_delete: Callback<i32>,
+    on_pin: Callback<(i32, bool)>,
+    on_edited: Callback<Comment>,
+    on_reply: Callback<NewComment>,
+    on_stale: Callback<()>,
+}
+
+#[function_component]
+fn CommentThread(props: &CommentThreadProps) -> Html {
+    let CommentThreadProps {
+        slug,
+        node,
+        depth,
+        can_pin,
+        on_delete,
+        on_pin,
+        on_edited,
+        on_reply,
+        on_stale,
+    } = props;
+
+    let auth = use_context::<crate::auth::AuthContext>().unwrap();
+
+    let expanded = use_state_eq(|| node.replies.len() <= COLLAPSE_REPLIES_OVER);
+    // `Some` while the reply form is open, holding the text it starts with.
+    let reply_draft = use_state_eq(|| None::<String>);
+
+    let comment_id = node.comment.id;
+    let can_reply = auth.is_authorized() && comment_id > 0;
+
+    let on_reply_click = {
+        let reply_draft = reply_draft.clone();
+        Callback::from(move |_| reply_draft.set(Some(String::new())))
+    };
+
+    let on_reply_submit = {
+        let reply_draft = reply_draft.clone();
+        let expanded = expanded.clone();
+        let on_reply = on_reply.clone();
+        Callback::from(move |body: String| {
+            reply_draft.set(None);
+            expanded.set(true);
+
+            let restore = {
+                let reply_draft = reply_draft.clone();
+                Callback::from(move |body: String| reply_draft.set(Some(body)))
+            };
+            // The reply form closes on submit, so there's nothing to re-enable.
+            on_reply.emit(NewComment {
+                body,
+                parent_id: Some(comment_id),
+                restore,
+                settled: Callback::noop(),
+            });
+        })
+    };
+
+    let on_reply_cancel = {
+        let reply_draft = reply_draft.clone();
+        Callback::from(move |_| reply_draft.set(None))
+    };
+
+    let on_toggle = {
+        let expanded = expanded.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            expanded.set(!*expanded);
+        })
+    };
+
+    let reply_count = node.replies.len();
+    let toggle_label = match (*expanded, reply_count) {
+        (true, _) => "Hide replies".to_string(),
+        (false, 1) => "Show 1 reply".to_string(),
+        (false, n) => format!("Show {n} replies"),
+    };
+
+    html! {
+        <>
+            <CommentCard
+                slug={slug.clone()}
+                comment={node.comment.clone()}
+                can_pin={*can_pin && *depth == 0 && comment_id > 0}
+                {can_reply}
+                on_delete={on_delete.clone()}
+                on_pin={on_pin.clone()}
+                on_edited={on_edited.clone()}
+                on_reply={on_reply_click}
+                on_stale={on_stale.clone()} />
+            if let Some(initial) = (*reply_draft).clone() {
+                <ReplyForm {initial} on_submit={on_reply_submit} on_cancel={on_reply_cancel} />
+            }
+            if reply_count > 0 {
+                <div class={classes!("comment-replies", (*depth < MAX_INDENT_DEPTH).then_some("comment-replies-indented"))}>
+                    <a href="" class="comment-replies-toggle" onclick={on_toggle}>{toggle_label}</a>
+                    if *expanded {
+                        {
+                            for node.replies.iter().map(|reply| html! {
+                                <CommentThread
+                                    key={reply.comment.id}
+                                    slug={slug.clone()}
+                                    node={reply.clone()}
+                                    depth={depth + 1}
+                                    can_pin={*can_pin}
+                                    on_delete={on_delete.clone()}
+                                    on_pin={on_pin.clone()}
+                                    on_edited={on_edited.clone()}
+                                    on_reply={on_reply.clone()}
+                                    on_stale={on_stale.clone()} />
+                            })
+                        }
+                    }
+                </div>
+            }
+        </>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+struct ReplyFormProps {
+    initial: String,
+    #[prop_or(AttrValue::Static("Post Reply"))]
+    submit_label: AttrValue,
+    on_submit: Callback<String>,
+    on_cancel: Callback<()>,
+}
+
+#[function_component]
+fn ReplyForm(props: &ReplyFormProps) -> Html {
+    let ReplyFormProps {
+        initial,
+        submit_label,
+        on_submit,
+        on_cancel,
+    } = props;
+
+    let auth = use_context::<crate::auth::AuthContext>().unwrap();
+
+    let reply_ref = use_node_ref();
+    let reply_len = use_state_eq(|| initial.chars().count());
+    let reply_truncated = use_state_eq(|| false);
+
+    let oninput = {
+        let reply_len = reply_len.clone();
+        let reply_truncated = reply_truncated.clone();
+        Callback::from(move |e: InputEvent| {
+            let el: HtmlTextAreaElement = e.target_unchecked_into();
+            let (len, truncated) = enforce_max_length(&el, COMMENT_MAX_LENGTH);
+            reply_len.set(len);
+            reply_truncated.set(truncated);
+        })
+    };
+
+    let on_submit = {
+        let reply_ref = reply_ref.clone();
+        let on_submit = on_submit.clone();
+        Callback::from(move |_| {
+            let Some(el) = reply_ref.cast::<HtmlTextAreaElement>() else {
+                return;
+            };
+            let body = el.value();
+            if !body.trim().is_empty() {
+                on_submit.emit(body);
+            }
+        })
+    };
+
+    let on_cancel = {
+        let on_cancel = on_cancel.clone();
+        Callback::from(move |_| on_cancel.emit(()))
+    };
+
+    html! {
+        <form class="card comment-form comment-reply-form">
+            <div class="card-block">
+            <textarea ref={reply_ref} {oninput} value={initial.clone()} class="form-control" placeholder="Write a reply..." rows="2"></textarea>
+            <CharCounter len={*reply_len} max={COMMENT_MAX_LENGTH} truncated={*reply_truncated} />
+            </div>
+            <div class="card-footer">
+            <img src={auth.user().map(|u| u.image().to_string())} class="comment-author-img" />
+            <button onclick={on_submit} type="button" class="btn btn-sm btn-primary">{submit_label}</button>
+            <button onclick={on_cancel} type="button" class="btn btn-sm btn-outline-secondary">{"Cancel"}</button>
+            </div>
+        </form>
+    }
+}
+
 #[derive(PartialEq, Properties)]
 pub struct CommentCardProps {
+    slug: String,
     comment: Comment,
+    /// Whether the viewer wrote the article and may pin this comment.
+    #[prop_or_default]
+    can_pin: bool,
+    #[prop_or_default]
+    can_reply: bool,
     on_delete: Callback<i32>,
+    on_pin: Callback<(i32, bool)>,
+    /// Gets the comment as saved after its author edited it.
+    on_edited: Callback<Comment>,
+    #[prop_or_default]
+    on_reply: Callback<()>,
+    /// Fired when the comment turns out to be out of date, e.g. it was deleted meanwhile.
+    on_stale: Callback<()>,
 }
 
 #[function_component]
 pub fn CommentCard(props: &CommentCardProps) -> Html {
-    let CommentCardProps { comment, on_delete } = props;
+    let CommentCardProps {
+        slug,
+        comment,
+        can_pin,
+        can_reply,
+        on_delete,
+        on_pin,
+        on_edited,
+        on_reply,
+        on_stale,
+    } = props;
 
     let auth = use_context::<crate::auth::AuthContext>().unwrap();
 
+    let editing = use_state_eq(|| false);
+    let edit_errors = use_state_eq(Vec::<String>::new);
+
     let date = DateTime::<Local>::from(comment.created_at).format("%B %e, %Y").to_string();
 
+    let body_html = use_memo(comment.body.clone(), |body| {
+        AttrValue::from(render_markdown(body))
+    });
+
     let on_delete = on_delete.clone();
     let comment_id = comment.id;
     let onclick = Callback::from(move |_| on_delete.emit(comment_id));
 
+    let pinned = comment.pinned_at.is_some();
+    let on_pin_click = {
+        let on_pin = on_pin.clone();
+        Callback::from(move |_| on_pin.emit((comment_id, !pinned)))
+    };
+
+    let on_edit_click = {
+        let editing = editing.clone();
+        let edit_errors = edit_errors.clone();
+        Callback::from(move |_| {
+            edit_errors.set(vec![]);
+            editing.set(true);
+        })
+    };
+
+    // The form stays open with the text until the server has taken it.
+    let on_edit_submit = {
+        let editing = editing.clone();
+        let edit_errors = edit_errors.clone();
+        let on_edited = on_edited.clone();
+        let user = auth.user().cloned();
+        let url = format!("/api/articles/{slug}/comments/{comment_id}");
+        Callback::from(move |body: String| {
+            let editing = editing.clone();
+            let edit_errors = edit_errors.clone();
+            let on_edited = on_edited.clone();
+            let user = user.clone();
+            let url = url.clone();
+            spawn_local(async move {
+                let result = ApiRequest::put(url)
+                    .auth(user.as_ref())
+                    .json(&json!({ "comment": { "body": body } }))
+                    .json_response::<CommentResp>()
+                    .await;
+
+                match result {
+                    Ok(resp) => {
+                        editing.set(false);
+                        on_edited.emit(resp.comment);
+                    }
+                    Err(err) => {
+                        log::error!("failed to edit comment: {err:?}");
+                        edit_errors.set(err.to_vec_string());
+                    }
+                }
+            });
+        })
+    };
+
+    let on_edit_cancel = {
+        let editing = editing.clone();
+        Callback::from(move |_| editing.set(false))
+    };
+
+    if *editing {
+        return html! {
+            <>
+                <ul class="error-messages">
+                    { for edit_errors.iter().map(|error| html! { <li>{error}</li> }) }
+                </ul>
+                <ReplyForm initial={comment.body.clone()} submit_label="Save"
+                    on_submit={on_edit_submit} on_cancel={on_edit_cancel} />
+            </>
+        };
+    }
+
+    // Same grace period as articles: fixing a typo right away doesn't count as an edit.
+    let edited = comment.updated_at - comment.created_at > chrono::Duration::minutes(1);
+
     html! {
-        <div class="card">
+        <div class={classes!("card", pinned.then_some("comment-pinned"))}>
             <div class="card-block">
-                <p class="card-text">{&comment.body}</p>
+                if pinned {
+                    <span class="tag-default tag-pill comment-pinned-badge">
+                        <i class="ion-pin"></i>{" Pinned"}
+                    </span>
+                }
+                <RenderedHtml class="card-text comment-body" html={(*body_html).clone()} />
+                <CommentReactions slug={slug.clone()} comment={comment.clone()} on_stale={on_stale.clone()} />
             </div>
             <div class="card-footer">
                 <Link<Route> to={Route::Profile{ username: comment.author.username.clone() }} classes="comment-author">
@@ -522,14 +1557,176 @@ pub fn CommentCard(props: &CommentCardProps) -> Html {
                     {&comment.author.username}
                 </Link<Route>>
                 <span class="date-posted">{date}</span>
+                if edited {
+                    <span class="date-posted" title={DateTime::<Local>::from(comment.updated_at).to_rfc2822()}>
+                        {" (edited)"}
+                    </span>
+                }
 
-                if matches!(auth.user(), Some(user) if user.username == comment.author.username) {
-                    <span class="mod-options">
-                        // <i class="ion-edit"></i>
+                <span class="mod-options">
+                    if *can_reply {
+                        <i onclick={on_reply.reform(|_: MouseEvent| ())} class="ion-reply" title="Reply"></i>
+                    }
+                    if *can_pin {
+                        <i onclick={on_pin_click} class="ion-pin"
+                            title={if pinned { "Unpin" } else { "Pin to top" }}></i>
+                    }
+                    if matches!(auth.user(), Some(user) if user.username == comment.author.username) {
+                        if comment_id > 0 {
+                            <i onclick={on_edit_click} class="ion-edit" title="Edit"></i>
+                        }
                         <i {onclick} class="ion-trash-a"></i>
+                    }
+                </span>
+            </div>
+        </div>
+    }
+}
+
+const REACTION_EMOJIS: [&str; 5] = ["👍", "❤️", "😄", "🎉", "🤔"];
+
+#[derive(Clone, PartialEq)]
+struct Reactions {
+    counts: BTreeMap<String, u32>,
+    mine: Vec<String>,
+}
+
+impl From<&Comment> for Reactions {
+    fn from(comment: &Comment) -> Self {
+        Self {
+            counts: comment.reactions.clone(),
+            mine: comment.my_reactions.clone(),
+        }
+    }
+}
+
+impl Reactions {
+    /// What the server will hold after the current user toggles `emoji`.
+    fn toggled(&self, emoji: &str) -> Self {
+        let mut next = self.clone();
+
+        if let Some(pos) = next.mine.iter().position(|e| e == emoji) {
+            next.mine.remove(pos);
+            let count = next.counts.entry(emoji.to_string()).or_default();
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                next.counts.remove(emoji);
+            }
+        } else {
+            next.mine.push(emoji.to_string());
+            *next.counts.entry(emoji.to_string()).or_default() += 1;
+        }
+
+        next
+    }
+}
+
+#[derive(PartialEq, Properties)]
+struct CommentReactionsProps {
+    slug: String,
+    comment: Comment,
+    on_stale: Callback<()>,
+}
+
+#[function_component]
+fn CommentReactions(props: &CommentReactionsProps) -> Html {
+    let CommentReactionsProps {
+        slug,
+        comment,
+        on_stale,
+    } = props;
+
+    let auth = use_context::<crate::auth::AuthContext>().unwrap();
+
+    let reactions = use_state_eq(|| Reactions::from(comment));
+    let show_picker = use_state_eq(|| false);
+
+    use_effect_with(comment.clone(), {
+        let reactions = reactions.clone();
+        move |comment| reactions.set(Reactions::from(comment))
+    });
+
+    // Applied optimistically, then replaced by the server's counts or rolled back on error.
+    let on_react = {
+        let reactions = reactions.clone();
+        let show_picker = show_picker.clone();
+        let auth = auth.clone();
+        let url = format!("/api/articles/{slug}/comments/{}/react", comment.id);
+        let on_stale = on_stale.clone();
+        Callback::from(move |emoji: String| {
+            let previous = (*reactions).clone();
+            reactions.set(previous.toggled(&emoji));
+            show_picker.set(false);
+
+            let reactions = reactions.clone();
+            let user = auth.user().cloned();
+            let url = url.clone();
+            let on_stale = on_stale.clone();
+            spawn_local(async move {
+                let resp = ApiRequest::post(url)
+                    .auth(user.as_ref())
+                    .json(&json!({ "reaction": { "emoji": emoji } }))
+                    .json_response::<CommentResp>()
+                    .await;
+
+                match resp {
+                    Ok(resp) => reactions.set(Reactions::from(&resp.comment)),
+                    Err(err) => {
+                        log::error!("reaction failed: {err:?}");
+                        reactions.set(previous);
+                        // The comment (or its article) may have been deleted meanwhile.
+                        on_stale.emit(());
+                    }
+                }
+            });
+        })
+    };
+
+    let authorized = auth.is_authorized();
+
+    html! {
+        <div class="comment-reactions">
+            {
+                for reactions.counts.iter().map(|(emoji, count)| {
+                    let mine = reactions.mine.contains(emoji);
+                    let onclick = {
+                        let on_react = on_react.clone();
+                        let emoji = emoji.clone();
+                        move |_| on_react.emit(emoji.clone())
+                    };
+                    html! {
+                        <button {onclick} disabled={!authorized}
+                            class={classes!("btn", "btn-sm", if mine {"btn-secondary"} else {"btn-outline-secondary"})}>
+                            {format!("{emoji} {count}")}
+                        </button>
+                    }
+                })
+            }
+            {" "}
+            if authorized {
+                <button class="btn btn-sm btn-link" type="button"
+                    onclick={let show_picker = show_picker.clone(); move |_| show_picker.set(!*show_picker)}>
+                    {"+ React"}
+                </button>
+                if *show_picker {
+                    <span class="reaction-picker">
+                    {
+                        for REACTION_EMOJIS.iter().map(|emoji| {
+                            let onclick = {
+                                let on_react = on_react.clone();
+                                let emoji = emoji.to_string();
+                                move |_| on_react.emit(emoji.clone())
+                            };
+                            html! {
+                                <button {onclick} type="button" class="btn btn-sm btn-link">{emoji}</button>
+                            }
+                        })
+                    }
                     </span>
                 }
-            </div>
+            } else {
+                <Link<Route> to={Route::Login}>{"Sign in to react"}</Link<Route>>
+            }
         </div>
     }
 }
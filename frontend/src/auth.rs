@@ -4,8 +4,13 @@ use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, AUTHORIZATION};
 use gloo_storage::{LocalStorage, Storage};
 use yew::prelude::*;
 use yew_hooks::{use_async_with_options, UseAsyncOptions};
+use yew_router::prelude::*;
 
-use crate::api::{ApiError, UserAuth, UserAuthResp};
+use crate::{
+    api::{set_unauthorized_handler, ApiError, UserAuth, UserAuthResp},
+    login::LoginQuery,
+    route::Route,
+};
 
 pub type AuthContext = UseReducerHandle<Auth>;
 
@@ -126,3 +131,29 @@ pub fn AuthProvider(props: &AuthProviderProps) -> Html {
         </ContextProvider<AuthContext>>
     }
 }
+
+/// Signs the user out and sends them to Login, which says the session expired, when an
+/// authenticated request comes back 401. Must be rendered inside both `AuthProvider` and the
+/// router.
+#[function_component]
+pub fn SessionExpiryHandler() -> Html {
+    let auth = use_context::<AuthContext>().unwrap();
+    let navigator = use_navigator().unwrap();
+
+    use_effect_with((), move |_| {
+        // Only requests that carried a token get here, so anonymous 401s never log anyone out.
+        set_unauthorized_handler(Some(Callback::from(move |_| {
+            auth.dispatch(Auth::Unauthorized);
+            if let Err(err) =
+                navigator.push_with_query(&Route::Login, &LoginQuery { expired: true })
+            {
+                log::warn!("failed to open the sign-in page: {err:?}");
+                navigator.push(&Route::Login);
+            }
+        })));
+
+        || set_unauthorized_handler(None)
+    });
+
+    Html::default()
+}
@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+
+use crate::{
+    api::{ApiError, ApiRequest, Badges, BadgesResp},
+    auth::AuthContext,
+};
+
+const BADGE_POLL_MILLIS: u32 = 60_000;
+
+/// Coming back to the tab refreshes right away, but not on every quick switch.
+const VISIBLE_REFRESH_DEBOUNCE_MILLIS: u32 = 1000;
+
+fn document_hidden() -> bool {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .map_or(false, |document| document.hidden())
+}
+
+/// Polls `GET /api/user/badges` while a user is signed in and the tab is visible.
+/// `None` until the first response arrives, and whenever nobody is signed in.
+#[hook]
+pub fn use_badges() -> Option<Badges> {
+    let auth = use_context::<AuthContext>().unwrap();
+
+    let badges = use_state_eq(|| None);
+    let hidden = use_state_eq(document_hidden);
+
+    use_event_with_window("visibilitychange", {
+        let hidden = hidden.clone();
+        move |_: Event| hidden.set(document_hidden())
+    });
+
+    let fetch = {
+        let auth = auth.clone();
+        let badges = badges.clone();
+        use_async(async move {
+            let resp: BadgesResp = ApiRequest::get("/api/user/badges")
+                .auth(auth.user())
+                .background()
+                .json_response()
+                .await?;
+
+            badges.set(Some(resp.badges));
+
+            Ok::<_, Rc<ApiError>>(())
+        })
+    };
+
+    let polling = auth.is_authorized() && !*hidden;
+
+    // An overlapping poll would only fetch the same counts again.
+    let poll = {
+        let fetch = fetch.clone();
+        move || {
+            if !fetch.loading {
+                fetch.run();
+            }
+        }
+    };
+
+    let refresh = use_debounce(poll.clone(), VISIBLE_REFRESH_DEBOUNCE_MILLIS);
+
+    use_interval(poll, if polling { BADGE_POLL_MILLIS } else { 0 });
+
+    use_effect_with((polling, auth.is_authorized()), {
+        let badges = badges.clone();
+        move |(polling, authorized)| {
+            if *polling {
+                refresh.run();
+            } else if !*authorized {
+                badges.set(None);
+            }
+        }
+    });
+
+    (*badges).clone()
+}
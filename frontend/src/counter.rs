@@ -0,0 +1,44 @@
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+/// Cuts the textarea's value down to `max` characters.
+/// Returns the resulting length and whether anything had to be cut (e.g. an oversized paste).
+pub fn enforce_max_length(el: &HtmlTextAreaElement, max: usize) -> (usize, bool) {
+    let value = el.value();
+    let len = value.chars().count();
+
+    if len <= max {
+        return (len, false);
+    }
+
+    el.set_value(&value.chars().take(max).collect::<String>());
+    (max, true)
+}
+
+#[derive(PartialEq, Properties)]
+pub struct CharCounterProps {
+    pub len: usize,
+    pub max: usize,
+    #[prop_or_default]
+    pub truncated: bool,
+}
+
+#[function_component]
+pub fn CharCounter(props: &CharCounterProps) -> Html {
+    let CharCounterProps {
+        len,
+        max,
+        truncated,
+    } = props;
+
+    let full = if len >= max { Some("text-danger") } else { None };
+
+    html! {
+        <small class={classes!("char-counter", "text-muted", full)}>
+            {format!("{len}/{max}")}
+            if *truncated {
+                {" (text beyond the limit was cut off)"}
+            }
+        </small>
+    }
+}
@@ -0,0 +1,23 @@
+use yew::prelude::*;
+
+use crate::{
+    api::ApiRequest,
+    feed::{Feed, FeedSource},
+};
+
+/// The signed-in user's unpublished articles, most recently edited first.
+#[function_component]
+pub fn Drafts() -> Html {
+    let source = FeedSource::new("drafts", || ApiRequest::get("/api/articles/drafts"));
+
+    html! {
+        <div class="container page">
+            <div class="row">
+                <div class="col-md-10 offset-md-1 col-xs-12">
+                    <h1>{"Drafts"}</h1>
+                    <Feed {source} limit=10 empty_message={AttrValue::from("You have no unpublished articles.")} />
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -1,22 +1,93 @@
 use std::rc::Rc;
 
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use yew::prelude::*;
-use yew_hooks::{use_async, use_async_with_options, use_state_ptr_eq, UseAsyncOptions};
+use yew_hooks::{
+    use_async, use_async_with_options, use_debounce, use_event_with_window, UseAsyncOptions,
+};
 use yew_router::prelude::*;
 
 use crate::{
-    api::{ApiError, ApiRequest, ArticleResp},
+    api::{ApiError, ApiRequest, ArticleResp, MAX_TAGS, TAG_MAX_LENGTH},
     auth::AuthContext,
+    markdown::{render_markdown, RenderedHtml},
     route::Route,
 };
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 struct ArticleData {
     title: String,
     description: String,
     body: String,
     tags: String,
+    /// Version of the article the edit is based on. Not stored with drafts: it always comes from
+    /// the article as loaded, so a conflict can be resolved by reloading.
+    #[serde(skip)]
+    version: Option<i32>,
+}
+
+/// Typing pauses this long before the form is written to storage.
+const DRAFT_SAVE_DEBOUNCE_MILLIS: u32 = 1000;
+
+/// New articles and edits of each existing article keep separate drafts.
+fn draft_key(slug: Option<&str>) -> String {
+    format!("editor_draft:{}", slug.unwrap_or("new"))
+}
+
+fn load_draft(slug: Option<&str>) -> Option<ArticleData> {
+    LocalStorage::get(draft_key(slug)).ok()
+}
+
+fn save_draft(slug: Option<&str>, data: &ArticleData) -> bool {
+    match LocalStorage::set(draft_key(slug), data) {
+        Ok(()) => true,
+        Err(err) => {
+            log::error!("failed to store draft: {err:?}");
+            false
+        }
+    }
+}
+
+fn clear_draft(slug: Option<&str>) {
+    LocalStorage::delete(draft_key(slug));
+}
+
+/// Splits the comma-separated tags field, dropping blanks and repeats.
+fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Cuts the tags field down to the first [`MAX_TAGS`] tags (e.g. after pasting a long list).
+/// Returns the tags kept and the ones that had to go.
+fn enforce_tag_limit(el: &web_sys::HtmlInputElement) -> (Vec<String>, Vec<String>) {
+    let mut tags = parse_tags(&el.value());
+    if tags.len() <= MAX_TAGS {
+        return (tags, vec![]);
+    }
+
+    let dropped = tags.split_off(MAX_TAGS);
+    el.set_value(&tags.join(", "));
+    (tags, dropped)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DraftStatus {
+    /// Nothing typed since the form was loaded or published.
+    Clean,
+    Unsaved,
+    Saved,
 }
 
 #[derive(PartialEq, Properties)]
@@ -37,7 +108,11 @@ pub fn Editor(props: &EditorProps) -> Html {
         };
     }
 
-    let article_data = use_state_ptr_eq(|| None::<ArticleData>);
+    // Read when the request starts rather than captured at render time, so a click can
+    // store the form data and run `publish` right away without going through an effect.
+    let article_data = use_mut_ref(|| None::<ArticleData>);
+    // Errors from the last attempt are hidden as soon as the user starts fixing the form.
+    let edited_since_publish = use_state_eq(|| false);
 
     let publish = use_async({
         let article_data = article_data.clone();
@@ -45,11 +120,11 @@ pub fn Editor(props: &EditorProps) -> Html {
         let navigator = navigator.clone();
         let slug = slug.clone();
         async move {
-            let Some(data) = &*article_data else {
+            let Some(data) = article_data.borrow_mut().take() else {
                 return Ok(());
             };
 
-            let req = if let Some(slug) = slug {
+            let req = if let Some(slug) = &slug {
                 ApiRequest::put(format!("/api/articles/{slug}"))
             } else {
                 ApiRequest::post("/api/articles")
@@ -62,12 +137,15 @@ pub fn Editor(props: &EditorProps) -> Html {
                         "title": &data.title,
                         "description": &data.description,
                         "body": &data.body,
-                        "tagList": data.tags.split(",").map(|tag| tag.trim().to_string()).filter(|s| !s.is_empty()).collect::<Vec<_>>(),
+                        "tagList": parse_tags(&data.tags),
+                        "version": data.version,
                     }
                 }))
                 .json_response()
                 .await?;
 
+            clear_draft(slug.as_deref());
+
             navigator.push(&Route::Article {
                 slug: resp.article.slug,
             });
@@ -76,19 +154,35 @@ pub fn Editor(props: &EditorProps) -> Html {
         }
     });
 
-    use_effect_with(
-        article_data.clone(),
-        {
-            let publish = publish.clone();
-            move |_| publish.run()
+    let on_publish = {
+        let publish = publish.clone();
+        let edited_since_publish = edited_since_publish.clone();
+        Callback::from(move |data| {
+            *article_data.borrow_mut() = Some(data);
+            edited_since_publish.set(false);
+            publish.run();
+        })
+    };
+
+    let on_edit = {
+        let edited_since_publish = edited_since_publish.clone();
+        Callback::from(move |_| edited_since_publish.set(true))
+    };
+
+    // A failed publish means the draft only lives in the form, so ask before the tab is closed.
+    let unsaved = publish.error.is_some();
+    use_event_with_window("beforeunload", move |e: Event| {
+        if unsaved {
+            e.prevent_default();
         }
-    );
+    });
 
-    let error_message = if let Some(err) = &publish.error {
-        log::info!("err: {err:?}");
-        err.to_vec_string()
-    } else {
-        vec![]
+    let error_message = match &publish.error {
+        Some(err) if !*edited_since_publish => {
+            log::info!("err: {err:?}");
+            err.to_vec_string()
+        }
+        _ => vec![],
     };
 
     html! {
@@ -104,7 +198,9 @@ pub fn Editor(props: &EditorProps) -> Html {
                         }
                         </ul>
 
-                        <EditorForm slug={slug.clone()} on_publish={move |data| article_data.set(Some(data))}/>
+                        // Keyed so switching between new and existing articles starts a fresh form.
+                        <EditorForm key={draft_key(slug.as_deref())} slug={slug.clone()} {on_publish} {on_edit}
+                            publishing={publish.loading} published={publish.data.is_some()}/>
                     </div>
                 </div>
             </div>
@@ -116,18 +212,41 @@ pub fn Editor(props: &EditorProps) -> Html {
 struct EditorFormProps {
     slug: Option<String>,
     on_publish: Callback<ArticleData>,
+    on_edit: Callback<()>,
+    publishing: bool,
+    published: bool,
 }
 
 #[function_component]
 fn EditorForm(props: &EditorFormProps) -> Html {
-    let EditorFormProps { slug, on_publish } = props;
+    let EditorFormProps {
+        slug,
+        on_publish,
+        on_edit,
+        publishing,
+        published,
+    } = props;
+
+    let auth = use_context::<AuthContext>().unwrap();
+
+    // A stored draft wins over the saved article, so nothing typed is lost on reload.
+    let draft = use_state(|| load_draft(slug.as_deref()));
+    let draft_status = use_state_eq(|| {
+        if draft.is_some() {
+            DraftStatus::Saved
+        } else {
+            DraftStatus::Clean
+        }
+    });
 
     let article = use_async_with_options(
         {
             let slug = slug.clone();
+            let auth = auth.clone();
             async move {
                 let slug = slug.ok_or(ApiError::AppError(json!({})))?;
                 let resp: ArticleResp = ApiRequest::get(&format!("/api/articles/{slug}"))
+                    .auth(auth.user())
                     .json_response()
                     .await?;
                 Ok::<_, Rc<ApiError>>(resp.article)
@@ -136,43 +255,169 @@ fn EditorForm(props: &EditorFormProps) -> Html {
         UseAsyncOptions::enable_auto(),
     );
 
+    let version = use_mut_ref(|| None::<i32>);
+    use_effect_with(article.data.as_ref().and_then(|a| a.version), {
+        let version = version.clone();
+        move |loaded| *version.borrow_mut() = *loaded
+    });
+
     let title_ref = use_node_ref();
     let description_ref = use_node_ref();
     let body_ref = use_node_ref();
     let tags_ref = use_node_ref();
 
-    let onclick = {
+    // `Some` while the body shows rendered, the same way the article page renders it.
+    let body_preview = use_state_eq(|| None::<AttrValue>);
+
+    let on_body_write = {
+        let body_preview = body_preview.clone();
+        Callback::from(move |_| body_preview.set(None))
+    };
+
+    let on_body_preview = {
+        let body_ref = body_ref.clone();
+        let body_preview = body_preview.clone();
+        Callback::from(move |_| {
+            if let Some(el) = body_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                body_preview.set(Some(AttrValue::from(render_markdown(&el.value()))));
+            }
+        })
+    };
+
+    let read_form = {
         let title_ref = title_ref.clone();
         let description_ref = description_ref.clone();
         let body_ref = body_ref.clone();
         let tags_ref = tags_ref.clone();
+        let version = version.clone();
+
+        move || -> Option<ArticleData> {
+            Some(ArticleData {
+                title: title_ref.cast::<web_sys::HtmlInputElement>()?.value(),
+                description: description_ref.cast::<web_sys::HtmlInputElement>()?.value(),
+                body: body_ref.cast::<web_sys::HtmlTextAreaElement>()?.value(),
+                tags: tags_ref.cast::<web_sys::HtmlInputElement>()?.value(),
+                version: *version.borrow(),
+            })
+        }
+    };
+
+    let onclick = {
+        let read_form = read_form.clone();
         let on_publish = on_publish.clone();
 
         Callback::from(move |_| {
-            let title = title_ref
-                .cast::<web_sys::HtmlInputElement>()
-                .unwrap()
-                .value();
-            let description = description_ref
-                .cast::<web_sys::HtmlInputElement>()
-                .unwrap()
-                .value();
-            let body = body_ref
-                .cast::<web_sys::HtmlTextAreaElement>()
-                .unwrap()
-                .value();
-            let tags = tags_ref
-                .cast::<web_sys::HtmlInputElement>()
-                .unwrap()
-                .value();
-
-            on_publish.emit(ArticleData {
-                title,
-                description,
-                body,
-                tags,
-            });
+            if let Some(data) = read_form() {
+                on_publish.emit(data);
+            }
+        })
+    };
+
+    let store_draft = use_debounce(
+        {
+            let draft_status = draft_status.clone();
+            let slug = slug.clone();
+            move || {
+                if let Some(data) = read_form() {
+                    if save_draft(slug.as_deref(), &data) {
+                        draft_status.set(DraftStatus::Saved);
+                    }
+                }
+            }
+        },
+        DRAFT_SAVE_DEBOUNCE_MILLIS,
+    );
+
+    let on_change = {
+        let on_edit = on_edit.clone();
+        let draft_status = draft_status.clone();
+        Callback::from(move |_| {
+            on_edit.emit(());
+            draft_status.set(DraftStatus::Unsaved);
+            store_draft.run();
+        })
+    };
+
+    let oninput = {
+        let on_change = on_change.clone();
+        Callback::from(move |_: InputEvent| on_change.emit(()))
+    };
+
+    let tags = use_state_eq(Vec::<String>::new);
+    let dropped_tags = use_state_eq(Vec::<String>::new);
+
+    let on_tags_input = {
+        let tags_ref = tags_ref.clone();
+        let tags = tags.clone();
+        let dropped_tags = dropped_tags.clone();
+        let on_change = on_change.clone();
+        Callback::from(move |_: InputEvent| {
+            if let Some(el) = tags_ref.cast::<web_sys::HtmlInputElement>() {
+                let (kept, dropped) = enforce_tag_limit(&el);
+                tags.set(kept);
+                dropped_tags.set(dropped);
+            }
+            on_change.emit(());
+        })
+    };
+
+    let on_remove_tag = {
+        let tags_ref = tags_ref.clone();
+        let tags = tags.clone();
+        let dropped_tags = dropped_tags.clone();
+        let on_change = on_change.clone();
+        Callback::from(move |tag: String| {
+            if let Some(el) = tags_ref.cast::<web_sys::HtmlInputElement>() {
+                let kept = parse_tags(&el.value())
+                    .into_iter()
+                    .filter(|t| *t != tag)
+                    .collect::<Vec<_>>();
+                el.set_value(&kept.join(", "));
+                tags.set(kept);
+                dropped_tags.set(vec![]);
+            }
+            on_change.emit(());
+        })
+    };
+
+    use_effect_with(*published, {
+        let draft_status = draft_status.clone();
+        move |published| {
+            if *published {
+                draft_status.set(DraftStatus::Clean);
+            }
+        }
+    });
+
+    let initial = (*draft).clone().or_else(|| {
+        article.data.as_ref().map(|a| ArticleData {
+            title: a.title.clone(),
+            description: a.description.clone(),
+            body: a.body.clone(),
+            tags: a.tag_list.join(", "),
+            version: a.version,
         })
+    });
+
+    // The form fills in once the draft or article is known, so the chips follow it.
+    use_effect_with(initial.as_ref().map(|d| d.tags.clone()), {
+        let tags = tags.clone();
+        move |value| {
+            if let Some(value) = value {
+                tags.set(parse_tags(value));
+            }
+        }
+    });
+
+    let too_long = tags
+        .iter()
+        .filter(|tag| tag.chars().count() > TAG_MAX_LENGTH)
+        .collect::<Vec<_>>();
+
+    let draft_indicator = match *draft_status {
+        DraftStatus::Clean => html! {},
+        DraftStatus::Unsaved => html! { <span class="draft-status">{"Unsaved changes"}</span> },
+        DraftStatus::Saved => html! { <span class="draft-status">{"Draft saved"}</span> },
     };
 
     html! {
@@ -183,35 +428,204 @@ fn EditorForm(props: &EditorFormProps) -> Html {
                         type="text"
                         class="form-control form-control-lg"
                         placeholder="Article Title"
-                        value={article.data.as_ref().map(|a| a.title.clone())}/>
+                        oninput={oninput.clone()}
+                        value={initial.as_ref().map(|d| d.title.clone())}/>
                 </fieldset>
                 <fieldset class="form-group">
                     <input ref={description_ref}
                         type="text"
                         class="form-control" placeholder="What's this article about?"
-                        value={article.data.as_ref().map(|a| a.description.clone())}/>
+                        oninput={oninput.clone()}
+                        value={initial.as_ref().map(|d| d.description.clone())}/>
                 </fieldset>
                 <fieldset class="form-group">
+                    <ul class="nav nav-tabs editor-tabs">
+                        <li class="nav-item">
+                            <a class={classes!("nav-link", body_preview.is_none().then_some("active"))}
+                                onclick={on_body_write} href="javascript:void(0);">{"Write"}</a>
+                        </li>
+                        <li class="nav-item">
+                            <a class={classes!("nav-link", body_preview.is_some().then_some("active"))}
+                                onclick={on_body_preview} href="javascript:void(0);">{"Preview"}</a>
+                        </li>
+                    </ul>
+                    if body_preview.is_none() {
+                        <MarkdownToolbar target={body_ref.clone()} on_change={on_change.clone()} />
+                    }
+                    // Only hidden while previewing, so the body stays in the textarea.
                     <textarea ref={body_ref}
                         class="form-control"
                         rows="8"
                         placeholder="Write your article (in markdown)"
-                        value={article.data.as_ref().map(|a| a.body.clone())}
+                        hidden={body_preview.is_some()}
+                        oninput={oninput.clone()}
+                        value={initial.as_ref().map(|d| d.body.clone())}
                     ></textarea>
+                    if let Some(html) = (*body_preview).clone() {
+                        <RenderedHtml class="article-body editor-preview" html={html} />
+                    }
                 </fieldset>
                 <fieldset class="form-group">
                     <input ref={tags_ref}
                         type="text"
                         class="form-control"
-                        disabled={slug.is_some()}
                         placeholder="Enter tags"
-                        value={article.data.as_ref().map(|a| a.tag_list.join(", "))}/>
-                    <div class="tag-list"></div>
+                        oninput={on_tags_input}
+                        value={initial.as_ref().map(|d| d.tags.clone())}/>
+                    <small class={classes!("tag-counter", "text-muted", (tags.len() >= MAX_TAGS).then_some("text-danger"))}>
+                        {format!("{} of {MAX_TAGS} tags", tags.len())}
+                    </small>
+                    if !dropped_tags.is_empty() {
+                        <small class="tag-feedback text-danger">
+                            {format!("Only {MAX_TAGS} tags are allowed, left out: {}", dropped_tags.join(", "))}
+                        </small>
+                    }
+                    {
+                        for too_long.iter().map(|tag| html! {
+                            <small class="tag-feedback text-danger">
+                                {format!("\"{tag}\" is longer than {TAG_MAX_LENGTH} characters")}
+                            </small>
+                        })
+                    }
+                    <div class="tag-list">
+                    {
+                        for tags.iter().map(|tag| {
+                            let onclick = {
+                                let tag = tag.clone();
+                                on_remove_tag.reform(move |_: MouseEvent| tag.clone())
+                            };
+                            let invalid = tag.chars().count() > TAG_MAX_LENGTH;
+
+                            html! {
+                                <span class={classes!("tag-default", "tag-pill", invalid.then_some("tag-invalid"))}>
+                                    <i {onclick} class="ion-close-round"></i>
+                                    {tag}
+                                </span>
+                            }
+                        })
+                    }
+                    </div>
                 </fieldset>
-                <button {onclick} class="btn btn-lg pull-xs-right btn-primary" type="button">
+                <button {onclick} class="btn btn-lg pull-xs-right btn-primary" type="button" disabled={*publishing || !too_long.is_empty()}>
                         {"Publish Article"}
                 </button>
+                {draft_indicator}
             </fieldset>
         </form>
     }
 }
+
+/// A markdown wrapper applied around the body textarea's selection.
+struct MarkdownFormat {
+    title: &'static str,
+    label: &'static str,
+    prefix: &'static str,
+    suffix: &'static str,
+    placeholder: &'static str,
+}
+
+const MARKDOWN_FORMATS: [MarkdownFormat; 4] = [
+    MarkdownFormat {
+        title: "Bold",
+        label: "B",
+        prefix: "**",
+        suffix: "**",
+        placeholder: "bold text",
+    },
+    MarkdownFormat {
+        title: "Italic",
+        label: "I",
+        prefix: "_",
+        suffix: "_",
+        placeholder: "italic text",
+    },
+    MarkdownFormat {
+        title: "Link",
+        label: "Link",
+        prefix: "[",
+        suffix: "](https://)",
+        placeholder: "link text",
+    },
+    MarkdownFormat {
+        title: "Code",
+        label: "</>",
+        prefix: "`",
+        suffix: "`",
+        placeholder: "code",
+    },
+];
+
+/// Wraps the selection in `format` (or inserts its placeholder at the caret when nothing is
+/// selected), then gives focus back to the textarea with the wrapped text selected.
+fn wrap_selection(el: &web_sys::HtmlTextAreaElement, format: &MarkdownFormat) {
+    // Selection offsets are in UTF-16 code units, not bytes or chars.
+    let value = el.value().encode_utf16().collect::<Vec<_>>();
+    let start = el
+        .selection_start()
+        .ok()
+        .flatten()
+        .map_or(value.len(), |n| n as usize)
+        .min(value.len());
+    let end = el
+        .selection_end()
+        .ok()
+        .flatten()
+        .map_or(start, |n| n as usize)
+        .clamp(start, value.len());
+
+    let selected = String::from_utf16_lossy(&value[start..end]);
+    let inner = if selected.is_empty() {
+        format.placeholder.to_string()
+    } else {
+        selected
+    };
+
+    el.set_value(&format!(
+        "{}{}{inner}{}{}",
+        String::from_utf16_lossy(&value[..start]),
+        format.prefix,
+        format.suffix,
+        String::from_utf16_lossy(&value[end..]),
+    ));
+
+    let inner_start = start + format.prefix.encode_utf16().count();
+    let inner_end = inner_start + inner.encode_utf16().count();
+    let _ = el.set_selection_range(inner_start as u32, inner_end as u32);
+    let _ = el.focus();
+}
+
+#[derive(PartialEq, Properties)]
+struct MarkdownToolbarProps {
+    target: NodeRef,
+    /// Setting the value from code fires no `input` event, so edits are reported here instead.
+    on_change: Callback<()>,
+}
+
+#[function_component]
+fn MarkdownToolbar(props: &MarkdownToolbarProps) -> Html {
+    let MarkdownToolbarProps { target, on_change } = props;
+
+    html! {
+        <div class="btn-group markdown-toolbar" role="group">
+        {
+            for MARKDOWN_FORMATS.iter().enumerate().map(|(i, format)| {
+                let onclick = {
+                    let target = target.clone();
+                    let on_change = on_change.clone();
+                    move |_| {
+                        if let Some(el) = target.cast::<web_sys::HtmlTextAreaElement>() {
+                            wrap_selection(&el, &MARKDOWN_FORMATS[i]);
+                            on_change.emit(());
+                        }
+                    }
+                };
+                html! {
+                    <button {onclick} type="button" title={format.title} class="btn btn-sm btn-outline-secondary">
+                        {format.label}
+                    </button>
+                }
+            })
+        }
+        </div>
+    }
+}
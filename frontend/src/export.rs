@@ -0,0 +1,123 @@
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{
+    js_sys,
+    wasm_bindgen::{JsCast, JsValue},
+    Blob, BlobPropertyBag, HtmlAnchorElement, Url,
+};
+use yew::prelude::*;
+
+use crate::api::Article;
+
+/// The article as a markdown file with front matter:
+///
+/// ```text
+/// ---
+/// title: "How to train your dragon"
+/// description: "Ever wonder how?"
+/// tags: ["dragons", "training"]
+/// ---
+///
+/// <body>
+/// ```
+///
+/// Values are JSON strings and arrays, which YAML reads unchanged, so an importer can parse
+/// the block between the `---` lines with either.
+pub fn article_markdown(article: &Article) -> String {
+    let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+
+    format!(
+        "---\ntitle: {}\ndescription: {}\ntags: {}\n---\n\n{}\n",
+        quote(&article.title),
+        quote(&article.description),
+        serde_json::to_string(&article.tag_list).unwrap_or_default(),
+        article.body.trim_end(),
+    )
+}
+
+/// `navigator.clipboard` only exists in secure contexts, and writing to it may still be refused.
+async fn copy_to_clipboard(text: &str) -> bool {
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+
+    let clipboard = js_sys::Reflect::get(&window.navigator(), &"clipboard".into())
+        .unwrap_or(JsValue::UNDEFINED);
+    if clipboard.is_undefined() {
+        return false;
+    }
+
+    let Ok(write_text) = js_sys::Reflect::get(&clipboard, &"writeText".into())
+        .and_then(|write_text| write_text.dyn_into::<js_sys::Function>())
+    else {
+        return false;
+    };
+
+    match write_text.call1(&clipboard, &text.into()) {
+        Ok(promise) => JsFuture::from(js_sys::Promise::from(promise)).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn download(filename: &str, text: &str) -> Result<(), JsValue> {
+    let mut options = BlobPropertyBag::new();
+    options.type_("text/markdown");
+    let blob = Blob::new_with_str_sequence_and_options(
+        &js_sys::Array::of1(&JsValue::from_str(text)),
+        &options,
+    )?;
+
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or(JsValue::NULL)?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    Url::revoke_object_url(&url)
+}
+
+#[derive(PartialEq, Properties)]
+pub struct ExportMarkdownButtonProps {
+    pub article: Article,
+}
+
+/// Copies the article as markdown, or downloads it as `<slug>.md` where the clipboard isn't available.
+#[function_component]
+pub fn ExportMarkdownButton(props: &ExportMarkdownButtonProps) -> Html {
+    let ExportMarkdownButtonProps { article } = props;
+
+    let done = use_state_eq(|| None::<&'static str>);
+
+    use_effect_with(article.slug.clone(), {
+        let done = done.clone();
+        move |_| done.set(None)
+    });
+
+    let onclick = {
+        let article = article.clone();
+        let done = done.clone();
+        Callback::from(move |_| {
+            let markdown = article_markdown(&article);
+            let filename = format!("{}.md", article.slug);
+            let done = done.clone();
+            spawn_local(async move {
+                if copy_to_clipboard(&markdown).await {
+                    done.set(Some(" Copied"));
+                } else if let Err(err) = download(&filename, &markdown) {
+                    log::error!("failed to export article: {err:?}");
+                } else {
+                    done.set(Some(" Downloaded"));
+                }
+            });
+        })
+    };
+
+    html! {
+        <button {onclick} type="button" class="btn btn-sm btn-outline-secondary">
+            <i class="ion-clipboard"></i>
+            { done.unwrap_or(" Copy as markdown") }
+        </button>
+    }
+}
@@ -1,13 +1,20 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use chrono::{DateTime, Local};
+use gloo_storage::{SessionStorage, Storage};
+use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 use yew_hooks::prelude::*;
 use yew_router::prelude::*;
 
 use crate::{
-    api::{ApiError, ApiRequest, Article, ArticleResp, MultipleArticle},
+    api::{
+        ApiError, ApiRequest, ArticleResp, ArticleSummary, LikedByResp, MultipleArticle,
+        UserProfile,
+    },
     auth::AuthContext,
+    preferences::use_preferences,
+    reading_list::SaveForLaterButton,
     route::Route,
 };
 
@@ -16,6 +23,12 @@ pub struct FeedTabProps {
     pub tabs: Vec<Tab>,
     pub cur_tab: FeedType,
     pub onclick: Callback<FeedType>,
+    /// Whether `cur_tab` is highlighted; off while one of the `children` is the current tab.
+    #[prop_or(true)]
+    pub active: bool,
+    /// Extra tabs, already wrapped in `li.nav-item`, for content other than feeds.
+    #[prop_or_default]
+    pub children: Html,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -30,6 +43,8 @@ pub fn FeedTab(props: &FeedTabProps) -> Html {
         tabs,
         cur_tab,
         onclick,
+        active,
+        children,
     } = props;
 
     html! {
@@ -39,7 +54,7 @@ pub fn FeedTab(props: &FeedTabProps) -> Html {
                 let onclick = onclick.clone();
                 html! {
                 <li class="nav-item">
-                    <a class={classes!("nav-link", if &tab.value == cur_tab {Some("active")} else {None})}
+                    <a class={classes!("nav-link", if *active && &tab.value == cur_tab {Some("active")} else {None})}
                         onclick={ move |_| onclick.emit(tab.value.clone()) }
                         href="javascript:void(0);"
                     >{&tab.name}</a>
@@ -47,6 +62,7 @@ pub fn FeedTab(props: &FeedTabProps) -> Html {
                 }
             })
         }
+        { children.clone() }
         </ul>
     }
 }
@@ -54,9 +70,33 @@ pub fn FeedTab(props: &FeedTabProps) -> Html {
 #[derive(PartialEq, Properties)]
 pub struct FeedProps {
     pub limit: usize,
-    pub feed_type: FeedType,
+    pub source: FeedSource,
+    /// Shown instead of the generic message when the feed has no articles.
+    #[prop_or_default]
+    pub empty_message: Option<AttrValue>,
+    /// Shown instead of either message when the empty feed needs more than text, e.g. links.
+    #[prop_or_default]
+    pub empty_state: Option<Html>,
+    /// Zero-based page to open first instead of the remembered one, e.g. taken from the URL.
+    #[prop_or_default]
+    pub initial_page: Option<usize>,
+    /// Told about every page change, including the first, so the page can be kept in the URL.
+    #[prop_or_default]
+    pub on_page: Callback<usize>,
 }
 
+/// How much of each card a feed shows; compact leaves out descriptions and tags.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    #[default]
+    Comfortable,
+    Compact,
+}
+
+/// Placeholder cards shown while a page loads; more than this would only push the footer around.
+const MAX_SKELETON_CARDS: usize = 3;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum FeedType {
     Global,
@@ -64,119 +104,267 @@ pub enum FeedType {
     Tag(String),
     User(String),
     Favorited(String),
+    Search(String),
+}
+
+/// Where a [`Feed`] gets its articles from: any endpoint that pages like `GET /api/articles`.
+/// The feed adds `limit`, `offset` and `format` to whatever request the source builds.
+#[derive(Clone)]
+pub struct FeedSource {
+    /// Tells sources apart, both for refetching when it changes and for remembering where the
+    /// reader left off.
+    key: String,
+    request: Rc<dyn Fn() -> ApiRequest>,
+}
+
+impl PartialEq for FeedSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl FeedSource {
+    /// `key` must differ between sources that return different articles.
+    pub fn new(key: impl Into<String>, request: impl Fn() -> ApiRequest + 'static) -> Self {
+        Self {
+            key: key.into(),
+            request: Rc::new(request),
+        }
+    }
+
+    /// `following_only` restricts the global and tag feeds to authors the user follows, and is
+    /// ignored for the others.
+    pub fn from_type(feed_type: FeedType, following_only: bool) -> Self {
+        let following_only =
+            following_only && matches!(feed_type, FeedType::Global | FeedType::Tag(_));
+
+        // Same keys as before sources existed, so saved positions still apply.
+        let key = format!("{feed_type:?}:{following_only}");
+
+        Self::new(key, move || {
+            let req = match &feed_type {
+                FeedType::Global => ApiRequest::get("/api/articles"),
+                FeedType::UserFeed => ApiRequest::get("/api/articles/feed"),
+                FeedType::Tag(tag) => ApiRequest::get("/api/articles").query([("tag", tag)]),
+                FeedType::User(username) => {
+                    ApiRequest::get("/api/articles").query([("author", username)])
+                }
+                FeedType::Favorited(username) => {
+                    ApiRequest::get("/api/articles").query([("favorited", username)])
+                }
+                FeedType::Search(q) => {
+                    ApiRequest::get("/api/articles/search").query([("q", q.as_str())])
+                }
+            };
+
+            if following_only {
+                req.query([("following", "true")])
+            } else {
+                req
+            }
+        })
+    }
+}
+
+impl From<FeedType> for FeedSource {
+    fn from(feed_type: FeedType) -> Self {
+        Self::from_type(feed_type, false)
+    }
+}
+
+struct FeedPage {
+    articles: MultipleArticle,
+    /// Followed users who favorited each article; empty when signed out or on failure.
+    liked_by: HashMap<String, Vec<UserProfile>>,
 }
 
 #[function_component]
 pub fn Feed(props: &FeedProps) -> Html {
-    let FeedProps { limit, feed_type } = props;
+    let FeedProps {
+        limit,
+        source,
+        empty_message,
+        empty_state,
+        initial_page,
+        on_page,
+    } = props;
 
     let auth = use_context::<AuthContext>().unwrap();
     let navigator = use_navigator().unwrap();
 
-    let cur_page = use_state_eq(|| 0);
+    let position_key = position_key(source);
 
-    use_effect_with(
-        feed_type.clone(),
-        {
-            let cur_page = cur_page.clone();
-            move |_| cur_page.set(0)
-        },
-    );
+    // Only the first source opens on `initial_page`; switching feeds goes by the saved positions.
+    let initial_page = use_mut_ref(|| *initial_page);
+    let cur_page = use_state_eq(|| {
+        initial_page
+            .borrow()
+            .or_else(|| load_position(&position_key).map(|p| p.page))
+            .unwrap_or(0)
+    });
+    // Scroll offset to restore once the saved page has rendered.
+    let pending_scroll = use_mut_ref(|| None);
+    let latest_page = use_mut_ref(|| 0);
+    *latest_page.borrow_mut() = *cur_page;
+
+    use_effect_with(position_key.clone(), {
+        let cur_page = cur_page.clone();
+        let pending_scroll = pending_scroll.clone();
+        move |key| {
+            let saved = load_position(key);
+            let page = initial_page
+                .borrow_mut()
+                .take()
+                .or_else(|| saved.as_ref().map(|p| p.page))
+                .unwrap_or(0);
+            cur_page.set(page);
+            // The saved offset means nothing on another page than the one it was saved on.
+            *pending_scroll.borrow_mut() = saved.filter(|p| p.page == page).map(|p| p.scroll_y);
+
+            // Runs when switching to another feed or leaving the page.
+            let key = key.clone();
+            move || save_position(&key, *latest_page.borrow())
+        }
+    });
+
+    use_effect_with(*cur_page, {
+        let on_page = on_page.clone();
+        move |page| on_page.emit(*page)
+    });
 
     let feed = {
         let auth = auth.clone();
-        let feed_type = feed_type.clone();
+        let source = source.clone();
         let limit = limit.clone();
         let cur_page = cur_page.clone();
 
         use_async(async move {
-            let url = match feed_type {
-                FeedType::Global => "/api/articles".to_string(),
-                FeedType::UserFeed => "/api/articles/feed".to_string(),
-                FeedType::Tag(tag) => format!("/api/articles?tag={tag}"),
-                FeedType::User(username) => format!("/api/articles?author={username}"),
-                FeedType::Favorited(username) => format!("/api/articles?favorited={username}"),
-            };
-
-            let articles: MultipleArticle = ApiRequest::get(&url)
+            let req = (source.request)()
                 .query([("limit", limit.to_string())])
                 .query([("offset", (*cur_page * limit).to_string())])
-                .auth(auth.user())
-                .json_response()
-                .await?;
+                .query([("format", "summary")]);
+
+            let articles: MultipleArticle = req.auth(auth.user()).json_response().await?;
+
+            // One request for the whole page, and only for signed-in users who can follow anyone.
+            let slugs = articles.articles.iter().map(|a| &a.slug).collect::<Vec<_>>();
+            let liked_by = if auth.is_authorized() && !slugs.is_empty() {
+                match ApiRequest::post("/api/articles/favorited-by-following")
+                    .auth(auth.user())
+                    .json(&serde_json::json!({ "slugs": slugs }))
+                    .json_response::<LikedByResp>()
+                    .await
+                {
+                    Ok(resp) => resp.profiles,
+                    Err(err) => {
+                        log::warn!("failed to load favorites by followed users: {err:?}");
+                        HashMap::new()
+                    }
+                }
+            } else {
+                HashMap::new()
+            };
 
-            Ok::<_, Rc<ApiError>>(Rc::new(articles))
+            Ok::<_, Rc<ApiError>>(Rc::new(FeedPage { articles, liked_by }))
         })
     };
 
     let update_feed = use_bool_toggle(false);
 
-    use_effect_with(
-        ((*feed_type).clone(), *update_feed, *cur_page),
-        {
-            let feed = feed.clone();
-            move |_| {
-                feed.run();
-                || {}
-            }
-        }
-    );
-
-    let fav_arg = use_state(|| None);
-
-    let send_fav = use_async({
-        let auth = auth.clone();
-        let fav_arg = fav_arg.clone();
-        let update_feed = update_feed.clone();
-
-        async move {
-            let Some((slug, fav)) = &*fav_arg else {
-                return Ok::<_, Rc<ApiError>>(())
-            };
+    let preferences = use_preferences();
+    let density = preferences.current.density;
+    let on_density = Callback::from(move |value: Density| {
+        let mut next = preferences.current.clone();
+        next.density = value;
+        preferences.set.emit(next);
+    });
 
-            let url = format!("/api/articles/{slug}/favorite");
+    use_effect_with((source.clone(), *update_feed, *cur_page), {
+        let feed = feed.clone();
+        move |_| {
+            feed.run();
+            || {}
+        }
+    });
 
-            let req = if *fav {
-                ApiRequest::post(&url)
-            } else {
-                ApiRequest::delete(&url)
+    use_effect_with(feed.loading, {
+        let feed = feed.clone();
+        let cur_page = cur_page.clone();
+        let limit = *limit;
+        move |loading| {
+            let Some(data) = feed.data.as_deref().filter(|_| !*loading) else {
+                return;
             };
 
-            let _: ArticleResp = req.auth(auth.user()).json_response().await?;
-
-            update_feed.toggle();
+            // The feed shrank since the position was saved; fall back to its last page.
+            if data.articles.articles.is_empty() && *cur_page > 0 {
+                let pages = (data.articles.articles_count + limit - 1) / limit;
+                cur_page.set(pages.saturating_sub(1));
+                return;
+            }
 
-            Ok(())
+            if let Some(scroll_y) = pending_scroll.borrow_mut().take() {
+                scroll_to_clamped(scroll_y);
+            }
         }
     });
 
-    let fav_callback = Rc::new(Callback::from({
-        let auth = auth.clone();
-        move |(slug, fav)| {
+    // Memoized on `auth` so the cards don't all re-render whenever the feed does.
+    let fav_callback = use_callback(auth.clone(), {
+        let update_feed = update_feed.clone();
+        move |(slug, fav): (String, bool), auth| {
             if auth.is_unauthorized() {
                 navigator.push(&Route::Register);
-            } else {
-                fav_arg.set(Some((slug, fav)));
-                send_fav.run();
+                return;
             }
+
+            let user = auth.user().cloned();
+            let update_feed = update_feed.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let url = format!("/api/articles/{slug}/favorite");
+
+                let req = if fav {
+                    ApiRequest::post(&url)
+                } else {
+                    ApiRequest::delete(&url)
+                };
+
+                match req.auth(user.as_ref()).json_response::<ArticleResp>().await {
+                    Ok(_) => update_feed.toggle(),
+                    Err(err) => log::error!("failed to update favorite: {err:?}"),
+                }
+            });
         }
-    }));
+    });
 
-    let Some(articles) = feed.data.as_ref() else {
-        return html! { <div class="article-preview">{"Loading articles..."}</div> };
+    let Some(FeedPage { articles, liked_by }) = feed.data.as_deref() else {
+        return html! {
+            <div aria-busy="true" aria-label="Loading articles...">
+                { for (0..(*limit).min(MAX_SKELETON_CARDS)).map(|_| html! { <ArticleCardSkeleton /> }) }
+            </div>
+        };
     };
 
     if articles.articles.is_empty() {
-        return html! { <div class="article-preview">{"No articles are here... yet."}</div> };
+        if let Some(empty_state) = empty_state {
+            return empty_state.clone();
+        }
+        let message = empty_message.as_deref().unwrap_or("No articles are here... yet.");
+        return html! { <div class="article-preview">{message}</div> };
     }
 
     let pages = (articles.articles_count + limit - 1) / limit;
 
     html! {
         <>
+        <DensityToggle density={density} onchange={on_density} />
         {
             for articles.articles.iter().map(|article| html! {
-                <ArticleCard article={article.clone()} fav_callback={fav_callback.clone()} />
+                <ArticleCard
+                    article={article.clone()}
+                    liked_by={liked_by.get(&article.slug).cloned().unwrap_or_default()}
+                    compact={density == Density::Compact}
+                    fav_callback={fav_callback.clone()} />
             })
         }
         if pages >= 2 {
@@ -201,19 +389,129 @@ pub fn Feed(props: &FeedProps) -> Html {
     }
 }
 
+/// Where the reader left a feed, so returning from an article doesn't start over at the top.
+#[derive(Serialize, Deserialize)]
+struct FeedPosition {
+    page: usize,
+    scroll_y: f64,
+}
+
+fn position_key(source: &FeedSource) -> String {
+    format!("feed_position:{}", source.key)
+}
+
+fn load_position(key: &str) -> Option<FeedPosition> {
+    SessionStorage::get(key).ok()
+}
+
+fn save_position(key: &str, page: usize) {
+    let scroll_y = web_sys::window()
+        .and_then(|window| window.scroll_y().ok())
+        .unwrap_or(0.0);
+
+    if let Err(err) = SessionStorage::set(key, FeedPosition { page, scroll_y }) {
+        log::warn!("failed to store feed position: {err:?}");
+    }
+}
+
+/// Scrolls to `y`, or as far as the current content allows.
+fn scroll_to_clamped(y: f64) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+
+    let max = window
+        .document()
+        .and_then(|document| document.document_element())
+        .map_or(y, |el| f64::from(el.scroll_height() - el.client_height()));
+
+    window.scroll_to_with_x_and_y(0.0, y.min(max).max(0.0));
+}
+
+#[derive(PartialEq, Properties)]
+struct DensityToggleProps {
+    density: Density,
+    onchange: Callback<Density>,
+}
+
+#[function_component]
+fn DensityToggle(props: &DensityToggleProps) -> Html {
+    let DensityToggleProps { density, onchange } = props;
+
+    let button = |value: Density, label: &'static str| {
+        let onchange = onchange.clone();
+        let active = *density == value;
+        html! {
+            <button type="button" aria-pressed={active.to_string()}
+                class={classes!("btn", "btn-sm", if active { "btn-secondary" } else { "btn-outline-secondary" })}
+                onclick={move |_| onchange.emit(value)}>
+                {label}
+            </button>
+        }
+    };
+
+    html! {
+        <div class="feed-density btn-group pull-xs-right" role="group" aria-label="Feed density">
+            {button(Density::Comfortable, "Comfortable")}
+            {button(Density::Compact, "Compact")}
+        </div>
+    }
+}
+
+#[function_component]
+fn ArticleCardSkeleton() -> Html {
+    html! {
+        <div class="article-preview skeleton" aria-hidden="true">
+            <div class="article-meta">
+                <span class="skeleton-avatar"></span>
+                <div class="info">
+                    <span class="skeleton-line skeleton-short"></span>
+                </div>
+            </div>
+            <span class="skeleton-line skeleton-title"></span>
+            <span class="skeleton-line"></span>
+        </div>
+    }
+}
+
+/// "Liked by alice", "Liked by alice and bob" or "Liked by alice and 3 others you follow".
+fn liked_by_line(profiles: &[UserProfile]) -> Option<String> {
+    match profiles {
+        [] => None,
+        [one] => Some(format!("Liked by {}", one.username)),
+        [one, two] => Some(format!("Liked by {} and {}", one.username, two.username)),
+        [first, rest @ ..] => Some(format!(
+            "Liked by {} and {} others you follow",
+            first.username,
+            rest.len()
+        )),
+    }
+}
+
 #[derive(PartialEq, Properties)]
 pub struct ArticleCardProps {
-    article: Article,
-    fav_callback: Rc<Callback<(String, bool)>>,
+    article: ArticleSummary,
+    /// Leaves out the description and tags.
+    #[prop_or_default]
+    compact: bool,
+    /// Followed users who favorited the article.
+    #[prop_or_default]
+    liked_by: Vec<UserProfile>,
+    fav_callback: Callback<(String, bool)>,
 }
 
 #[function_component]
 pub fn ArticleCard(props: &ArticleCardProps) -> Html {
     let ArticleCardProps {
         article,
+        compact,
+        liked_by,
         fav_callback,
     } = props;
 
+    let auth = use_context::<AuthContext>().unwrap();
+    let is_author = matches!(auth.user(), Some(user) if user.username == article.author.username);
+
     let date = DateTime::<Local>::from(article.created_at).format("%B %e, %Y").to_string();
     let btn_outline = if article.favorited {
         "btn-primary"
@@ -231,7 +529,7 @@ pub fn ArticleCard(props: &ArticleCardProps) -> Html {
     };
 
     html! {
-        <div class="article-preview">
+        <div class={classes!("article-preview", compact.then_some("compact"))}>
             <div class="article-meta">
                 <Link<Route> to={Route::Profile{ username: article.author.username.clone() }}>
                     <img src={article.author.image().to_string()}/>
@@ -245,19 +543,37 @@ pub fn ArticleCard(props: &ArticleCardProps) -> Html {
                 <button {onclick} class={classes!("btn", "btn-sm", "pull-xs-right", btn_outline)}>
                     <i class="ion-heart"></i>{" "}{article.favorites_count}
                 </button>
+                <SaveForLaterButton slug={article.slug.clone()} classes="pull-xs-right" />
+                if is_author {
+                    <Link<Route> to={Route::Editor { slug: article.slug.clone() }} classes="btn btn-sm btn-outline-secondary pull-xs-right">
+                        <i class="ion-edit"></i>{" Edit"}
+                    </Link<Route>>
+                }
+                <span class="comments-count pull-xs-right" title="Comments">
+                    {format!("\u{1f4ac} {}", article.comments_count)}
+                </span>
             </div>
+            if let Some(line) = liked_by_line(liked_by) {
+                <div class="liked-by">
+                    <i class="ion-heart"></i>{" "}{line}
+                </div>
+            }
             <Link<Route> to={Route::Article { slug: article.slug.clone() }} classes="preview-link">
                 <h1>{&article.title}</h1>
-                <p>{&article.description}</p>
+                if !*compact {
+                    <p>{&article.description}</p>
+                }
                 <span>{"Read more..."}</span>
 
-                <ul class="tag-list">
-                    { for article.tag_list.iter().map(|tag| html! {
-                        <li class="tag-default tag-pill tag-outline">
-                            {tag}
-                        </li>
-                    })}
-                </ul>
+                if !*compact {
+                    <ul class="tag-list">
+                        { for article.tag_list.iter().map(|tag| html! {
+                            <li class="tag-default tag-pill tag-outline">
+                                {tag}
+                            </li>
+                        })}
+                    </ul>
+                }
             </Link<Route>>
         </div>
     }
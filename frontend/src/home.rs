@@ -1,33 +1,119 @@
 use std::rc::Rc;
 
+use gloo_timers::callback::Timeout;
+use serde::{Deserialize, Serialize};
+use web_sys::HtmlInputElement;
 use yew::prelude::*;
-use yew_hooks::{use_async_with_options, UseAsyncOptions};
+use yew_hooks::{use_async, use_async_with_options, use_list, UseAsyncOptions};
+use yew_router::prelude::*;
 
 use crate::{
-    api::{ApiError, TagsResp},
+    api::{ApiError, ApiRequest, MultipleArticle, ProfilesResp, TagsResp, UserProfile},
     auth::AuthContext,
-    feed::{Feed, FeedTab, FeedType, Tab},
+    feed::{Feed, FeedSource, FeedTab, FeedType, Tab},
+    route::Route,
 };
 
+/// Typing pauses this long before the search runs.
+const SEARCH_DEBOUNCE_MILLIS: u32 = 300;
+
+/// The feed being shown, kept in the URL (e.g. `/#/?tab=tag&tag=rust&page=2`) so that coming
+/// back to the home page, or opening a shared link, shows the same feed.
+#[derive(Default, Serialize, Deserialize)]
+struct HomeQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tab: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    q: Option<String>,
+    /// One-based, unlike the feed's own page numbers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+}
+
+impl HomeQuery {
+    fn new(feed_type: &FeedType, page: usize) -> Self {
+        let (tab, tag, q) = match feed_type {
+            FeedType::UserFeed => (Some("feed"), None, None),
+            FeedType::Global => (Some("global"), None, None),
+            FeedType::Tag(tag) => (Some("tag"), Some(tag.clone()), None),
+            FeedType::Search(q) => (Some("search"), None, Some(q.clone())),
+            // Not shown on the home page.
+            FeedType::User(_) | FeedType::Favorited(_) => (None, None, None),
+        };
+
+        Self {
+            tab: tab.map(str::to_string),
+            tag,
+            q,
+            page: (page > 0).then_some(page + 1),
+        }
+    }
+
+    /// `None` when the URL doesn't name a tab, or names one it doesn't fully describe.
+    fn feed_type(&self) -> Option<FeedType> {
+        match self.tab.as_deref()? {
+            "feed" => Some(FeedType::UserFeed),
+            "global" => Some(FeedType::Global),
+            "tag" => self.tag.clone().map(FeedType::Tag),
+            "search" => self.q.clone().map(FeedType::Search),
+            _ => None,
+        }
+    }
+
+    /// Zero-based, as the feed counts.
+    fn page(&self) -> Option<usize> {
+        self.page.map(|page| page.saturating_sub(1))
+    }
+}
+
 #[function_component]
 pub fn Home() -> Html {
     let auth = use_context::<AuthContext>().unwrap();
+    let navigator = use_navigator().unwrap();
+    let location = use_location().unwrap();
 
-    let cur_tab = use_state(|| FeedType::Global);
+    // Only read when the page opens; from then on the URL follows the state below.
+    let from_url = use_state(|| location.query::<HomeQuery>().unwrap_or_default());
+    let url_tab = from_url.feed_type();
+
+    let cur_tab = use_state(|| url_tab.clone().unwrap_or(FeedType::Global));
+    let cur_page = use_state_eq(|| from_url.page().unwrap_or(0));
+    let following_only = use_state_eq(|| false);
+    let query = use_state_eq(|| match &url_tab {
+        Some(FeedType::Search(q)) => q.clone(),
+        _ => String::new(),
+    });
+    // Replacing or dropping the timeout cancels it, so only the last keystroke searches.
+    let pending_search = use_mut_ref(|| None::<Timeout>);
 
     use_effect_with(
         auth.is_loading(),
         {
             let feed_type = cur_tab.clone();
             let auth = auth.clone();
+            let url_tab = url_tab.clone();
             move |_| {
-                if auth.is_authorized() {
+                if auth.is_authorized() && url_tab.is_none() {
                     feed_type.set(FeedType::UserFeed);
                 }
             }
         }
     );
 
+    // Replaced rather than pushed, so paging and switching tabs don't pile up history entries.
+    use_effect_with(((*cur_tab).clone(), *cur_page), move |(tab, page)| {
+        if let Err(err) = navigator.replace_with_query(&Route::Home, &HomeQuery::new(tab, *page)) {
+            log::warn!("failed to keep the feed in the URL: {err:?}");
+        }
+    });
+
+    let on_page = {
+        let cur_page = cur_page.clone();
+        Callback::from(move |page| cur_page.set(page))
+    };
+
     let mut tabs = vec![];
 
     if auth.is_authorized() {
@@ -49,9 +135,29 @@ pub fn Home() -> Html {
         });
     }
 
+    if let FeedType::Search(q) = &*cur_tab {
+        tabs.push(Tab {
+            name: format!("Results for \"{q}\""),
+            value: FeedType::Search(q.clone()),
+        });
+    }
+
+    let clear_search = {
+        let query = query.clone();
+        let pending_search = pending_search.clone();
+        move || {
+            pending_search.borrow_mut().take();
+            query.set(String::new());
+        }
+    };
+
     let onclick_tab = {
         let cur_tab = cur_tab.clone();
+        let clear_search = clear_search.clone();
         move |tab| {
+            if !matches!(tab, FeedType::Search(_)) {
+                clear_search();
+            }
             cur_tab.set(tab);
         }
     };
@@ -59,10 +165,64 @@ pub fn Home() -> Html {
     let onclick_tag = {
         let feed_type = cur_tab.clone();
         move |tag| {
+            clear_search();
             feed_type.set(FeedType::Tag(tag));
         }
     };
 
+    let oninput_search = {
+        let cur_tab = cur_tab.clone();
+        let query = query.clone();
+        let authorized = auth.is_authorized();
+        move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let value = input.value();
+            query.set(value.clone());
+
+            let cur_tab = cur_tab.clone();
+            *pending_search.borrow_mut() = Some(Timeout::new(SEARCH_DEBOUNCE_MILLIS, move || {
+                let q = value.trim();
+                if !q.is_empty() {
+                    cur_tab.set(FeedType::Search(q.to_string()));
+                } else if matches!(*cur_tab, FeedType::Search(_)) {
+                    cur_tab.set(if authorized {
+                        FeedType::UserFeed
+                    } else {
+                        FeedType::Global
+                    });
+                }
+            }));
+        }
+    };
+
+    let onchange_following = {
+        let following_only = following_only.clone();
+        move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            following_only.set(input.checked());
+        }
+    };
+
+    // A new user's feed is empty until they follow someone who writes; point them onwards.
+    let empty_state = (*cur_tab == FeedType::UserFeed).then(|| {
+        let onclick = {
+            let cur_tab = cur_tab.clone();
+            move |_| cur_tab.set(FeedType::Global)
+        };
+        html! {
+            <div class="article-preview">
+                <p>{"Your feed shows articles from the people you follow, and there's nothing from them yet."}</p>
+                <p>
+                    <a {onclick} href="javascript:void(0);">{"Browse the Global Feed"}</a>
+                    {" to find authors worth following, or pick someone from Who to follow."}
+                </p>
+            </div>
+        }
+    });
+
+    let show_following_toggle =
+        auth.is_authorized() && matches!(*cur_tab, FeedType::Global | FeedType::Tag(_));
+
     html! {
         <div class="home-page">
 
@@ -78,11 +238,23 @@ pub fn Home() -> Html {
         <div class="container page">
             <div class="row">
                 <div class="col-md-9">
+                    <input type="search" class="form-control home-search" placeholder="Search articles"
+                        value={(*query).clone()} oninput={oninput_search} />
                     <div class="feed-toggle">
                         <FeedTab {tabs} cur_tab={(*cur_tab).clone()} onclick={onclick_tab} />
                     </div>
 
-                    <Feed feed_type={(*cur_tab).clone()} limit=10 />
+                    if show_following_toggle {
+                        <div class="checkbox">
+                            <label>
+                                <input type="checkbox" checked={*following_only} onchange={onchange_following} />
+                                {" Only authors I follow"}
+                            </label>
+                        </div>
+                    }
+
+                    <Feed source={FeedSource::from_type((*cur_tab).clone(), show_following_toggle && *following_only)} limit=10
+                        initial_page={from_url.page()} {on_page} {empty_state} />
                 </div>
 
                 <div class="col-md-3">
@@ -90,6 +262,9 @@ pub fn Home() -> Html {
                         <p>{"Popular Tags"}</p>
                         <Tags onclick={onclick_tag} />
                     </div>
+                    if auth.is_authorized() {
+                        <WhoToFollow />
+                    }
                 </div>
 
             </div>
@@ -140,3 +315,131 @@ fn Tags(props: &TagsProps) -> Html {
         </div>
     }
 }
+
+/// Newest article slug matching `filter`, used to seed author suggestions.
+async fn latest_article_slug(
+    filter: Option<(&str, &str)>,
+    auth: &AuthContext,
+) -> Result<Option<String>, ApiError> {
+    let mut req = ApiRequest::get("/api/articles").query([("limit", "1"), ("format", "summary")]);
+    if let Some(filter) = filter {
+        req = req.query([filter]);
+    }
+
+    let articles: MultipleArticle = req.auth(auth.user()).json_response().await?;
+    Ok(articles.articles.into_iter().next().map(|a| a.slug))
+}
+
+#[function_component]
+fn WhoToFollow() -> Html {
+    let auth = use_context::<AuthContext>().unwrap();
+
+    // Usernames followed from here; hidden right away and restored if the request fails.
+    let followed = use_list(Vec::<String>::new());
+
+    let suggestions = use_async({
+        let auth = auth.clone();
+        async move {
+            let Some(user) = auth.user() else {
+                return Ok(vec![]);
+            };
+
+            // Authors similar to the user's latest favorite, or to the newest article if there's none.
+            let mut seed = latest_article_slug(Some(("favorited", &user.username)), &auth).await?;
+            if seed.is_none() {
+                seed = latest_article_slug(None, &auth).await?;
+            }
+            let Some(slug) = seed else {
+                return Ok(vec![]);
+            };
+
+            let resp: ProfilesResp =
+                ApiRequest::get(format!("/api/articles/{slug}/similar-authors"))
+                    .auth(Some(user))
+                    .json_response()
+                    .await?;
+
+            Ok::<_, Rc<ApiError>>(
+                resp.profiles
+                    .into_iter()
+                    .filter(|p| !p.following && p.username != user.username)
+                    .collect::<Vec<UserProfile>>(),
+            )
+        }
+    });
+
+    use_effect_with(auth.user().map(|u| u.username.clone()), {
+        let suggestions = suggestions.clone();
+        move |_| suggestions.run()
+    });
+
+    let on_follow = {
+        let auth = auth.clone();
+        let followed = followed.clone();
+        let suggestions = suggestions.clone();
+        Callback::from(move |username: String| {
+            followed.push(username.clone());
+
+            let user = auth.user().cloned();
+            let followed = followed.clone();
+            let suggestions = suggestions.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = ApiRequest::post(format!("/api/profiles/{username}/follow"))
+                    .auth(user.as_ref())
+                    .json_response::<serde_json::Value>()
+                    .await;
+
+                match result {
+                    Ok(_) => suggestions.run(),
+                    Err(err) => {
+                        log::error!("failed to follow {username}: {err:?}");
+                        followed.retain(|u| u != &username);
+                    }
+                }
+            });
+        })
+    };
+
+    let followed = followed.current();
+    let profiles = suggestions
+        .data
+        .iter()
+        .flatten()
+        .filter(|p| !followed.contains(&p.username))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    html! {
+        <div class="sidebar">
+            <p>{"Who to follow"}</p>
+            if suggestions.data.is_none() {
+                <div>{"Loading suggestions..."}</div>
+            } else if profiles.is_empty() {
+                <div>{"No suggestions right now."}</div>
+            } else {
+                <ul class="list-unstyled">
+                {
+                    for profiles.into_iter().map(|profile| {
+                        let onclick = {
+                            let on_follow = on_follow.clone();
+                            let username = profile.username.clone();
+                            move |_| on_follow.emit(username.clone())
+                        };
+                        html! {
+                            <li>
+                                <Link<Route> to={Route::Profile { username: profile.username.clone() }}>
+                                    <img src={profile.image().to_string()} class="user-pic" />
+                                    {&profile.username}
+                                </Link<Route>>
+                                <button {onclick} class="btn btn-sm btn-outline-secondary pull-xs-right">
+                                    <i class="ion-plus-round"></i>{" Follow"}
+                                </button>
+                            </li>
+                        }
+                    })
+                }
+                </ul>
+            }
+        </div>
+    }
+}
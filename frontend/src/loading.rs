@@ -0,0 +1,121 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use web_sys::js_sys;
+use yew::prelude::*;
+use yew_hooks::use_interval;
+
+/// A request running longer than this gets the "Still loading…" notice, which mostly happens
+/// while the backend is cold-starting.
+const SLOW_REQUEST_MILLIS: f64 = 3000.0;
+
+/// How often the indicator checks whether the oldest request has become slow.
+const SLOW_CHECK_MILLIS: u32 = 500;
+
+#[derive(Default)]
+struct InFlight {
+    next_id: u64,
+    /// Start time of each running request, in milliseconds since the epoch.
+    started: BTreeMap<u64, f64>,
+    listener: Option<Callback<usize>>,
+}
+
+thread_local! {
+    static IN_FLIGHT: RefCell<InFlight> = RefCell::default();
+}
+
+/// Applies `change` and tells the listener the new count, outside the borrow so that it may
+/// start requests of its own.
+fn update<R>(change: impl FnOnce(&mut InFlight) -> R) -> R {
+    let (ret, listener, count) = IN_FLIGHT.with(|in_flight| {
+        let mut in_flight = in_flight.borrow_mut();
+        let ret = change(&mut in_flight);
+        (ret, in_flight.listener.clone(), in_flight.started.len())
+    });
+    if let Some(listener) = listener {
+        listener.emit(count);
+    }
+    ret
+}
+
+/// Keeps a request counted as running until dropped, however the request ends.
+pub struct RequestGuard {
+    id: u64,
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        update(|in_flight| in_flight.started.remove(&self.id));
+    }
+}
+
+/// Counts a request as running for the [`LoadingIndicator`].
+pub fn track() -> RequestGuard {
+    update(|in_flight| {
+        let id = in_flight.next_id;
+        in_flight.next_id += 1;
+        in_flight.started.insert(id, js_sys::Date::now());
+        RequestGuard { id }
+    })
+}
+
+/// Whether any running request has been going for longer than [`SLOW_REQUEST_MILLIS`].
+fn any_slow() -> bool {
+    let now = js_sys::Date::now();
+    IN_FLIGHT.with(|in_flight| {
+        in_flight
+            .borrow()
+            .started
+            .values()
+            .any(|started| now - started > SLOW_REQUEST_MILLIS)
+    })
+}
+
+/// A bar along the top of the page while requests are running. Once one of them is slow, a
+/// single notice says so, however many there are; both go away when the last one finishes.
+#[function_component]
+pub fn LoadingIndicator() -> Html {
+    let pending = use_state_eq(|| 0);
+    let slow = use_state_eq(|| false);
+
+    use_effect_with((), {
+        let pending = pending.clone();
+        move |_| {
+            let listener = Callback::from(move |count| pending.set(count));
+            // Requests started before the indicator mounted still count.
+            update(|in_flight| in_flight.listener = Some(listener));
+            || IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().listener = None)
+        }
+    });
+
+    {
+        let slow = slow.clone();
+        use_interval(
+            move || slow.set(any_slow()),
+            if *pending > 0 { SLOW_CHECK_MILLIS } else { 0 },
+        );
+    }
+
+    use_effect_with(*pending, {
+        let slow = slow.clone();
+        move |pending| {
+            if *pending == 0 {
+                slow.set(false);
+            }
+        }
+    });
+
+    if *pending == 0 {
+        return html! {};
+    }
+
+    html! {
+        <>
+            <div class="loading-bar" aria-hidden="true"></div>
+            if *slow {
+                <div class="loading-slow" role="status">
+                    {"Still loading\u{2026} the server may be waking up."}
+                </div>
+            }
+        </>
+    }
+}
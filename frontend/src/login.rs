@@ -1,5 +1,8 @@
 use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use validator::ValidateEmail;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 use yew_hooks::prelude::*;
@@ -20,11 +23,65 @@ pub enum LoginMode {
     SignUp,
 }
 
+/// Query string of the sign-in page.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LoginQuery {
+    /// Set when the user was signed out because their session stopped being accepted.
+    #[serde(default)]
+    pub expired: bool,
+}
+
+/// Same minimum as the server enforces on sign-up.
+const PASSWORD_MIN_LENGTH: usize = 8;
+
+/// Catches the obvious mistakes before a round-trip; the server still checks everything.
+/// Messages read like the server's, as `<field> <message>`.
+fn check_form(
+    mode: LoginMode,
+    username: &str,
+    email: &str,
+    password: &str,
+) -> Result<(), ApiError> {
+    let mut errors = serde_json::Map::new();
+
+    if mode == LoginMode::SignUp && username.trim().is_empty() {
+        errors.insert("username".into(), json!("can't be blank"));
+    }
+
+    if email.trim().is_empty() {
+        errors.insert("email".into(), json!("can't be blank"));
+    } else if !email.trim().validate_email() {
+        errors.insert("email".into(), json!("is not a valid address"));
+    }
+
+    if password.is_empty() {
+        errors.insert("password".into(), json!("can't be blank"));
+    } else if mode == LoginMode::SignUp && password.chars().count() < PASSWORD_MIN_LENGTH {
+        errors.insert(
+            "password".into(),
+            json!(format!(
+                "must be at least {PASSWORD_MIN_LENGTH} characters long"
+            )),
+        );
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::AppError(errors.into()))
+    }
+}
+
 #[function_component]
 pub fn Login(props: &LoginProps) -> Html {
     let auth = use_context::<AuthContext>().unwrap();
+    let location = use_location().unwrap();
 
     let mode = props.mode;
+    let expired = mode == LoginMode::SignIn
+        && location
+            .query::<LoginQuery>()
+            .map_or(false, |query| query.expired);
 
     let title = match props.mode {
         LoginMode::SignIn => "Sign in",
@@ -44,6 +101,26 @@ pub fn Login(props: &LoginProps) -> Html {
     let email_ref = use_node_ref();
     let password_ref = use_node_ref();
 
+    // The submit button stays disabled until every field has something in it.
+    let filled = use_state_eq(|| false);
+
+    let oninput = {
+        let filled = filled.clone();
+        let refs = [
+            username_ref.clone(),
+            email_ref.clone(),
+            password_ref.clone(),
+        ];
+        Callback::from(move |_: InputEvent| {
+            // The username field only exists when signing up.
+            filled.set(refs.iter().all(|field| {
+                field
+                    .cast::<HtmlInputElement>()
+                    .map_or(true, |input| !input.value().is_empty())
+            }));
+        })
+    };
+
     let state = {
         let auth = auth.clone();
         let username_ref = username_ref.clone();
@@ -53,6 +130,12 @@ pub fn Login(props: &LoginProps) -> Html {
         use_async(async move {
             let email = email_ref.cast::<HtmlInputElement>().unwrap().value();
             let password = password_ref.cast::<HtmlInputElement>().unwrap().value();
+            let username = username_ref
+                .cast::<HtmlInputElement>()
+                .map(|input| input.value())
+                .unwrap_or_default();
+
+            check_form(mode, &username, &email, &password)?;
 
             match mode {
                 LoginMode::SignIn => {
@@ -60,8 +143,6 @@ pub fn Login(props: &LoginProps) -> Html {
                     auth.dispatch(Auth::Authorized(user));
                 }
                 LoginMode::SignUp => {
-                    let username = username_ref.cast::<HtmlInputElement>().unwrap().value();
-
                     let user = register_user(&username, &email, &password).await?;
                     auth.dispatch(Auth::Authorized(user));
                 }
@@ -84,6 +165,8 @@ pub fn Login(props: &LoginProps) -> Html {
 
     let error_message = if let Some(err) = &state.error {
         err.to_vec_string()
+    } else if expired {
+        vec![ApiError::Unauthorized.to_string()]
     } else {
         vec![]
     };
@@ -110,16 +193,16 @@ pub fn Login(props: &LoginProps) -> Html {
                         <form>
                             if props.mode == LoginMode::SignUp {
                                 <fieldset class="form-group">
-                                    <input ref={username_ref}  disabled={state.loading} class="form-control form-control-lg" type="text" placeholder="Your Name"/>
+                                    <input ref={username_ref} oninput={oninput.clone()} disabled={state.loading} class="form-control form-control-lg" type="text" placeholder="Your Name"/>
                                 </fieldset>
                             }
                             <fieldset class="form-group">
-                                <input ref={email_ref} disabled={state.loading} class="form-control form-control-lg" type="email" placeholder="Email"/>
+                                <input ref={email_ref} oninput={oninput.clone()} disabled={state.loading} class="form-control form-control-lg" type="email" placeholder="Email"/>
                             </fieldset>
                             <fieldset class="form-group">
-                                <input ref={password_ref} disabled={state.loading} class="form-control form-control-lg" type="password" placeholder="Password"/>
+                                <input ref={password_ref} {oninput} disabled={state.loading} class="form-control form-control-lg" type="password" placeholder="Password"/>
                             </fieldset>
-                            <button {onclick} disabled={state.loading} class="btn btn-lg btn-primary pull-xs-right">
+                            <button {onclick} disabled={state.loading || !*filled} class="btn btn-lg btn-primary pull-xs-right">
                                 {title}
                             </button>
                         </form>
@@ -1,19 +1,33 @@
+mod activity;
+mod analytics;
 mod api;
 mod article;
 mod auth;
+mod badges;
+mod counter;
+mod drafts;
 mod editor;
+mod export;
 mod feed;
 mod home;
+mod loading;
 mod login;
+mod markdown;
+mod preferences;
 mod profile;
+mod reading_list;
 mod route;
+mod search;
 mod setting;
+mod time;
+mod toc;
 
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 use crate::{
-    auth::{AuthContext, AuthProvider},
+    auth::{AuthContext, AuthProvider, SessionExpiryHandler},
+    preferences::PreferencesProvider,
     route::Route,
 };
 
@@ -26,9 +40,13 @@ fn main() {
 fn App() -> Html {
     html! {
         <AuthProvider>
-            <HashRouter>
-                <Switch<Route> render={switch}/>
-            </HashRouter>
+            <PreferencesProvider>
+                <HashRouter>
+                    <SessionExpiryHandler />
+                    <loading::LoadingIndicator />
+                    <Switch<Route> render={switch}/>
+                </HashRouter>
+            </PreferencesProvider>
         </AuthProvider>
     }
 }
@@ -43,11 +61,15 @@ fn switch(routes: Route) -> Html {
         Route::Editor { slug } => html! { <editor::Editor slug={Some(slug.clone())} /> },
         Route::Article { slug } => html! { <article::Article slug={slug.clone()} /> },
         Route::Profile { username } => html! { <profile::Profile username={username.clone()} /> },
+        Route::ReadingList => html! { <reading_list::ReadingList /> },
+        Route::Drafts => html! { <drafts::Drafts /> },
+        Route::Search { q } => html! { <search::Search q={q.clone()} /> },
         Route::NotFound => html! { <Redirect<Route> to={Route::Home} /> },
     };
 
     html! {
         <>
+            <analytics::PageView route={routes.clone()} />
             <Header route={routes} />
             {content}
             <Footer />
@@ -65,25 +87,78 @@ fn Header(props: &HeaderProps) -> Html {
     let HeaderProps { route } = props;
 
     let auth = use_context::<AuthContext>().unwrap();
+    let navigator = use_navigator().unwrap();
+
+    let badges = badges::use_badges().unwrap_or_default();
+
+    let search_ref = use_node_ref();
+
+    let onsubmit_search = {
+        let search_ref = search_ref.clone();
+        move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let q = search_ref
+                .cast::<web_sys::HtmlInputElement>()
+                .unwrap()
+                .value();
+            let q = q.trim();
+            if !q.is_empty() {
+                navigator.push(&Route::Search { q: q.to_string() });
+            }
+        }
+    };
+
+    let cur_q = match route {
+        Route::Search { q } => Some(q.clone()),
+        _ => None,
+    };
 
     html! {
         <nav class="navbar navbar-light">
             <div class="container">
                 <Link<Route> classes="navbar-brand" to={Route::Home}>{"conduit"}</Link<Route>>
 
+                <form class="form-inline pull-xs-left" onsubmit={onsubmit_search}>
+                    <input ref={search_ref}
+                        class="form-control form-control-sm"
+                        type="search"
+                        placeholder="Search articles"
+                        value={cur_q} />
+                </form>
+
                 <ul class="nav navbar-nav pull-xs-right">
                     <HeaderLink route={route.clone()} to={Route::Home}>
                         {"Home"}
                     </HeaderLink>
+                    <HeaderLink route={route.clone()} to={Route::ReadingList}>
+                        <i class="ion-bookmark"></i>
+                        {" Reading List"}
+                    </HeaderLink>
 
                     if let Some(user) = auth.user() {
                         <HeaderLink route={route.clone()} to={Route::NewArticle}>
                             <i class="ion-compose"></i>
                             {" New Article"}
                             </HeaderLink>
+                        if badges.drafts > 0 {
+                            <HeaderLink route={route.clone()} to={Route::Drafts}>
+                                <i class="ion-document"></i>
+                                {" Drafts "}
+                                <span class="tag-default tag-pill" title="Unpublished articles">
+                                    {badges.drafts}
+                                </span>
+                            </HeaderLink>
+                        }
                         <HeaderLink route={route.clone()} to={Route::Setting}>
                             <i class="ion-gear-a"></i>
                             {" Settings"}
+                            if badges.pending_email_changes > 0 {
+                                {" "}
+                                <span class="tag-default tag-pill" title="Email change awaiting confirmation">
+                                    {badges.pending_email_changes}
+                                </span>
+                            }
                         </HeaderLink>
                         <HeaderLink route={route.clone()} to={Route::Profile { username: user.username.clone() }}>
                             <img class="user-pic"
@@ -142,6 +217,7 @@ fn Footer() -> Html {
                     {"An interactive learning project from "}
                     <a href="https://thinkster.io">{"Thinkster"}</a>
                     {". Code & design licensed under MIT."}
+                    <analytics::DoNotTrackToggle />
                 </span>
             </div>
         </footer>
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+use web_sys::Element;
+use yew::prelude::*;
+
+/// Same rules as `slugify` in the backend's `toc.rs`, which computes the anchors the table of
+/// contents links to. The two have to stay in step or the links stop landing on their headings.
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Link targets allowed through, as in the backend's `markdown.rs`; anything else with a
+/// scheme (`javascript:`, `data:`, ...) is replaced. Relative links have no scheme and are kept.
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim();
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(i) if url[i..].starts_with(':') => SAFE_SCHEMES
+            .iter()
+            .any(|scheme| url[..i].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+fn sanitize_url(url: CowStr) -> CowStr {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("#")
+    }
+}
+
+/// Renders markdown to HTML that is safe to insert into the page, matching the server's
+/// `render_markdown`: raw HTML such as `<script>` is dropped, links may only use the
+/// [`SAFE_SCHEMES`], and headings get `id`s equal to their TOC anchors, numbering repeats
+/// `-1`, `-2`, ... in document order.
+pub fn render_markdown(markdown: &str) -> String {
+    let mut events = vec![];
+    let mut used = HashSet::new();
+    let mut heading: Option<(usize, String)> = None;
+
+    for event in Parser::new(markdown) {
+        let event = match event {
+            Event::Html(_) | Event::InlineHtml(_) => continue,
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: sanitize_url(dest_url),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: sanitize_url(dest_url),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Heading { .. }) => {
+                heading = Some((events.len(), String::new()));
+                event
+            }
+            Event::Text(ref text) | Event::Code(ref text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(text);
+                }
+                event
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((start, text)) = heading.take() {
+                    let base = slugify(&text);
+                    let mut anchor = base.clone();
+                    let mut n = 0;
+                    while !used.insert(anchor.clone()) {
+                        n += 1;
+                        anchor = format!("{base}-{n}");
+                    }
+
+                    if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+                        *id = Some(CowStr::from(anchor));
+                    }
+                }
+                event
+            }
+            event => event,
+        };
+        events.push(event);
+    }
+
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, events.into_iter());
+    html_output
+}
+
+#[derive(PartialEq, Properties)]
+pub struct RenderedHtmlProps {
+    /// Output of [`render_markdown`] or the server's equivalent; never unsanitized input.
+    pub html: AttrValue,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// Shows already rendered markdown.
+#[function_component]
+pub fn RenderedHtml(props: &RenderedHtmlProps) -> Html {
+    let RenderedHtmlProps { html, class } = props;
+
+    let node_ref = use_node_ref();
+
+    // Rendered after the `div` is mounted, so the ref is always attached by then.
+    use_effect_with(html.clone(), {
+        let node_ref = node_ref.clone();
+        move |html| {
+            if let Some(el) = node_ref.cast::<Element>() {
+                el.set_inner_html(html);
+            }
+        }
+    });
+
+    html! {
+        <div ref={node_ref} class={class.clone()}></div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_markdown;
+
+    /// The `id` attributes in `html`, in document order.
+    fn heading_ids(html: &str) -> Vec<&str> {
+        html.split(" id=\"")
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap())
+            .collect()
+    }
+
+    /// Same document and anchors as the backend's `rendered_heading_ids_match_toc_anchors`, so a
+    /// change to either `slugify` that the other doesn't follow fails one of the two.
+    #[test]
+    fn heading_ids_match_the_server_toc_anchors() {
+        let markdown = "# Intro\n\n## What's New in 2.0?\n\n### Intro\n\nText.\n\n## Intro\n\n\
+                        ## `code` & more\n\n## !!!\n";
+
+        assert_eq!(
+            heading_ids(&render_markdown(markdown)),
+            [
+                "intro",
+                "whats-new-in-20",
+                "intro-1",
+                "intro-2",
+                "code-more",
+                "section"
+            ]
+        );
+    }
+}
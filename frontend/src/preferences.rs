@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use yew::prelude::*;
+
+use crate::{
+    api::{ApiRequest, UserAuth},
+    auth::AuthContext,
+    feed::Density,
+};
+
+const PREFERENCES_KEY: &str = "preferences";
+
+/// Where these settings lived before they were kept together; only read until the first change.
+const LEGACY_DENSITY_KEY: &str = "feed_density";
+const LEGACY_DO_NOT_TRACK_KEY: &str = "do_not_track";
+
+/// UI settings kept in LocalStorage and, while someone is signed in, mirrored to
+/// `/api/user/preferences` so they follow the user to other browsers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Preferences {
+    pub density: Density,
+    pub do_not_track: bool,
+    /// When any setting last changed. On sign-in the copy with the later time wins as a whole.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Keys this client doesn't know about, kept so saving doesn't drop them.
+    #[serde(flatten)]
+    other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct PreferencesResp {
+    preferences: Preferences,
+}
+
+fn load_local() -> Preferences {
+    LocalStorage::get(PREFERENCES_KEY).unwrap_or_else(|_| Preferences {
+        density: LocalStorage::get(LEGACY_DENSITY_KEY).unwrap_or_default(),
+        do_not_track: LocalStorage::get(LEGACY_DO_NOT_TRACK_KEY).unwrap_or(false),
+        ..Default::default()
+    })
+}
+
+fn store_local(preferences: &Preferences) {
+    if let Err(err) = LocalStorage::set(PREFERENCES_KEY, preferences) {
+        log::error!("failed to store preferences: {err:?}");
+    }
+}
+
+async fn store_remote(user: &UserAuth, preferences: &Preferences) {
+    let result = ApiRequest::put("/api/user/preferences")
+        .auth(Some(user))
+        .json(&json!({ "preferences": preferences }))
+        .json_response::<PreferencesResp>()
+        .await;
+
+    if let Err(err) = result {
+        log::warn!("failed to save preferences: {err:?}");
+    }
+}
+
+/// Picks the copy changed last (never changed sorts first) and says whether the server needs
+/// the result. Keys only the server knows survive a local win.
+fn merge(local: Preferences, server: Preferences) -> (Preferences, bool) {
+    if server.updated_at > local.updated_at {
+        return (server, false);
+    }
+    if server.updated_at == local.updated_at {
+        return (local, false);
+    }
+
+    let mut merged = local;
+    for (key, value) in server.other {
+        merged.other.entry(key).or_insert(value);
+    }
+    (merged, true)
+}
+
+#[derive(Clone, PartialEq)]
+pub struct PreferencesContext {
+    pub current: Preferences,
+    /// Stores the given preferences locally and, when signed in, on the server.
+    pub set: Callback<Preferences>,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct PreferencesProviderProps {
+    pub children: Children,
+}
+
+/// Must sit inside `AuthProvider`; reconciles with the server each time someone signs in.
+#[function_component]
+pub fn PreferencesProvider(props: &PreferencesProviderProps) -> Html {
+    let auth = use_context::<AuthContext>().unwrap();
+
+    let preferences = use_state_eq(load_local);
+
+    use_effect_with(auth.user().map(|user| user.username.clone()), {
+        let user = auth.user().cloned();
+        let preferences = preferences.clone();
+        move |_| {
+            if let Some(user) = user {
+                wasm_bindgen_futures::spawn_local(async move {
+                    let server = match ApiRequest::get("/api/user/preferences")
+                        .auth(Some(&user))
+                        .json_response::<PreferencesResp>()
+                        .await
+                    {
+                        Ok(resp) => resp.preferences,
+                        Err(err) => {
+                            log::warn!("failed to load preferences: {err:?}");
+                            return;
+                        }
+                    };
+
+                    // Read again, the user may have changed something while the request was out.
+                    let (merged, push) = merge(load_local(), server);
+                    store_local(&merged);
+                    preferences.set(merged.clone());
+                    if push {
+                        store_remote(&user, &merged).await;
+                    }
+                });
+            }
+        }
+    });
+
+    let set = use_callback(auth.user().cloned(), {
+        let preferences = preferences.clone();
+        move |mut value: Preferences, user: &Option<UserAuth>| {
+            value.updated_at = Some(Utc::now());
+            store_local(&value);
+            preferences.set(value.clone());
+
+            if let Some(user) = user.clone() {
+                wasm_bindgen_futures::spawn_local(async move {
+                    store_remote(&user, &value).await;
+                });
+            }
+        }
+    });
+
+    let context = PreferencesContext {
+        current: (*preferences).clone(),
+        set,
+    };
+
+    html! {
+        <ContextProvider<PreferencesContext> {context}>
+            { for props.children.iter() }
+        </ContextProvider<PreferencesContext>>
+    }
+}
+
+#[hook]
+pub fn use_preferences() -> PreferencesContext {
+    use_context::<PreferencesContext>().unwrap()
+}
@@ -5,8 +5,9 @@ use yew_hooks::prelude::*;
 use yew_router::prelude::use_navigator;
 
 use crate::{
+    activity::ActivityTimeline,
     api::{ApiError, ApiRequest, UserProfile, UserProfileResp},
-    feed::{Feed, FeedTab, FeedType, Tab},
+    feed::{Feed, FeedSource, FeedTab, FeedType, Tab},
     route::Route,
 };
 
@@ -38,15 +39,19 @@ pub fn Profile(props: &ProfileProps) -> Html {
     };
 
     let cur_tab = use_state(|| FeedType::User(username.clone()));
+    // The activity tab isn't a feed of articles, so it sits beside the feed tabs.
+    let show_activity = use_state_eq(|| false);
 
     use_effect_with(
         (username.clone(), auth.clone()),
         {
             let reload_profile = reload_profile.clone();
             let cur_tab = cur_tab.clone();
+            let show_activity = show_activity.clone();
             let username = username.clone();
             move |_| {
                 cur_tab.set(FeedType::User(username));
+                show_activity.set(false);
                 reload_profile.run();
             }
         }
@@ -63,6 +68,14 @@ pub fn Profile(props: &ProfileProps) -> Html {
         },
     ];
 
+    let own_profile = auth.user().map_or(false, |u| &u.username == username);
+    let empty_message = match (&*cur_tab, own_profile) {
+        (FeedType::Favorited(_), true) => "No favorited articles yet.".to_string(),
+        (FeedType::Favorited(_), false) => format!("{username} has no favorited articles yet."),
+        (_, true) => "You haven't written any articles yet.".to_string(),
+        (_, false) => format!("{username} hasn't written any articles yet."),
+    };
+
     html! {
         <div class="profile-page">
             <div class="user-info">
@@ -77,10 +90,30 @@ pub fn Profile(props: &ProfileProps) -> Html {
                 <div class="row">
                     <div class="col-xs-12 col-md-10 offset-md-1">
                         <div class="articles-toggle">
-                            <FeedTab {tabs} cur_tab={(*cur_tab).clone()}
-                                onclick={let cur_tab = cur_tab.clone(); move |tab| cur_tab.set(tab)} />
+                            <FeedTab {tabs} cur_tab={(*cur_tab).clone()} active={!*show_activity}
+                                onclick={
+                                    let cur_tab = cur_tab.clone();
+                                    let show_activity = show_activity.clone();
+                                    move |tab| {
+                                        cur_tab.set(tab);
+                                        show_activity.set(false);
+                                    }
+                                }>
+                                if own_profile {
+                                    <li class="nav-item">
+                                        <a class={classes!("nav-link", show_activity.then_some("active"))}
+                                            onclick={let show_activity = show_activity.clone(); move |_| show_activity.set(true)}
+                                            href="javascript:void(0);"
+                                        >{"Activity"}</a>
+                                    </li>
+                                }
+                            </FeedTab>
                         </div>
-                        <Feed feed_type={(*cur_tab).clone()} limit=5 />
+                        if own_profile && *show_activity {
+                            <ActivityTimeline limit=10 />
+                        } else {
+                            <Feed source={FeedSource::from((*cur_tab).clone())} limit=5 empty_message={AttrValue::from(empty_message)} />
+                        }
                     </div>
                 </div>
             </div>
@@ -115,24 +148,63 @@ fn ProfileHeader(props: &ProfileHeaderProps) -> Html {
         .unwrap_or("")
         .to_string();
 
+    let stats = profile.as_ref().and_then(|p| {
+        Some(format!(
+            "{} articles \u{b7} {} followers",
+            p.articles_count?, p.followers_count?,
+        ))
+    });
+
     let following = use_state_eq(|| false);
 
-    following.set(profile.as_ref().map_or(false, |p| p.following));
+    use_effect_with(profile.clone(), {
+        let following = following.clone();
+        move |profile| following.set(profile.as_ref().map_or(false, |p| p.following))
+    });
 
+    // The button flips immediately; the server response then reconciles `profile`
+    // (and through the effect above, `following`), or the flip is rolled back on error.
     let follow = use_async({
         let auth = auth.clone();
         let navigator = navigator.clone();
+        let following = following.clone();
         async move {
             if let Some(p) = profile.as_ref() {
                 if auth.is_authorized() {
-                    let req = if p.following {
+                    let was_following = *following;
+                    following.set(!was_following);
+
+                    let req = if was_following {
                         ApiRequest::delete(format!("/api/profiles/{}/follow", p.username))
                     } else {
                         ApiRequest::post(format!("/api/profiles/{}/follow", p.username))
                     };
 
-                    let prof: UserProfileResp = req.auth(auth.user()).json_response().await?;
-                    profile.set(Some(prof.profile));
+                    match req
+                        .auth(auth.user())
+                        .json_response::<UserProfileResp>()
+                        .await
+                    {
+                        Ok(prof) => {
+                            // Follow responses leave out the counts; keep ours, adjusted.
+                            let followers_count = p.followers_count.map(|count| {
+                                if was_following {
+                                    count.saturating_sub(1)
+                                } else {
+                                    count + 1
+                                }
+                            });
+                            profile.set(Some(UserProfile {
+                                articles_count: p.articles_count,
+                                followers_count,
+                                ..prof.profile
+                            }));
+                        }
+                        Err(err) => {
+                            following.set(was_following);
+                            return Err(Rc::new(err));
+                        }
+                    }
                 } else {
                     navigator.push(&Route::Register);
                 }
@@ -142,11 +214,16 @@ fn ProfileHeader(props: &ProfileHeaderProps) -> Html {
         }
     });
 
+    let follow_loading = follow.loading;
+
     html! {
         <div class="col-xs-12 col-md-10 offset-md-1">
             <img src={image} class="user-img" />
             <h4>{&username}</h4>
             <p>{bio}</p>
+            if let Some(stats) = stats {
+                <p class="profile-stats">{stats}</p>
+            }
             if auth.user().map_or(false, |u| u.username == username) {
                 <button onclick={ move |_| navigator.push(&Route::Setting) }
                     class="btn btn-sm btn-outline-secondary action-btn">
@@ -155,12 +232,12 @@ fn ProfileHeader(props: &ProfileHeaderProps) -> Html {
                     { "  Edit Profile Settings" }
                 </button>
             } else if *following {
-                <button onclick={ move |_| follow.run() } class="btn btn-sm btn-secondary action-btn">
+                <button onclick={ move |_| follow.run() } disabled={follow_loading} class="btn btn-sm btn-secondary action-btn">
                     <i class="ion-plus-round"></i>
                     { format!("  Unfollow {username}") }
                 </button>
             } else {
-                <button onclick={ move |_| follow.run() } class="btn btn-sm btn-outline-secondary action-btn">
+                <button onclick={ move |_| follow.run() } disabled={follow_loading} class="btn btn-sm btn-outline-secondary action-btn">
                     <i class="ion-plus-round"></i>
                     { format!("  Follow {username}") }
                 </button>
@@ -0,0 +1,195 @@
+use std::{collections::HashSet, rc::Rc};
+
+use chrono::{DateTime, Local};
+use gloo_storage::{LocalStorage, Storage};
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+use yew_router::prelude::*;
+
+use crate::{
+    api::{ApiError, ApiRequest, Article, ArticleResp},
+    auth::AuthContext,
+    route::Route,
+};
+
+const READING_LIST_KEY: &str = "reading_list";
+
+/// Slugs saved for later, oldest first. Lives only in this browser's LocalStorage.
+pub fn saved_slugs() -> Vec<String> {
+    let mut slugs = LocalStorage::get::<Vec<String>>(READING_LIST_KEY).unwrap_or_default();
+    let mut seen = HashSet::new();
+    slugs.retain(|slug| seen.insert(slug.clone()));
+    slugs
+}
+
+pub fn is_saved(slug: &str) -> bool {
+    saved_slugs().iter().any(|s| s == slug)
+}
+
+/// Adds or removes `slug`, returning whether it is saved afterwards.
+pub fn toggle_saved(slug: &str) -> bool {
+    let mut slugs = saved_slugs();
+    let saved = if let Some(pos) = slugs.iter().position(|s| s == slug) {
+        slugs.remove(pos);
+        false
+    } else {
+        slugs.push(slug.to_string());
+        true
+    };
+
+    if let Err(err) = LocalStorage::set(READING_LIST_KEY, &slugs) {
+        log::error!("failed to store reading list: {err:?}");
+    }
+
+    saved
+}
+
+#[derive(PartialEq, Properties)]
+pub struct SaveForLaterButtonProps {
+    pub slug: String,
+    #[prop_or_default]
+    pub classes: Classes,
+}
+
+#[function_component]
+pub fn SaveForLaterButton(props: &SaveForLaterButtonProps) -> Html {
+    let SaveForLaterButtonProps { slug, classes } = props;
+
+    let saved = use_state_eq(|| is_saved(slug));
+
+    use_effect_with(slug.clone(), {
+        let saved = saved.clone();
+        move |slug| saved.set(is_saved(slug))
+    });
+
+    let onclick = {
+        let saved = saved.clone();
+        let slug = slug.clone();
+        move |_| saved.set(toggle_saved(&slug))
+    };
+
+    let btn_outline = if *saved {
+        "btn-secondary"
+    } else {
+        "btn-outline-secondary"
+    };
+
+    html! {
+        <button {onclick} type="button" class={classes!("btn", "btn-sm", btn_outline, classes.clone())}>
+            <i class="ion-bookmark"></i>
+            { if *saved { " Saved" } else { " Save for later" } }
+        </button>
+    }
+}
+
+#[function_component]
+pub fn ReadingList() -> Html {
+    let auth = use_context::<AuthContext>().unwrap();
+
+    let slugs = use_state(saved_slugs);
+
+    let articles = {
+        let auth = auth.clone();
+        let slugs = slugs.clone();
+        use_async(async move {
+            let mut articles = vec![];
+
+            for slug in slugs.iter() {
+                // Articles deleted since they were saved just drop out of the list.
+                match ApiRequest::get(format!("/api/articles/{slug}"))
+                    .auth(auth.user())
+                    .json_response::<ArticleResp>()
+                    .await
+                {
+                    Ok(resp) => articles.push(resp.article),
+                    Err(err) => log::warn!("skipping saved article {slug}: {err:?}"),
+                }
+            }
+
+            Ok::<_, Rc<ApiError>>(articles)
+        })
+    };
+
+    use_effect_with(((*slugs).clone(), auth.is_loading()), {
+        let articles = articles.clone();
+        move |_| articles.run()
+    });
+
+    let on_remove = {
+        let slugs = slugs.clone();
+        Callback::from(move |slug: String| {
+            toggle_saved(&slug);
+            slugs.set(saved_slugs());
+        })
+    };
+
+    let content = if slugs.is_empty() {
+        html! { <div class="article-preview">{"Nothing saved for later yet."}</div> }
+    } else if let Some(articles) = articles.data.as_ref() {
+        html! {
+            <>
+            {
+                for articles.iter().map(|article| html! {
+                    <ReadingListItem article={article.clone()} on_remove={on_remove.clone()} />
+                })
+            }
+            </>
+        }
+    } else {
+        html! { <div class="article-preview">{"Loading articles..."}</div> }
+    };
+
+    html! {
+        <div class="container page">
+            <div class="row">
+                <div class="col-md-10 offset-md-1 col-xs-12">
+                    <h1>{"Reading List"}</h1>
+                    {content}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+struct ReadingListItemProps {
+    article: Article,
+    on_remove: Callback<String>,
+}
+
+#[function_component]
+fn ReadingListItem(props: &ReadingListItemProps) -> Html {
+    let ReadingListItemProps { article, on_remove } = props;
+
+    let date = DateTime::<Local>::from(article.created_at).format("%B %e, %Y").to_string();
+
+    let onclick = {
+        let on_remove = on_remove.clone();
+        let slug = article.slug.clone();
+        move |_| on_remove.emit(slug.clone())
+    };
+
+    html! {
+        <div class="article-preview">
+            <div class="article-meta">
+                <Link<Route> to={Route::Profile{ username: article.author.username.clone() }}>
+                    <img src={article.author.image().to_string()}/>
+                </Link<Route>>
+                <div class="info">
+                    <Link<Route> to={Route::Profile{ username: article.author.username.clone() }} classes="author">
+                        {&article.author.username}
+                    </Link<Route>>
+                    <span class="date">{date}</span>
+                </div>
+                <button {onclick} class="btn btn-sm btn-outline-danger pull-xs-right">
+                    <i class="ion-close-round"></i>{" Remove"}
+                </button>
+            </div>
+            <Link<Route> to={Route::Article { slug: article.slug.clone() }} classes="preview-link">
+                <h1>{&article.title}</h1>
+                <p>{&article.description}</p>
+                <span>{"Read more..."}</span>
+            </Link<Route>>
+        </div>
+    }
+}
@@ -18,6 +18,12 @@ pub enum Route {
     Article { slug: String },
     #[at("/profile/:username")]
     Profile { username: String },
+    #[at("/reading-list")]
+    ReadingList,
+    #[at("/drafts")]
+    Drafts,
+    #[at("/search/:q")]
+    Search { q: String },
     #[not_found]
     #[at("/404")]
     NotFound,
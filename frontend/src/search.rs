@@ -0,0 +1,24 @@
+use yew::prelude::*;
+
+use crate::feed::{Feed, FeedSource, FeedType};
+
+#[derive(PartialEq, Properties)]
+pub struct SearchProps {
+    pub q: String,
+}
+
+#[function_component]
+pub fn Search(props: &SearchProps) -> Html {
+    let SearchProps { q } = props;
+
+    html! {
+        <div class="container page">
+            <div class="row">
+                <div class="col-md-10 offset-md-1 col-xs-12">
+                    <h1>{format!("Results for \u{201c}{q}\u{201d}")}</h1>
+                    <Feed source={FeedSource::from(FeedType::Search(q.clone()))} limit=10 />
+                </div>
+            </div>
+        </div>
+    }
+}
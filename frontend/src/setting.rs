@@ -7,8 +7,9 @@ use yew_hooks::use_async;
 use yew_router::prelude::*;
 
 use crate::{
-    api::{ApiError, ApiRequest, UserAuthResp},
+    api::{ApiError, ApiRequest, UserAuthResp, BIO_MAX_LENGTH},
     auth::{Auth, AuthContext},
+    counter::{enforce_max_length, CharCounter},
     route::Route,
 };
 
@@ -26,6 +27,8 @@ pub fn Setting() -> Html {
     let username = use_state_eq(|| "".to_string());
     let bio = use_state_eq(|| "".to_string());
     let email = use_state_eq(|| "".to_string());
+    let bio_len = use_state_eq(|| None::<usize>);
+    let bio_truncated = use_state_eq(|| false);
 
     let auth = auth.clone();
     let email_ref = email_ref.clone();
@@ -85,6 +88,17 @@ pub fn Setting() -> Html {
         move |_| update.run()
     };
 
+    let on_bio_input = {
+        let bio_len = bio_len.clone();
+        let bio_truncated = bio_truncated.clone();
+        Callback::from(move |e: InputEvent| {
+            let el: HtmlTextAreaElement = e.target_unchecked_into();
+            let (len, truncated) = enforce_max_length(&el, BIO_MAX_LENGTH);
+            bio_len.set(Some(len));
+            bio_truncated.set(truncated);
+        })
+    };
+
     let onclick_logout = {
         let auth = auth.clone();
         Callback::from(move |_| {
@@ -123,8 +137,14 @@ pub fn Setting() -> Html {
                     rows="8"
                     placeholder="Short bio about you"
                     value={(*bio).clone()}
+                    oninput={on_bio_input}
                     disabled={update.loading}
                 ></textarea>
+                <CharCounter
+                    len={bio_len.unwrap_or_else(|| bio.chars().count())}
+                    max={BIO_MAX_LENGTH}
+                    truncated={*bio_truncated}
+                />
             </fieldset>
 
             <fieldset class="form-group">
@@ -0,0 +1,133 @@
+use std::rc::Rc;
+
+use web_sys::{
+    js_sys,
+    wasm_bindgen::{closure::Closure, JsCast},
+    IntersectionObserver, IntersectionObserverEntry, IntersectionObserverInit, ScrollBehavior,
+    ScrollIntoViewOptions,
+};
+use yew::prelude::*;
+
+use crate::api::TocEntry;
+
+/// The TOC is only worth the space once there is more than one section to jump between.
+pub fn worth_showing(entries: &[TocEntry]) -> bool {
+    entries.len() > 1
+}
+
+/// Reports the heading closest to the top of the viewport until dropped.
+pub struct ScrollSpy {
+    observer: IntersectionObserver,
+    _callback: Closure<dyn FnMut(js_sys::Array)>,
+}
+
+impl Drop for ScrollSpy {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
+/// Watches the headings `entries` link to, calling `on_active` with the anchor of each heading
+/// that scrolls into the top part of the viewport. The body must already be in the document,
+/// rendered with [`render_markdown`](crate::markdown::render_markdown) so the ids exist.
+pub fn watch(entries: &[TocEntry], on_active: Callback<String>) -> Option<ScrollSpy> {
+    let callback = Closure::<dyn FnMut(js_sys::Array)>::new(move |observed: js_sys::Array| {
+        let visible = observed
+            .iter()
+            .map(|entry| entry.unchecked_into::<IntersectionObserverEntry>())
+            .find(|entry| entry.is_intersecting());
+        if let Some(entry) = visible {
+            on_active.emit(entry.target().id());
+        }
+    });
+
+    // Only the top 30% of the viewport counts, so a heading becomes current once it has been
+    // scrolled up to where reading happens rather than as soon as it peeks in at the bottom.
+    let mut options = IntersectionObserverInit::new();
+    options.root_margin("0px 0px -70% 0px");
+    let observer =
+        IntersectionObserver::new_with_options(callback.as_ref().unchecked_ref(), &options)
+            .map_err(|err| log::warn!("scroll spy unavailable: {err:?}"))
+            .ok()?;
+
+    let document = web_sys::window()?.document()?;
+    for entry in entries {
+        if let Some(heading) = document.get_element_by_id(&entry.anchor) {
+            observer.observe(&heading);
+        }
+    }
+
+    Some(ScrollSpy {
+        observer,
+        _callback: callback,
+    })
+}
+
+fn scroll_to(anchor: &str) {
+    let heading = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(anchor));
+    if let Some(heading) = heading {
+        let mut options = ScrollIntoViewOptions::new();
+        options.behavior(ScrollBehavior::Smooth);
+        heading.scroll_into_view_with_scroll_into_view_options(&options);
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct TableOfContentsProps {
+    pub entries: Rc<Vec<TocEntry>>,
+    /// Anchor of the section currently being read.
+    #[prop_or_default]
+    pub active: Option<String>,
+}
+
+/// Sticky sidebar next to the article body. On narrow screens it sits above the body instead
+/// and starts collapsed behind its title.
+#[function_component]
+pub fn TableOfContents(props: &TableOfContentsProps) -> Html {
+    let TableOfContentsProps { entries, active } = props;
+
+    let collapsed = use_state_eq(|| true);
+
+    let on_toggle = {
+        let collapsed = collapsed.clone();
+        Callback::from(move |_| collapsed.set(!*collapsed))
+    };
+
+    // Indent relative to the outermost heading used, articles often start at `##`.
+    let top_level = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+
+    html! {
+        <nav class={classes!("article-toc", collapsed.then_some("collapsed"))}>
+            <button onclick={on_toggle} type="button" class="article-toc-title">
+                {"Contents"}
+            </button>
+            <ul>
+            {
+                for entries.iter().map(|entry| {
+                    let onclick = {
+                        let anchor = entry.anchor.clone();
+                        let collapsed = collapsed.clone();
+                        Callback::from(move |e: MouseEvent| {
+                            e.prevent_default();
+                            scroll_to(&anchor);
+                            collapsed.set(true);
+                        })
+                    };
+                    let indent = format!("padding-left: {}rem", f32::from(entry.level - top_level) * 0.75);
+                    let is_active = active.as_ref() == Some(&entry.anchor);
+
+                    html! {
+                        <li style={indent}>
+                            <a {onclick} href={format!("#{}", entry.anchor)} class={classes!(is_active.then_some("active"))}>
+                                {&entry.text}
+                            </a>
+                        </li>
+                    }
+                })
+            }
+            </ul>
+        </nav>
+    }
+}